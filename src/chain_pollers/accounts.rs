@@ -0,0 +1,119 @@
+use crate::chain_pollers::persistence::{AccountUpdate, AccountUpdateSource};
+use crate::clients::solana::{AccountFilter, SolanaAccount};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Latest known state of the accounts owned by interesting programs, with
+/// enough history to undo updates from orphaned slots.
+///
+/// Attach it with `SolanaChainPoller::set_account_tracker`. The poller
+/// snapshots every program's accounts with `getProgramAccounts` when it
+/// starts and whenever it (re)acquires its lease, and after each slot
+/// refetches the accounts passed to interesting programs by successful
+/// transactions. Accounts not matching the program's filters are ignored.
+/// Orphaned slots restore each account's previous state.
+#[derive(Default)]
+pub struct AccountTracker {
+    filters: HashMap<String, Vec<AccountFilter>>,
+    history: Mutex<HashMap<String, Vec<AccountUpdate>>>,
+}
+
+impl AccountTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only track accounts of `program_id` matching all `filters`.
+    pub fn set_filters(&mut self, program_id: &str, filters: Vec<AccountFilter>) {
+        self.filters.insert(program_id.to_string(), filters);
+    }
+
+    pub fn filters(&self, program_id: &str) -> &[AccountFilter] {
+        self.filters.get(program_id).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn matches_filters(&self, program_id: &str, account: &SolanaAccount) -> bool {
+        self.filters(program_id)
+            .iter()
+            .all(|filter| filter.matches(&account.data))
+    }
+
+    /// Latest update recorded for `pubkey`.
+    pub fn latest(&self, pubkey: &str) -> Option<AccountUpdate> {
+        self.history
+            .lock()
+            .expect("account tracker lock poisoned")
+            .get(pubkey)
+            .and_then(|versions| versions.last())
+            .cloned()
+    }
+
+    /// Whether `pubkey` currently exists and is tracked.
+    pub fn is_tracked(&self, pubkey: &str) -> bool {
+        self.latest(pubkey).is_some_and(|u| u.account.is_some())
+    }
+
+    /// Records `update` if it changes the account's owner, lamports or data,
+    /// returning whether it did. Versions older than `keep_from_slot` are
+    /// pruned, except the newest of them, which rollbacks restore.
+    pub fn record(&self, update: &AccountUpdate, keep_from_slot: u64) -> bool {
+        let mut history = self.history.lock().expect("account tracker lock poisoned");
+        let versions = history.entry(update.pubkey.clone()).or_default();
+
+        let unchanged = match versions.last() {
+            Some(latest) => same_state(latest.account.as_ref(), update.account.as_ref()),
+            None => update.account.is_none(),
+        };
+        if unchanged {
+            return false;
+        }
+
+        versions.push(update.clone());
+        let stale = versions
+            .iter()
+            .take_while(|v| v.slot < keep_from_slot)
+            .count();
+        if stale > 1 {
+            versions.drain(..stale - 1);
+        }
+        true
+    }
+
+    /// Drops every version recorded at or after `orphaned_slot` and returns
+    /// the restored state of each affected account, as updates to deliver.
+    pub fn rollback(&self, orphaned_slot: u64) -> Vec<AccountUpdate> {
+        let mut history = self.history.lock().expect("account tracker lock poisoned");
+        let mut restored = Vec::new();
+
+        history.retain(|pubkey, versions| {
+            let Some(first_orphaned) = versions.iter().position(|v| v.slot >= orphaned_slot) else {
+                return true;
+            };
+            let program_id = versions[first_orphaned].program_id.clone();
+            versions.truncate(first_orphaned);
+
+            let previous = versions.last();
+            restored.push(AccountUpdate {
+                pubkey: pubkey.clone(),
+                program_id,
+                slot: previous.map_or(orphaned_slot, |p| p.slot),
+                context_slot: previous.map_or(orphaned_slot, |p| p.context_slot),
+                account: previous.and_then(|p| p.account.clone()),
+                decoded: previous.and_then(|p| p.decoded.clone()),
+                source: AccountUpdateSource::Rollback { orphaned_slot },
+            });
+            !versions.is_empty()
+        });
+
+        restored.sort_by(|a, b| a.pubkey.cmp(&b.pubkey));
+        restored
+    }
+}
+
+fn same_state(a: Option<&SolanaAccount>, b: Option<&SolanaAccount>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.owner == b.owner && a.lamports == b.lamports && a.data == b.data,
+        (None, None) => true,
+        _ => false,
+    }
+}
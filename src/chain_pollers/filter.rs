@@ -0,0 +1,113 @@
+use crate::clients::solana::SolanaTransaction;
+
+/// Selects the transactions `SolanaChainPoller` indexes, in place of the
+/// default "invokes an interesting program" rule. Criteria compose with
+/// `And`, `Or` and `Not`.
+///
+/// Only matching transactions reach the `SlotHandler` hooks, the log parser,
+/// the event store and subscribers. Logs and instructions are still only
+/// fetched and decoded for `interesting_programs`, so a filter that selects
+/// transactions by signer alone yields transaction-level hooks and balance
+/// changes, but no logs of other programs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionFilter {
+    /// Invokes, at any depth, at least one of the programs.
+    AnyProgram(Vec<String>),
+    /// Invokes every one of the programs.
+    AllPrograms(Vec<String>),
+    /// Lists at least one of the accounts among its account keys, including
+    /// addresses loaded from lookup tables.
+    MentionsAccount(Vec<String>),
+    /// Is signed by at least one of the accounts.
+    SignedBy(Vec<String>),
+    /// Succeeded.
+    Success,
+    /// Paid at least this many lamports in fees.
+    MinFee(u64),
+    /// Holds a token balance of at least one of the mints before or after
+    /// executing.
+    TokenMint(Vec<String>),
+    And(Vec<TransactionFilter>),
+    Or(Vec<TransactionFilter>),
+    Not(Box<TransactionFilter>),
+}
+
+impl TransactionFilter {
+    pub fn matches(&self, transaction: &SolanaTransaction) -> bool {
+        match self {
+            TransactionFilter::AnyProgram(programs) => {
+                programs.iter().any(|p| transaction.program_ids.contains(p))
+            }
+            TransactionFilter::AllPrograms(programs) => {
+                programs.iter().all(|p| transaction.program_ids.contains(p))
+            }
+            TransactionFilter::MentionsAccount(accounts) => {
+                transaction.account_keys.iter().any(|k| accounts.contains(k))
+            }
+            TransactionFilter::SignedBy(signers) => transaction
+                .account_keys
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| transaction.is_signer(*i))
+                .any(|(_, k)| signers.contains(k)),
+            TransactionFilter::Success => transaction.success,
+            TransactionFilter::MinFee(fee) => transaction.fee >= *fee,
+            TransactionFilter::TokenMint(mints) => transaction
+                .pre_token_balances
+                .iter()
+                .chain(&transaction.post_token_balances)
+                .any(|b| mints.contains(&b.mint)),
+            TransactionFilter::And(filters) => filters.iter().all(|f| f.matches(transaction)),
+            TransactionFilter::Or(filters) => filters.iter().any(|f| f.matches(transaction)),
+            TransactionFilter::Not(filter) => !filter.matches(transaction),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::solana::SolanaTokenBalance;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn criteria_compose() {
+        let transaction = SolanaTransaction {
+            signature: "sig".to_string(),
+            fee: 5000,
+            success: true,
+            account_keys: strings(&["Payer", "Cosigner", "Vault", "Prog", "Token"]),
+            signer_flags: vec![true, true, false, false, false],
+            program_ids: strings(&["Prog", "Token"]),
+            post_token_balances: vec![SolanaTokenBalance {
+                account_index: 2,
+                mint: "Mint".to_string(),
+                owner: None,
+                program_id: None,
+                amount: 1,
+                decimals: 0,
+            }],
+            ..Default::default()
+        };
+
+        let matches = |filter: TransactionFilter| filter.matches(&transaction);
+        assert!(matches(TransactionFilter::AnyProgram(strings(&["Other", "Prog"]))));
+        assert!(!matches(TransactionFilter::AllPrograms(strings(&["Other", "Prog"]))));
+        assert!(matches(TransactionFilter::MentionsAccount(strings(&["Vault"]))));
+        assert!(matches(TransactionFilter::SignedBy(strings(&["Cosigner"]))));
+        assert!(!matches(TransactionFilter::SignedBy(strings(&["Vault"]))));
+        assert!(matches(TransactionFilter::TokenMint(strings(&["Mint"]))));
+        assert!(!matches(TransactionFilter::MinFee(10_000)));
+        assert!(matches(TransactionFilter::And(vec![
+            TransactionFilter::Success,
+            TransactionFilter::Or(vec![
+                TransactionFilter::MinFee(10_000),
+                TransactionFilter::SignedBy(strings(&["Payer"])),
+            ]),
+            TransactionFilter::Not(Box::new(TransactionFilter::AnyProgram(strings(&["Other"])))),
+        ])));
+    }
+}
@@ -1,6 +1,12 @@
+pub mod accounts;
+pub mod filter;
 pub mod persistence;
 pub mod solana;
+pub mod stream;
 
+pub use accounts::*;
+pub use filter::*;
 pub use persistence::*;
 pub use solana::*;
+pub use stream::*;
 
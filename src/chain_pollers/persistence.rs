@@ -1,9 +1,18 @@
+use crate::clients::solana::{
+    BalanceChange, SolanaAccount, SolanaProgramLog, SolanaSlot, SolanaTransaction,
+};
 use crate::config::ChainId;
-use crate::transaction_log_parser::DecodedLog;
+use crate::transaction_log_parser::{ComputeUnits, DecodedLog, ReturnData};
+use crate::transaction_log_parser::{InstructionPosition, InstructionWithContext};
 use async_trait::async_trait;
+use solana_sdk::transaction::TransactionError;
 use thiserror::Error;
 
+#[cfg(any(test, feature = "testing"))]
+pub mod conformance;
 pub mod memory;
+#[cfg(feature = "sql")]
+pub mod sql;
 
 #[derive(Debug, Clone)]
 pub struct SlotRecord {
@@ -17,8 +26,188 @@ pub struct SlotRecord {
 #[derive(Debug, Clone)]
 pub struct LogWithSlot {
     pub log: DecodedLog,
-    pub raw_log: crate::clients::solana::SolanaProgramLog,
-    pub slot: crate::clients::solana::SolanaSlot,
+    pub raw_log: SolanaProgramLog,
+    pub slot: SolanaSlot,
+    /// Set when `raw_log` is a `Program <id> consumed N of M compute units` line.
+    pub compute_units: Option<ComputeUnits>,
+    /// Set when `raw_log` is a `Program return: ` line.
+    pub return_data: Option<ReturnData>,
+    /// Whether the emitting transaction succeeded. Logs of failed
+    /// transactions describe state changes that were rolled back.
+    pub success: bool,
+    pub error: Option<TransactionError>,
+}
+
+/// What produced an `AccountUpdate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountUpdateSource {
+    /// The `getProgramAccounts` snapshot taken when the poller starts.
+    Snapshot,
+    /// Refetched after the transaction that last touched it in `slot`.
+    Transaction { signature: String },
+    /// Restored to its version before `orphaned_slot`, which a reorg removed.
+    Rollback { orphaned_slot: u64 },
+    /// Refetched when replaying a dead letter.
+    Replay,
+}
+
+/// New state of an account owned by an interesting program.
+#[derive(Debug, Clone)]
+pub struct AccountUpdate {
+    pub pubkey: String,
+    pub program_id: String,
+    /// Slot the update belongs to: the slot that wrote the account, or the
+    /// snapshot's slot. For rollbacks, the slot of the restored version.
+    pub slot: u64,
+    /// Slot of the RPC node that served the data, which may be later than
+    /// `slot` if the account was written again since.
+    pub context_slot: u64,
+    /// `None` when the account was closed or is no longer owned by
+    /// `program_id`.
+    pub account: Option<SolanaAccount>,
+    /// Account type and value decoded by the poller's `LogParser`.
+    pub decoded: Option<(String, serde_json::Value)>,
+    pub source: AccountUpdateSource,
+}
+
+#[derive(Debug, Clone)]
+pub struct EventRecord {
+    pub chain_id: ChainId,
+    pub slot: u64,
+    pub blockhash: String,
+    pub signature: String,
+    pub log_index: u64,
+    pub program_id: String,
+    pub log: DecodedLog,
+    pub raw_log: SolanaProgramLog,
+}
+
+impl EventRecord {
+    pub fn from_log_with_slot(log_with_slot: &LogWithSlot) -> Self {
+        Self {
+            chain_id: log_with_slot.slot.chain_id,
+            slot: log_with_slot.slot.slot,
+            blockhash: log_with_slot.slot.blockhash.clone(),
+            signature: log_with_slot.raw_log.signature.clone(),
+            log_index: log_with_slot.raw_log.log_index,
+            program_id: log_with_slot.raw_log.program_id.clone(),
+            log: log_with_slot.log.clone(),
+            raw_log: log_with_slot.raw_log.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EventQuery {
+    pub program_id: Option<String>,
+    pub event_name: Option<String>,
+    pub signature: Option<String>,
+    pub from_slot: Option<u64>,
+    pub to_slot: Option<u64>,
+}
+
+impl EventQuery {
+    pub fn matches(&self, record: &EventRecord) -> bool {
+        if let Some(program_id) = &self.program_id {
+            if &record.program_id != program_id {
+                return false;
+            }
+        }
+        if let Some(event_name) = &self.event_name {
+            if &record.log.event_name != event_name {
+                return false;
+            }
+        }
+        if let Some(signature) = &self.signature {
+            if &record.signature != signature {
+                return false;
+            }
+        }
+        self.from_slot.is_none_or(|from| record.slot >= from)
+            && self.to_slot.is_none_or(|to| record.slot <= to)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TransactionQuery {
+    pub program_id: Option<String>,
+    pub signature: Option<String>,
+    pub from_slot: Option<u64>,
+    pub to_slot: Option<u64>,
+}
+
+impl TransactionQuery {
+    pub fn matches(&self, transaction: &SolanaTransaction) -> bool {
+        if let Some(program_id) = &self.program_id {
+            if !transaction.program_ids.contains(program_id) {
+                return false;
+            }
+        }
+        if let Some(signature) = &self.signature {
+            if &transaction.signature != signature {
+                return false;
+            }
+        }
+        self.from_slot.is_none_or(|from| transaction.slot >= from)
+            && self.to_slot.is_none_or(|to| transaction.slot <= to)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    pub chain_id: ChainId,
+    pub holder_id: String,
+    pub epoch: u64,
+    pub expires_at_ms: u64,
+}
+
+impl Lease {
+    pub fn is_expired_at(&self, now_ms: u64) -> bool {
+        self.expires_at_ms <= now_ms
+    }
+}
+
+pub fn unix_time_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadLetterKind {
+    Decode,
+    Slot,
+    Transaction,
+    Instruction,
+    Log,
+    ReorgSlot,
+    AccountUpdate,
+    BalanceChange,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub id: u64,
+    pub chain_id: ChainId,
+    pub slot: u64,
+    /// Block the failed item came from. Replay refuses to run once a reorg
+    /// has replaced it; `None` for items not tied to one block, such as
+    /// account updates.
+    pub blockhash: Option<String>,
+    pub kind: DeadLetterKind,
+    pub raw_log: Option<SolanaProgramLog>,
+    pub signature: Option<String>,
+    pub instruction: Option<InstructionPosition>,
+    /// Account whose update or balance change failed, for
+    /// `DeadLetterKind::AccountUpdate` and `DeadLetterKind::BalanceChange`.
+    pub account: Option<String>,
+    /// Token mint of a failed `DeadLetterKind::BalanceChange`; `None` for
+    /// SOL balance changes.
+    pub mint: Option<String>,
+    pub error: String,
+    pub attempts: u32,
+    pub created_at_ms: u64,
 }
 
 #[derive(Error, Debug)]
@@ -31,10 +220,19 @@ pub enum PersistenceError {
     StoreClosed,
     #[error("Invalid chain ID")]
     InvalidChainId,
+    #[error("Lease lost")]
+    LeaseLost,
     #[error("Other error: {0}")]
     Other(String),
 }
 
+/// Slot cursor storage shared by the replicas polling a chain.
+///
+/// Writes carry the lease epoch of the poller making them, or 0 when it runs
+/// without a `LeaseManager`. A store rejects a write with
+/// `PersistenceError::LeaseLost` once a newer epoch has written to the chain,
+/// checking and recording the epoch atomically with the write, so a replica
+/// that lost its lease mid-slot cannot overwrite its successor's progress.
 #[async_trait]
 pub trait ChainPollerPersistence: Send + Sync {
     async fn get_last_processed_slot(
@@ -42,7 +240,7 @@ pub trait ChainPollerPersistence: Send + Sync {
         chain_id: ChainId,
     ) -> Result<Option<SlotRecord>, PersistenceError>;
 
-    async fn save_slot(&self, slot: &SlotRecord) -> Result<(), PersistenceError>;
+    async fn save_slot(&self, slot: &SlotRecord, epoch: u64) -> Result<(), PersistenceError>;
 
     async fn get_slot(
         &self,
@@ -54,20 +252,187 @@ pub trait ChainPollerPersistence: Send + Sync {
         &self,
         chain_id: ChainId,
         slot_number: u64,
+        epoch: u64,
     ) -> Result<(), PersistenceError>;
 
+    /// Last slot a `FinalityBuffer` released to handlers. Stores that keep
+    /// the default implementations lose the buffered slots when the poller
+    /// restarts or fails over.
+    async fn get_last_released_slot(
+        &self,
+        _chain_id: ChainId,
+    ) -> Result<Option<u64>, PersistenceError> {
+        Ok(None)
+    }
+
+    async fn save_last_released_slot(
+        &self,
+        _chain_id: ChainId,
+        _slot_number: u64,
+        _epoch: u64,
+    ) -> Result<(), PersistenceError> {
+        Ok(())
+    }
+
     async fn close(&self) -> Result<(), PersistenceError>;
 }
 
+/// Optional storage for decoded events and the transactions that emitted them.
+///
+/// Logs are keyed by `(signature, log_index)` and transactions by signature,
+/// so saving the same slot twice is idempotent. Everything stored for a slot
+/// is removed by `delete_slot_events` when that slot is orphaned by a reorg.
+/// Writes are fenced by lease epoch like `ChainPollerPersistence` writes.
 #[async_trait]
-pub trait SlotHandler: Send + Sync {
-    async fn handle_slot(
+pub trait EventStore: Send + Sync {
+    async fn save_log(
+        &self,
+        log_with_slot: &LogWithSlot,
+        epoch: u64,
+    ) -> Result<(), PersistenceError>;
+
+    async fn save_transaction(
         &self,
-        slot: &crate::clients::solana::SolanaSlot,
-    ) -> anyhow::Result<()>;
+        chain_id: ChainId,
+        transaction: &SolanaTransaction,
+        epoch: u64,
+    ) -> Result<(), PersistenceError>;
 
-    async fn handle_log(&self, log_with_slot: &LogWithSlot) -> anyhow::Result<()>;
+    async fn query_logs(
+        &self,
+        chain_id: ChainId,
+        query: &EventQuery,
+    ) -> Result<Vec<EventRecord>, PersistenceError>;
+
+    async fn get_transaction(
+        &self,
+        chain_id: ChainId,
+        signature: &str,
+    ) -> Result<Option<SolanaTransaction>, PersistenceError>;
+
+    async fn query_transactions(
+        &self,
+        chain_id: ChainId,
+        query: &TransactionQuery,
+    ) -> Result<Vec<SolanaTransaction>, PersistenceError>;
 
-    async fn handle_reorg_slot(&self, slot_number: u64);
+    async fn delete_slot_events(
+        &self,
+        chain_id: ChainId,
+        slot_number: u64,
+        epoch: u64,
+    ) -> Result<(), PersistenceError>;
+
+    async fn close(&self) -> Result<(), PersistenceError>;
 }
 
+/// Per-chain lease used to elect a single active poller among replicas.
+///
+/// `try_acquire` both acquires a free or expired lease and renews one the
+/// caller already holds. The epoch only increases when the lease changes
+/// hands, so a writer holding an older epoch has been fenced off.
+#[async_trait]
+pub trait LeaseManager: Send + Sync {
+    async fn try_acquire(
+        &self,
+        chain_id: ChainId,
+        holder_id: &str,
+        ttl: std::time::Duration,
+    ) -> Result<Option<Lease>, PersistenceError>;
+
+    async fn validate_lease(&self, lease: &Lease) -> Result<(), PersistenceError>;
+
+    async fn release(&self, lease: &Lease) -> Result<(), PersistenceError>;
+
+    async fn get_lease(&self, chain_id: ChainId) -> Result<Option<Lease>, PersistenceError>;
+}
+
+/// Storage for logs that failed to decode and handler calls that failed after
+/// every retry. `save_dead_letter` assigns and returns the record's `id`,
+/// ignoring the one passed in; `update_dead_letter` overwrites by `id`.
+/// Writes are fenced by lease epoch per `DeadLetter::chain_id`, like
+/// `ChainPollerPersistence` writes.
+#[async_trait]
+pub trait DeadLetterStore: Send + Sync {
+    async fn save_dead_letter(
+        &self,
+        dead_letter: &DeadLetter,
+        epoch: u64,
+    ) -> Result<u64, PersistenceError>;
+
+    async fn get_dead_letter(&self, id: u64) -> Result<Option<DeadLetter>, PersistenceError>;
+
+    async fn list_dead_letters(
+        &self,
+        chain_id: ChainId,
+    ) -> Result<Vec<DeadLetter>, PersistenceError>;
+
+    async fn update_dead_letter(
+        &self,
+        dead_letter: &DeadLetter,
+        epoch: u64,
+    ) -> Result<(), PersistenceError>;
+
+    async fn delete_dead_letter(&self, id: u64, epoch: u64) -> Result<(), PersistenceError>;
+
+    async fn close(&self) -> Result<(), PersistenceError>;
+}
+
+/// Receives indexed data. Delivery is at-least-once: a slot whose hooks
+/// already ran is delivered again when it is retried after an error, after a
+/// restart before its cursor was saved, or by the replica that takes over the
+/// lease, so hooks should be idempotent.
+#[async_trait]
+pub trait SlotHandler: Send + Sync {
+    async fn handle_slot(&self, slot: &SolanaSlot) -> anyhow::Result<()>;
+
+    async fn handle_log(&self, log_with_slot: &LogWithSlot) -> anyhow::Result<()>;
+
+    /// Called instead of `handle_log` for logs of failed transactions when the
+    /// poller is configured with `FailedTransactionLogs::Route`.
+    async fn handle_failed_log(&self, _log_with_slot: &LogWithSlot) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called for every transaction in a slot that invokes an interesting
+    /// program, before its instructions and logs are handled.
+    async fn handle_transaction(
+        &self,
+        _transaction: &SolanaTransaction,
+        _slot: &SolanaSlot,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called, in execution order, for every instruction of an interesting
+    /// program and every instruction it invokes through CPI.
+    async fn handle_instruction(
+        &self,
+        _instruction: &InstructionWithContext,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called, after `handle_transaction`, for every SOL and SPL token balance
+    /// changed by a transaction that invokes an interesting program.
+    async fn handle_balance_change(&self, _change: &BalanceChange) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called when an account owned by an interesting program changes, if
+    /// the poller has an `AccountTracker`.
+    async fn handle_account_update(&self, _update: &AccountUpdate) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called once every hook for `slot` has succeeded, while the poller
+    /// still holds its lease, before the slot is recorded as processed.
+    /// Handlers that stage their writes and commit them here, in one
+    /// transaction with their own last committed slot and fenced by `epoch`,
+    /// apply each slot exactly once across failovers.
+    async fn commit_slot(&self, _slot: &SolanaSlot, _epoch: u64) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn handle_reorg_slot(&self, slot_number: u64) -> anyhow::Result<()>;
+}
@@ -0,0 +1,799 @@
+//! Reusable conformance suites for `ChainPollerPersistence`, `LeaseManager`
+//! and `DeadLetterStore` implementations.
+//!
+//! Every backend is expected to behave like the in-memory implementations.
+//! Backend crates enable the `testing` feature and call
+//! [`run_conformance_suite`], [`run_lease_conformance_suite`] or
+//! [`run_dead_letter_conformance_suite`] from their own tests with a factory
+//! that returns a fresh, empty store for each case.
+
+use crate::chain_pollers::persistence::*;
+use crate::clients::solana::SolanaProgramLog;
+use crate::config::ChainId;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+const CHAIN_A: ChainId = 101;
+const CHAIN_B: ChainId = 102;
+
+fn slot_record(chain_id: ChainId, slot: u64) -> SlotRecord {
+    SlotRecord {
+        slot,
+        blockhash: format!("hash-{}-{}", chain_id, slot),
+        parent: slot.saturating_sub(1),
+        block_time: 1_700_000_000 + slot,
+        chain_id,
+    }
+}
+
+fn chain_for_writer(writer: u64) -> ChainId {
+    if writer.is_multiple_of(2) {
+        CHAIN_A
+    } else {
+        CHAIN_B
+    }
+}
+
+fn assert_record_eq(actual: &SlotRecord, expected: &SlotRecord) {
+    assert_eq!(actual.slot, expected.slot, "slot mismatch");
+    assert_eq!(actual.blockhash, expected.blockhash, "blockhash mismatch");
+    assert_eq!(actual.parent, expected.parent, "parent mismatch");
+    assert_eq!(actual.block_time, expected.block_time, "block_time mismatch");
+    assert_eq!(actual.chain_id, expected.chain_id, "chain_id mismatch");
+}
+
+/// Runs every conformance case, each against a fresh store from `factory`.
+pub async fn run_conformance_suite<F, Fut>(mut factory: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Arc<dyn ChainPollerPersistence>>,
+{
+    empty_store_has_no_last_processed_slot(factory().await).await;
+    save_and_get_slot_round_trips(factory().await).await;
+    last_processed_slot_follows_most_recent_save(factory().await).await;
+    last_processed_slot_is_none_after_its_record_is_deleted(factory().await).await;
+    delete_missing_slot_returns_not_found(factory().await).await;
+    delete_slot_removes_only_that_slot(factory().await).await;
+    chains_are_isolated(factory().await).await;
+    writes_from_stale_epochs_are_rejected(factory().await).await;
+    operations_after_close_return_store_closed(factory().await).await;
+    concurrent_writers_do_not_lose_slots(factory().await).await;
+    close_while_busy_is_clean(factory().await).await;
+}
+
+/// A fresh store reports no last-processed slot and no slot records.
+pub async fn empty_store_has_no_last_processed_slot(store: Arc<dyn ChainPollerPersistence>) {
+    let last = store
+        .get_last_processed_slot(CHAIN_A)
+        .await
+        .expect("get_last_processed_slot failed on empty store");
+    assert!(last.is_none(), "empty store returned a last processed slot");
+
+    let slot = store
+        .get_slot(CHAIN_A, 1)
+        .await
+        .expect("get_slot failed on empty store");
+    assert!(slot.is_none(), "empty store returned a slot record");
+}
+
+/// A saved record is returned unchanged by `get_slot`.
+pub async fn save_and_get_slot_round_trips(store: Arc<dyn ChainPollerPersistence>) {
+    let record = slot_record(CHAIN_A, 42);
+    store.save_slot(&record, 0).await.expect("save_slot failed");
+
+    let fetched = store
+        .get_slot(CHAIN_A, 42)
+        .await
+        .expect("get_slot failed")
+        .expect("saved slot not found");
+    assert_record_eq(&fetched, &record);
+
+    let missing = store.get_slot(CHAIN_A, 43).await.expect("get_slot failed");
+    assert!(missing.is_none(), "unsaved slot was returned");
+}
+
+/// The last-processed slot is the most recently saved record, not the highest
+/// slot number. Reorg reconciliation relies on this to rewind the cursor to
+/// the common ancestor.
+pub async fn last_processed_slot_follows_most_recent_save(
+    store: Arc<dyn ChainPollerPersistence>,
+) {
+    for slot in [10, 11, 12] {
+        store
+            .save_slot(&slot_record(CHAIN_A, slot), 0)
+            .await
+            .expect("save_slot failed");
+    }
+
+    let last = store
+        .get_last_processed_slot(CHAIN_A)
+        .await
+        .expect("get_last_processed_slot failed")
+        .expect("last processed slot missing");
+    assert_record_eq(&last, &slot_record(CHAIN_A, 12));
+
+    store
+        .save_slot(&slot_record(CHAIN_A, 10), 0)
+        .await
+        .expect("save_slot failed");
+
+    let last = store
+        .get_last_processed_slot(CHAIN_A)
+        .await
+        .expect("get_last_processed_slot failed")
+        .expect("last processed slot missing");
+    assert_record_eq(&last, &slot_record(CHAIN_A, 10));
+
+    let newer = store.get_slot(CHAIN_A, 12).await.expect("get_slot failed");
+    assert!(newer.is_some(), "rewinding the cursor must not delete newer slots");
+}
+
+/// Deleting the record the cursor points at leaves no last-processed slot.
+pub async fn last_processed_slot_is_none_after_its_record_is_deleted(
+    store: Arc<dyn ChainPollerPersistence>,
+) {
+    store
+        .save_slot(&slot_record(CHAIN_A, 5), 0)
+        .await
+        .expect("save_slot failed");
+    store.delete_slot(CHAIN_A, 5, 0).await.expect("delete_slot failed");
+
+    let last = store
+        .get_last_processed_slot(CHAIN_A)
+        .await
+        .expect("get_last_processed_slot failed");
+    assert!(last.is_none(), "cursor returned a deleted slot record");
+}
+
+/// Deleting a slot that was never saved, or was already deleted, is `NotFound`.
+pub async fn delete_missing_slot_returns_not_found(store: Arc<dyn ChainPollerPersistence>) {
+    let result = store.delete_slot(CHAIN_A, 7, 0).await;
+    assert!(
+        matches!(result, Err(PersistenceError::NotFound)),
+        "expected NotFound, got {:?}",
+        result
+    );
+
+    store
+        .save_slot(&slot_record(CHAIN_A, 7), 0)
+        .await
+        .expect("save_slot failed");
+    store.delete_slot(CHAIN_A, 7, 0).await.expect("delete_slot failed");
+
+    let result = store.delete_slot(CHAIN_A, 7, 0).await;
+    assert!(
+        matches!(result, Err(PersistenceError::NotFound)),
+        "expected NotFound on second delete, got {:?}",
+        result
+    );
+}
+
+/// Deleting one slot leaves its neighbours and the cursor untouched.
+pub async fn delete_slot_removes_only_that_slot(store: Arc<dyn ChainPollerPersistence>) {
+    for slot in [20, 21, 22] {
+        store
+            .save_slot(&slot_record(CHAIN_A, slot), 0)
+            .await
+            .expect("save_slot failed");
+    }
+
+    store.delete_slot(CHAIN_A, 21, 0).await.expect("delete_slot failed");
+
+    assert!(store.get_slot(CHAIN_A, 20).await.expect("get_slot failed").is_some());
+    assert!(store.get_slot(CHAIN_A, 21).await.expect("get_slot failed").is_none());
+    assert!(store.get_slot(CHAIN_A, 22).await.expect("get_slot failed").is_some());
+
+    let last = store
+        .get_last_processed_slot(CHAIN_A)
+        .await
+        .expect("get_last_processed_slot failed")
+        .expect("last processed slot missing");
+    assert_eq!(last.slot, 22);
+}
+
+/// Records and cursors for one chain are invisible to every other chain.
+pub async fn chains_are_isolated(store: Arc<dyn ChainPollerPersistence>) {
+    store
+        .save_slot(&slot_record(CHAIN_A, 100), 0)
+        .await
+        .expect("save_slot failed");
+    store
+        .save_slot(&slot_record(CHAIN_B, 200), 0)
+        .await
+        .expect("save_slot failed");
+
+    let last_a = store
+        .get_last_processed_slot(CHAIN_A)
+        .await
+        .expect("get_last_processed_slot failed")
+        .expect("chain A cursor missing");
+    let last_b = store
+        .get_last_processed_slot(CHAIN_B)
+        .await
+        .expect("get_last_processed_slot failed")
+        .expect("chain B cursor missing");
+    assert_record_eq(&last_a, &slot_record(CHAIN_A, 100));
+    assert_record_eq(&last_b, &slot_record(CHAIN_B, 200));
+
+    assert!(store.get_slot(CHAIN_B, 100).await.expect("get_slot failed").is_none());
+    assert!(store.get_slot(CHAIN_A, 200).await.expect("get_slot failed").is_none());
+
+    let result = store.delete_slot(CHAIN_B, 100, 0).await;
+    assert!(
+        matches!(result, Err(PersistenceError::NotFound)),
+        "deleting another chain's slot should be NotFound, got {:?}",
+        result
+    );
+    assert!(store.get_slot(CHAIN_A, 100).await.expect("get_slot failed").is_some());
+}
+
+/// Once an epoch has written to a chain, writes with an older epoch fail with
+/// `LeaseLost` and change nothing, while other chains are unaffected.
+pub async fn writes_from_stale_epochs_are_rejected(store: Arc<dyn ChainPollerPersistence>) {
+    store
+        .save_slot(&slot_record(CHAIN_A, 1), 1)
+        .await
+        .expect("save_slot failed");
+    store
+        .save_slot(&slot_record(CHAIN_A, 2), 2)
+        .await
+        .expect("save_slot with newer epoch failed");
+
+    assert!(
+        matches!(
+            store.save_slot(&slot_record(CHAIN_A, 3), 1).await,
+            Err(PersistenceError::LeaseLost)
+        ),
+        "save_slot with stale epoch was accepted"
+    );
+    assert!(
+        matches!(
+            store.delete_slot(CHAIN_A, 2, 1).await,
+            Err(PersistenceError::LeaseLost)
+        ),
+        "delete_slot with stale epoch was accepted"
+    );
+    let last = store
+        .get_last_processed_slot(CHAIN_A)
+        .await
+        .expect("get_last_processed_slot failed")
+        .expect("last processed slot missing");
+    assert_record_eq(&last, &slot_record(CHAIN_A, 2));
+
+    store
+        .save_slot(&slot_record(CHAIN_A, 3), 2)
+        .await
+        .expect("save_slot with current epoch failed");
+    store
+        .save_slot(&slot_record(CHAIN_B, 1), 0)
+        .await
+        .expect("save_slot on another chain failed");
+}
+
+/// The `FinalityBuffer` cursor round-trips per chain and is independent of
+/// slot records. Not part of [`run_conformance_suite`], since stores may keep
+/// the default no-op implementation; backends that persist it should call
+/// this too.
+pub async fn last_released_slot_round_trips(store: Arc<dyn ChainPollerPersistence>) {
+    assert_eq!(
+        store.get_last_released_slot(CHAIN_A).await.expect("get_last_released_slot failed"),
+        None
+    );
+    for slot in [5, 3] {
+        store
+            .save_last_released_slot(CHAIN_A, slot, 0)
+            .await
+            .expect("save_last_released_slot failed");
+    }
+    store
+        .save_last_released_slot(CHAIN_B, 9, 0)
+        .await
+        .expect("save_last_released_slot failed");
+
+    assert_eq!(
+        store.get_last_released_slot(CHAIN_A).await.expect("get_last_released_slot failed"),
+        Some(3)
+    );
+    assert_eq!(
+        store.get_last_released_slot(CHAIN_B).await.expect("get_last_released_slot failed"),
+        Some(9)
+    );
+    assert!(store.get_last_processed_slot(CHAIN_A).await.expect("get_last_processed_slot failed").is_none());
+}
+
+/// After `close`, every operation (including a second `close`) is `StoreClosed`.
+pub async fn operations_after_close_return_store_closed(
+    store: Arc<dyn ChainPollerPersistence>,
+) {
+    store
+        .save_slot(&slot_record(CHAIN_A, 1), 0)
+        .await
+        .expect("save_slot failed");
+    store.close().await.expect("close failed");
+
+    assert!(matches!(
+        store.get_last_processed_slot(CHAIN_A).await,
+        Err(PersistenceError::StoreClosed)
+    ));
+    assert!(matches!(
+        store.save_slot(&slot_record(CHAIN_A, 2), 0).await,
+        Err(PersistenceError::StoreClosed)
+    ));
+    assert!(matches!(
+        store.get_slot(CHAIN_A, 1).await,
+        Err(PersistenceError::StoreClosed)
+    ));
+    assert!(matches!(
+        store.delete_slot(CHAIN_A, 1, 0).await,
+        Err(PersistenceError::StoreClosed)
+    ));
+    assert!(matches!(store.close().await, Err(PersistenceError::StoreClosed)));
+}
+
+/// Concurrent writers on several chains never lose a saved record, and each
+/// chain's cursor ends on one of the slots written to it.
+pub async fn concurrent_writers_do_not_lose_slots(store: Arc<dyn ChainPollerPersistence>) {
+    const WRITERS: u64 = 8;
+    const SLOTS_PER_WRITER: u64 = 25;
+
+    let mut handles = Vec::new();
+    for writer in 0..WRITERS {
+        let store = store.clone();
+        let chain_id = chain_for_writer(writer);
+        handles.push(tokio::spawn(async move {
+            for i in 0..SLOTS_PER_WRITER {
+                let slot = writer * SLOTS_PER_WRITER + i + 1;
+                store
+                    .save_slot(&slot_record(chain_id, slot), 0)
+                    .await
+                    .expect("concurrent save_slot failed");
+            }
+        }));
+    }
+    for handle in handles {
+        handle.await.expect("writer task panicked");
+    }
+
+    for writer in 0..WRITERS {
+        let chain_id = chain_for_writer(writer);
+        for i in 0..SLOTS_PER_WRITER {
+            let slot = writer * SLOTS_PER_WRITER + i + 1;
+            let fetched = store
+                .get_slot(chain_id, slot)
+                .await
+                .expect("get_slot failed")
+                .unwrap_or_else(|| panic!("slot {} lost on chain {}", slot, chain_id));
+            assert_record_eq(&fetched, &slot_record(chain_id, slot));
+        }
+    }
+
+    for chain_id in [CHAIN_A, CHAIN_B] {
+        let last = store
+            .get_last_processed_slot(chain_id)
+            .await
+            .expect("get_last_processed_slot failed")
+            .expect("cursor missing after concurrent writes");
+        assert_eq!(last.chain_id, chain_id, "cursor points at another chain");
+        let writer = (last.slot - 1) / SLOTS_PER_WRITER;
+        assert_eq!(chain_for_writer(writer), chain_id, "cursor points at a foreign slot");
+    }
+}
+
+/// Closing while writers and readers are in flight never surfaces anything
+/// other than success or `StoreClosed`, and the store stays closed afterwards.
+pub async fn close_while_busy_is_clean(store: Arc<dyn ChainPollerPersistence>) {
+    const WORKERS: u64 = 4;
+
+    let mut handles = Vec::new();
+    for worker in 0..WORKERS {
+        let store = store.clone();
+        handles.push(tokio::spawn(async move {
+            for i in 0..200u64 {
+                let slot = worker * 1_000 + i + 1;
+                match store.save_slot(&slot_record(CHAIN_A, slot), 0).await {
+                    Ok(()) | Err(PersistenceError::StoreClosed) => {}
+                    Err(e) => panic!("unexpected save_slot error while closing: {:?}", e),
+                }
+                match store.get_last_processed_slot(CHAIN_A).await {
+                    Ok(_) | Err(PersistenceError::StoreClosed) => {}
+                    Err(e) => panic!("unexpected read error while closing: {:?}", e),
+                }
+                match store.delete_slot(CHAIN_A, slot, 0).await {
+                    Ok(())
+                    | Err(PersistenceError::NotFound)
+                    | Err(PersistenceError::StoreClosed) => {}
+                    Err(e) => panic!("unexpected delete_slot error while closing: {:?}", e),
+                }
+                tokio::task::yield_now().await;
+            }
+        }));
+    }
+
+    tokio::task::yield_now().await;
+    store.close().await.expect("close failed while busy");
+
+    for handle in handles {
+        handle.await.expect("worker task panicked");
+    }
+
+    assert!(matches!(
+        store.save_slot(&slot_record(CHAIN_A, 1), 0).await,
+        Err(PersistenceError::StoreClosed)
+    ));
+    assert!(matches!(
+        store.get_last_processed_slot(CHAIN_A).await,
+        Err(PersistenceError::StoreClosed)
+    ));
+}
+
+/// Runs every lease conformance case, each against a fresh manager from
+/// `factory`.
+pub async fn run_lease_conformance_suite<F, Fut>(mut factory: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Arc<dyn LeaseManager>>,
+{
+    free_lease_is_acquired_and_renewed(factory().await).await;
+    held_lease_blocks_other_holders(factory().await).await;
+    expired_lease_is_taken_over_with_new_epoch(factory().await).await;
+    released_lease_is_taken_over_immediately(factory().await).await;
+    concurrent_acquire_elects_single_holder(factory().await).await;
+}
+
+/// A free lease is granted, and renewing it extends the expiry without
+/// changing the epoch.
+pub async fn free_lease_is_acquired_and_renewed(leases: Arc<dyn LeaseManager>) {
+    assert!(leases.get_lease(CHAIN_A).await.expect("get_lease failed").is_none());
+
+    let first = leases
+        .try_acquire(CHAIN_A, "replica-1", Duration::from_secs(30))
+        .await
+        .expect("try_acquire failed")
+        .expect("free lease was not granted");
+    assert_eq!(first.holder_id, "replica-1");
+    leases.validate_lease(&first).await.expect("fresh lease is invalid");
+
+    let renewed = leases
+        .try_acquire(CHAIN_A, "replica-1", Duration::from_secs(60))
+        .await
+        .expect("try_acquire failed")
+        .expect("holder could not renew its lease");
+    assert_eq!(renewed.epoch, first.epoch, "renewal must not bump the epoch");
+    assert!(renewed.expires_at_ms >= first.expires_at_ms);
+    leases.validate_lease(&first).await.expect("renewal invalidated the lease");
+}
+
+/// While a lease is live, nobody else can acquire it, and other chains are
+/// unaffected.
+pub async fn held_lease_blocks_other_holders(leases: Arc<dyn LeaseManager>) {
+    leases
+        .try_acquire(CHAIN_A, "replica-1", Duration::from_secs(30))
+        .await
+        .expect("try_acquire failed")
+        .expect("free lease was not granted");
+
+    let contender = leases
+        .try_acquire(CHAIN_A, "replica-2", Duration::from_secs(30))
+        .await
+        .expect("try_acquire failed");
+    assert!(contender.is_none(), "live lease was granted to another holder");
+
+    let other_chain = leases
+        .try_acquire(CHAIN_B, "replica-2", Duration::from_secs(30))
+        .await
+        .expect("try_acquire failed");
+    assert!(other_chain.is_some(), "leases must be per chain");
+}
+
+/// Once a lease expires another holder takes it over with a higher epoch,
+/// and the previous holder's lease no longer validates.
+pub async fn expired_lease_is_taken_over_with_new_epoch(leases: Arc<dyn LeaseManager>) {
+    let primary = leases
+        .try_acquire(CHAIN_A, "replica-1", Duration::from_millis(50))
+        .await
+        .expect("try_acquire failed")
+        .expect("free lease was not granted");
+
+    tokio::time::sleep(Duration::from_millis(120)).await;
+    assert!(matches!(
+        leases.validate_lease(&primary).await,
+        Err(PersistenceError::LeaseLost)
+    ));
+
+    let standby = leases
+        .try_acquire(CHAIN_A, "replica-2", Duration::from_secs(30))
+        .await
+        .expect("try_acquire failed")
+        .expect("expired lease was not taken over");
+    assert!(standby.epoch > primary.epoch, "takeover must bump the epoch");
+
+    assert!(matches!(
+        leases.validate_lease(&primary).await,
+        Err(PersistenceError::LeaseLost)
+    ));
+    let stale = leases
+        .try_acquire(CHAIN_A, "replica-1", Duration::from_secs(30))
+        .await
+        .expect("try_acquire failed");
+    assert!(stale.is_none(), "previous holder reclaimed a live lease");
+}
+
+/// Releasing a lease lets a standby take over without waiting for expiry.
+pub async fn released_lease_is_taken_over_immediately(leases: Arc<dyn LeaseManager>) {
+    let primary = leases
+        .try_acquire(CHAIN_A, "replica-1", Duration::from_secs(30))
+        .await
+        .expect("try_acquire failed")
+        .expect("free lease was not granted");
+    leases.release(&primary).await.expect("release failed");
+
+    let standby = leases
+        .try_acquire(CHAIN_A, "replica-2", Duration::from_secs(30))
+        .await
+        .expect("try_acquire failed")
+        .expect("released lease was not granted");
+    assert!(standby.epoch > primary.epoch);
+}
+
+/// Concurrent contenders for a free lease elect exactly one holder.
+pub async fn concurrent_acquire_elects_single_holder(leases: Arc<dyn LeaseManager>) {
+    let mut handles = Vec::new();
+    for replica in 0..8 {
+        let leases = leases.clone();
+        handles.push(tokio::spawn(async move {
+            leases
+                .try_acquire(CHAIN_A, &format!("replica-{}", replica), Duration::from_secs(30))
+                .await
+                .expect("concurrent try_acquire failed")
+        }));
+    }
+
+    let mut winners = Vec::new();
+    for handle in handles {
+        if let Some(lease) = handle.await.expect("contender task panicked") {
+            winners.push(lease);
+        }
+    }
+    assert_eq!(winners.len(), 1, "expected exactly one lease holder");
+
+    let current = leases
+        .get_lease(CHAIN_A)
+        .await
+        .expect("get_lease failed")
+        .expect("lease missing after election");
+    assert_eq!(current, winners[0]);
+}
+
+fn dead_letter(chain_id: ChainId, slot: u64, kind: DeadLetterKind) -> DeadLetter {
+    let signature = format!("sig-{}-{}", chain_id, slot);
+    DeadLetter {
+        id: 0,
+        chain_id,
+        slot,
+        blockhash: Some(format!("hash-{}-{}", chain_id, slot)),
+        kind,
+        raw_log: Some(SolanaProgramLog {
+            program_id: "Prog1111".to_string(),
+            log_index: 2,
+            signature: signature.clone(),
+            slot,
+            block_time: Some(1_700_000_000),
+            log_message: "Program log: Deposit".to_string(),
+            instruction_index: 1,
+            inner_instruction_index: Some(0),
+            depth: 2,
+            parent_program_id: Some("Parent1111".to_string()),
+            logs_truncated: false,
+        }),
+        signature: Some(signature),
+        instruction: Some(InstructionPosition {
+            top_level_index: 1,
+            inner_index: Some(0),
+        }),
+        account: Some("Account1111".to_string()),
+        mint: Some("Mint1111".to_string()),
+        error: "handler failed".to_string(),
+        attempts: 3,
+        created_at_ms: 1_700_000_000_000,
+    }
+}
+
+fn assert_dead_letter_eq(actual: &DeadLetter, expected: &DeadLetter) {
+    assert_eq!(actual.id, expected.id, "id mismatch");
+    assert_eq!(actual.chain_id, expected.chain_id, "chain_id mismatch");
+    assert_eq!(actual.slot, expected.slot, "slot mismatch");
+    assert_eq!(actual.blockhash, expected.blockhash, "blockhash mismatch");
+    assert_eq!(actual.kind, expected.kind, "kind mismatch");
+    assert_eq!(
+        serde_json::to_value(&actual.raw_log).unwrap(),
+        serde_json::to_value(&expected.raw_log).unwrap(),
+        "raw_log mismatch"
+    );
+    assert_eq!(actual.signature, expected.signature, "signature mismatch");
+    assert_eq!(actual.instruction, expected.instruction, "instruction mismatch");
+    assert_eq!(actual.account, expected.account, "account mismatch");
+    assert_eq!(actual.mint, expected.mint, "mint mismatch");
+    assert_eq!(actual.error, expected.error, "error mismatch");
+    assert_eq!(actual.attempts, expected.attempts, "attempts mismatch");
+    assert_eq!(actual.created_at_ms, expected.created_at_ms, "created_at_ms mismatch");
+}
+
+/// Runs every dead-letter conformance case, each against a fresh store from
+/// `factory`.
+pub async fn run_dead_letter_conformance_suite<F, Fut>(mut factory: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Arc<dyn DeadLetterStore>>,
+{
+    saved_dead_letter_round_trips_with_new_id(factory().await).await;
+    dead_letters_are_listed_per_chain_oldest_first(factory().await).await;
+    update_and_delete_dead_letter_by_id(factory().await).await;
+    dead_letter_writes_from_stale_epochs_are_rejected(factory().await).await;
+    dead_letter_operations_after_close_return_store_closed(factory().await).await;
+}
+
+/// `save_dead_letter` ignores the passed `id`, assigns a new one and stores
+/// every other field unchanged.
+pub async fn saved_dead_letter_round_trips_with_new_id(store: Arc<dyn DeadLetterStore>) {
+    let mut expected = dead_letter(CHAIN_A, 7, DeadLetterKind::Log);
+    expected.id = 99;
+    let first = store
+        .save_dead_letter(&expected, 0)
+        .await
+        .expect("save_dead_letter failed");
+    let second = store
+        .save_dead_letter(&dead_letter(CHAIN_A, 8, DeadLetterKind::Slot), 0)
+        .await
+        .expect("save_dead_letter failed");
+    assert_ne!(first, second, "dead letters share an id");
+
+    expected.id = first;
+    let actual = store
+        .get_dead_letter(first)
+        .await
+        .expect("get_dead_letter failed")
+        .expect("saved dead letter missing");
+    assert_dead_letter_eq(&actual, &expected);
+    assert!(store
+        .get_dead_letter(first.max(second) + 1)
+        .await
+        .expect("get_dead_letter failed")
+        .is_none());
+}
+
+/// `list_dead_letters` returns only the chain's records, in `id` order.
+pub async fn dead_letters_are_listed_per_chain_oldest_first(store: Arc<dyn DeadLetterStore>) {
+    let mut ids = Vec::new();
+    for slot in [30, 10, 20] {
+        ids.push(
+            store
+                .save_dead_letter(&dead_letter(CHAIN_A, slot, DeadLetterKind::Transaction), 0)
+                .await
+                .expect("save_dead_letter failed"),
+        );
+        store
+            .save_dead_letter(&dead_letter(CHAIN_B, slot, DeadLetterKind::Transaction), 0)
+            .await
+            .expect("save_dead_letter failed");
+    }
+
+    let listed = store
+        .list_dead_letters(CHAIN_A)
+        .await
+        .expect("list_dead_letters failed");
+    assert_eq!(listed.iter().map(|d| d.id).collect::<Vec<_>>(), ids);
+    assert_eq!(listed.iter().map(|d| d.slot).collect::<Vec<_>>(), vec![30, 10, 20]);
+    assert!(listed.iter().all(|d| d.chain_id == CHAIN_A));
+}
+
+/// `update_dead_letter` overwrites by `id`, and both it and
+/// `delete_dead_letter` report `NotFound` for unknown ids.
+pub async fn update_and_delete_dead_letter_by_id(store: Arc<dyn DeadLetterStore>) {
+    let mut saved = dead_letter(CHAIN_A, 5, DeadLetterKind::Instruction);
+    saved.id = store
+        .save_dead_letter(&saved, 0)
+        .await
+        .expect("save_dead_letter failed");
+
+    saved.attempts += 1;
+    saved.error = "still failing".to_string();
+    store
+        .update_dead_letter(&saved, 0)
+        .await
+        .expect("update_dead_letter failed");
+    let actual = store
+        .get_dead_letter(saved.id)
+        .await
+        .expect("get_dead_letter failed")
+        .expect("updated dead letter missing");
+    assert_dead_letter_eq(&actual, &saved);
+
+    store
+        .delete_dead_letter(saved.id, 0)
+        .await
+        .expect("delete_dead_letter failed");
+    assert!(store
+        .get_dead_letter(saved.id)
+        .await
+        .expect("get_dead_letter failed")
+        .is_none());
+    assert!(matches!(
+        store.update_dead_letter(&saved, 0).await,
+        Err(PersistenceError::NotFound)
+    ));
+    assert!(matches!(
+        store.delete_dead_letter(saved.id, 0).await,
+        Err(PersistenceError::NotFound)
+    ));
+}
+
+/// Once an epoch has written a chain's dead letters, writes with an older
+/// epoch fail with `LeaseLost` and change nothing.
+pub async fn dead_letter_writes_from_stale_epochs_are_rejected(store: Arc<dyn DeadLetterStore>) {
+    let mut saved = dead_letter(CHAIN_A, 5, DeadLetterKind::Log);
+    saved.id = store
+        .save_dead_letter(&saved, 2)
+        .await
+        .expect("save_dead_letter failed");
+
+    assert!(matches!(
+        store
+            .save_dead_letter(&dead_letter(CHAIN_A, 6, DeadLetterKind::Log), 1)
+            .await,
+        Err(PersistenceError::LeaseLost)
+    ));
+    let mut stale = saved.clone();
+    stale.attempts += 1;
+    assert!(matches!(
+        store.update_dead_letter(&stale, 1).await,
+        Err(PersistenceError::LeaseLost)
+    ));
+    assert!(matches!(
+        store.delete_dead_letter(saved.id, 1).await,
+        Err(PersistenceError::LeaseLost)
+    ));
+
+    let listed = store
+        .list_dead_letters(CHAIN_A)
+        .await
+        .expect("list_dead_letters failed");
+    assert_eq!(listed.len(), 1);
+    assert_dead_letter_eq(&listed[0], &saved);
+
+    store
+        .save_dead_letter(&dead_letter(CHAIN_B, 6, DeadLetterKind::Log), 0)
+        .await
+        .expect("save_dead_letter on another chain failed");
+}
+
+/// After `close`, every operation (including a second `close`) is `StoreClosed`.
+pub async fn dead_letter_operations_after_close_return_store_closed(
+    store: Arc<dyn DeadLetterStore>,
+) {
+    let id = store
+        .save_dead_letter(&dead_letter(CHAIN_A, 1, DeadLetterKind::Slot), 0)
+        .await
+        .expect("save_dead_letter failed");
+    store.close().await.expect("close failed");
+
+    assert!(matches!(
+        store
+            .save_dead_letter(&dead_letter(CHAIN_A, 2, DeadLetterKind::Slot), 0)
+            .await,
+        Err(PersistenceError::StoreClosed)
+    ));
+    assert!(matches!(
+        store.get_dead_letter(id).await,
+        Err(PersistenceError::StoreClosed)
+    ));
+    assert!(matches!(
+        store.list_dead_letters(CHAIN_A).await,
+        Err(PersistenceError::StoreClosed)
+    ));
+    assert!(matches!(
+        store.delete_dead_letter(id, 0).await,
+        Err(PersistenceError::StoreClosed)
+    ));
+    assert!(matches!(store.close().await, Err(PersistenceError::StoreClosed)));
+}
@@ -1,12 +1,41 @@
 use crate::chain_pollers::persistence::*;
+use crate::clients::solana::SolanaTransaction;
 use crate::config::ChainId;
+use dashmap::mapref::one::RefMut;
 use dashmap::DashMap;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// Highest lease epoch that has written each chain's data.
+#[derive(Default)]
+struct EpochFence {
+    epochs: DashMap<ChainId, u64>,
+}
+
+impl EpochFence {
+    /// Records `epoch` for the chain, or fails with `LeaseLost` if a newer
+    /// epoch has already written. Callers write while holding the returned
+    /// guard so a newer writer cannot interleave.
+    fn admit(
+        &self,
+        chain_id: ChainId,
+        epoch: u64,
+    ) -> Result<RefMut<'_, ChainId, u64>, PersistenceError> {
+        let mut fenced = self.epochs.entry(chain_id).or_insert(0);
+        if epoch < *fenced {
+            return Err(PersistenceError::LeaseLost);
+        }
+        *fenced = epoch;
+        Ok(fenced)
+    }
+}
+
 pub struct InMemoryChainPollerPersistence {
     last_processed_slots: Arc<DashMap<String, u64>>,
     slots: Arc<DashMap<String, SlotRecord>>,
+    last_released_slots: Arc<DashMap<String, u64>>,
+    fence: Arc<EpochFence>,
     closed: Arc<RwLock<bool>>,
 }
 
@@ -15,6 +44,8 @@ impl InMemoryChainPollerPersistence {
         Self {
             last_processed_slots: Arc::new(DashMap::new()),
             slots: Arc::new(DashMap::new()),
+            last_released_slots: Arc::new(DashMap::new()),
+            fence: Arc::new(EpochFence::default()),
             closed: Arc::new(RwLock::new(false)),
         }
     }
@@ -52,12 +83,13 @@ impl ChainPollerPersistence for InMemoryChainPollerPersistence {
         }
     }
 
-    async fn save_slot(&self, slot: &SlotRecord) -> Result<(), PersistenceError> {
+    async fn save_slot(&self, slot: &SlotRecord, epoch: u64) -> Result<(), PersistenceError> {
         let closed = *self.closed.read().await;
         if closed {
             return Err(PersistenceError::StoreClosed);
         }
 
+        let _fence = self.fence.admit(slot.chain_id, epoch)?;
         let slot_key = Self::make_slot_record_key(slot.chain_id, slot.slot);
         self.slots.insert(slot_key.clone(), slot.clone());
 
@@ -88,12 +120,14 @@ impl ChainPollerPersistence for InMemoryChainPollerPersistence {
         &self,
         chain_id: ChainId,
         slot_number: u64,
+        epoch: u64,
     ) -> Result<(), PersistenceError> {
         let closed = *self.closed.read().await;
         if closed {
             return Err(PersistenceError::StoreClosed);
         }
 
+        let _fence = self.fence.admit(chain_id, epoch)?;
         let key = Self::make_slot_record_key(chain_id, slot_number);
         if self.slots.remove(&key).is_none() {
             return Err(PersistenceError::NotFound);
@@ -102,6 +136,36 @@ impl ChainPollerPersistence for InMemoryChainPollerPersistence {
         Ok(())
     }
 
+    async fn get_last_released_slot(
+        &self,
+        chain_id: ChainId,
+    ) -> Result<Option<u64>, PersistenceError> {
+        let closed = *self.closed.read().await;
+        if closed {
+            return Err(PersistenceError::StoreClosed);
+        }
+
+        let key = Self::make_slot_key(chain_id);
+        Ok(self.last_released_slots.get(&key).map(|v| *v.value()))
+    }
+
+    async fn save_last_released_slot(
+        &self,
+        chain_id: ChainId,
+        slot_number: u64,
+        epoch: u64,
+    ) -> Result<(), PersistenceError> {
+        let closed = *self.closed.read().await;
+        if closed {
+            return Err(PersistenceError::StoreClosed);
+        }
+
+        let _fence = self.fence.admit(chain_id, epoch)?;
+        let key = Self::make_slot_key(chain_id);
+        self.last_released_slots.insert(key, slot_number);
+        Ok(())
+    }
+
     async fn close(&self) -> Result<(), PersistenceError> {
         let mut closed = self.closed.write().await;
         if *closed {
@@ -111,6 +175,7 @@ impl ChainPollerPersistence for InMemoryChainPollerPersistence {
         *closed = true;
         self.last_processed_slots.clear();
         self.slots.clear();
+        self.last_released_slots.clear();
 
         Ok(())
     }
@@ -122,3 +187,601 @@ impl Default for InMemoryChainPollerPersistence {
     }
 }
 
+#[derive(Default)]
+struct SlotEvents {
+    logs: Vec<EventRecord>,
+    transactions: Vec<SolanaTransaction>,
+}
+
+/// Keeps every slot's events in memory. Without a retention limit the store
+/// grows for as long as the poller runs, so unbounded instances are meant for
+/// tests; long-running pollers should use `with_retention` or a durable store.
+pub struct InMemoryEventStore {
+    slots: Arc<RwLock<BTreeMap<(ChainId, u64), SlotEvents>>>,
+    retained_slots: Option<u64>,
+    fence: Arc<EpochFence>,
+    closed: Arc<RwLock<bool>>,
+}
+
+impl InMemoryEventStore {
+    pub fn new() -> Self {
+        Self {
+            slots: Arc::new(RwLock::new(BTreeMap::new())),
+            retained_slots: None,
+            fence: Arc::new(EpochFence::default()),
+            closed: Arc::new(RwLock::new(false)),
+        }
+    }
+
+    /// Drops a chain's events once they are more than `retained_slots` slots
+    /// behind the newest slot saved for that chain.
+    pub fn with_retention(retained_slots: u64) -> Self {
+        Self {
+            retained_slots: Some(retained_slots),
+            ..Self::new()
+        }
+    }
+
+    fn prune(&self, slots: &mut BTreeMap<(ChainId, u64), SlotEvents>, chain_id: ChainId) {
+        let Some(retained_slots) = self.retained_slots else {
+            return;
+        };
+        let Some((&(_, newest), _)) = slots.range((chain_id, 0)..=(chain_id, u64::MAX)).next_back()
+        else {
+            return;
+        };
+        let oldest_kept = newest.saturating_sub(retained_slots.saturating_sub(1));
+        let expired: Vec<_> = slots
+            .range((chain_id, 0)..(chain_id, oldest_kept))
+            .map(|(key, _)| *key)
+            .collect();
+        for key in expired {
+            slots.remove(&key);
+        }
+    }
+
+    async fn ensure_open(&self) -> Result<(), PersistenceError> {
+        if *self.closed.read().await {
+            return Err(PersistenceError::StoreClosed);
+        }
+        Ok(())
+    }
+
+    fn slot_range(
+        chain_id: ChainId,
+        from_slot: Option<u64>,
+        to_slot: Option<u64>,
+    ) -> std::ops::RangeInclusive<(ChainId, u64)> {
+        (chain_id, from_slot.unwrap_or(0))..=(chain_id, to_slot.unwrap_or(u64::MAX))
+    }
+}
+
+#[async_trait::async_trait]
+impl EventStore for InMemoryEventStore {
+    async fn save_log(
+        &self,
+        log_with_slot: &LogWithSlot,
+        epoch: u64,
+    ) -> Result<(), PersistenceError> {
+        self.ensure_open().await?;
+
+        let record = EventRecord::from_log_with_slot(log_with_slot);
+        let mut slots = self.slots.write().await;
+        let _fence = self.fence.admit(record.chain_id, epoch)?;
+        let events = slots.entry((record.chain_id, record.slot)).or_default();
+        match events
+            .logs
+            .iter_mut()
+            .find(|l| l.signature == record.signature && l.log_index == record.log_index)
+        {
+            Some(existing) => *existing = record,
+            None => events.logs.push(record),
+        }
+        self.prune(&mut slots, log_with_slot.slot.chain_id);
+
+        Ok(())
+    }
+
+    async fn save_transaction(
+        &self,
+        chain_id: ChainId,
+        transaction: &SolanaTransaction,
+        epoch: u64,
+    ) -> Result<(), PersistenceError> {
+        self.ensure_open().await?;
+
+        let mut slots = self.slots.write().await;
+        let _fence = self.fence.admit(chain_id, epoch)?;
+        let events = slots.entry((chain_id, transaction.slot)).or_default();
+        match events
+            .transactions
+            .iter_mut()
+            .find(|t| t.signature == transaction.signature)
+        {
+            Some(existing) => *existing = transaction.clone(),
+            None => events.transactions.push(transaction.clone()),
+        }
+        self.prune(&mut slots, chain_id);
+
+        Ok(())
+    }
+
+    async fn query_logs(
+        &self,
+        chain_id: ChainId,
+        query: &EventQuery,
+    ) -> Result<Vec<EventRecord>, PersistenceError> {
+        self.ensure_open().await?;
+
+        let slots = self.slots.read().await;
+        Ok(slots
+            .range(Self::slot_range(chain_id, query.from_slot, query.to_slot))
+            .flat_map(|(_, events)| events.logs.iter())
+            .filter(|record| query.matches(record))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_transaction(
+        &self,
+        chain_id: ChainId,
+        signature: &str,
+    ) -> Result<Option<SolanaTransaction>, PersistenceError> {
+        self.ensure_open().await?;
+
+        let slots = self.slots.read().await;
+        Ok(slots
+            .range(Self::slot_range(chain_id, None, None))
+            .flat_map(|(_, events)| events.transactions.iter())
+            .find(|t| t.signature == signature)
+            .cloned())
+    }
+
+    async fn query_transactions(
+        &self,
+        chain_id: ChainId,
+        query: &TransactionQuery,
+    ) -> Result<Vec<SolanaTransaction>, PersistenceError> {
+        self.ensure_open().await?;
+
+        let slots = self.slots.read().await;
+        Ok(slots
+            .range(Self::slot_range(chain_id, query.from_slot, query.to_slot))
+            .flat_map(|(_, events)| events.transactions.iter())
+            .filter(|t| query.matches(t))
+            .cloned()
+            .collect())
+    }
+
+    async fn delete_slot_events(
+        &self,
+        chain_id: ChainId,
+        slot_number: u64,
+        epoch: u64,
+    ) -> Result<(), PersistenceError> {
+        self.ensure_open().await?;
+
+        let mut slots = self.slots.write().await;
+        let _fence = self.fence.admit(chain_id, epoch)?;
+        slots.remove(&(chain_id, slot_number));
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<(), PersistenceError> {
+        let mut closed = self.closed.write().await;
+        if *closed {
+            return Err(PersistenceError::StoreClosed);
+        }
+
+        *closed = true;
+        self.slots.write().await.clear();
+
+        Ok(())
+    }
+}
+
+impl Default for InMemoryEventStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct InMemoryLeaseManager {
+    leases: Arc<DashMap<ChainId, Lease>>,
+}
+
+impl InMemoryLeaseManager {
+    pub fn new() -> Self {
+        Self {
+            leases: Arc::new(DashMap::new()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LeaseManager for InMemoryLeaseManager {
+    async fn try_acquire(
+        &self,
+        chain_id: ChainId,
+        holder_id: &str,
+        ttl: std::time::Duration,
+    ) -> Result<Option<Lease>, PersistenceError> {
+        let now = unix_time_ms();
+        let expires_at_ms = now + ttl.as_millis() as u64;
+
+        let mut entry = self.leases.entry(chain_id).or_insert_with(|| Lease {
+            chain_id,
+            holder_id: holder_id.to_string(),
+            epoch: 0,
+            expires_at_ms: 0,
+        });
+        let lease = entry.value_mut();
+
+        if lease.holder_id == holder_id && lease.epoch > 0 {
+            lease.expires_at_ms = expires_at_ms;
+            return Ok(Some(lease.clone()));
+        }
+
+        if !lease.is_expired_at(now) {
+            return Ok(None);
+        }
+
+        lease.holder_id = holder_id.to_string();
+        lease.epoch += 1;
+        lease.expires_at_ms = expires_at_ms;
+        Ok(Some(lease.clone()))
+    }
+
+    async fn validate_lease(&self, lease: &Lease) -> Result<(), PersistenceError> {
+        match self.leases.get(&lease.chain_id) {
+            Some(current)
+                if current.holder_id == lease.holder_id
+                    && current.epoch == lease.epoch
+                    && !current.is_expired_at(unix_time_ms()) =>
+            {
+                Ok(())
+            }
+            _ => Err(PersistenceError::LeaseLost),
+        }
+    }
+
+    async fn release(&self, lease: &Lease) -> Result<(), PersistenceError> {
+        if let Some(mut current) = self.leases.get_mut(&lease.chain_id) {
+            if current.holder_id == lease.holder_id && current.epoch == lease.epoch {
+                current.expires_at_ms = 0;
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_lease(&self, chain_id: ChainId) -> Result<Option<Lease>, PersistenceError> {
+        Ok(self
+            .leases
+            .get(&chain_id)
+            .filter(|l| l.epoch > 0)
+            .map(|l| l.value().clone()))
+    }
+}
+
+impl Default for InMemoryLeaseManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct InMemoryDeadLetterStore {
+    dead_letters: Arc<DashMap<u64, DeadLetter>>,
+    next_id: Arc<std::sync::atomic::AtomicU64>,
+    fence: Arc<EpochFence>,
+    closed: Arc<RwLock<bool>>,
+}
+
+impl InMemoryDeadLetterStore {
+    pub fn new() -> Self {
+        Self {
+            dead_letters: Arc::new(DashMap::new()),
+            next_id: Arc::new(std::sync::atomic::AtomicU64::new(1)),
+            fence: Arc::new(EpochFence::default()),
+            closed: Arc::new(RwLock::new(false)),
+        }
+    }
+
+    async fn ensure_open(&self) -> Result<(), PersistenceError> {
+        if *self.closed.read().await {
+            return Err(PersistenceError::StoreClosed);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl DeadLetterStore for InMemoryDeadLetterStore {
+    async fn save_dead_letter(
+        &self,
+        dead_letter: &DeadLetter,
+        epoch: u64,
+    ) -> Result<u64, PersistenceError> {
+        self.ensure_open().await?;
+
+        let _fence = self.fence.admit(dead_letter.chain_id, epoch)?;
+        let id = self
+            .next_id
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let mut dead_letter = dead_letter.clone();
+        dead_letter.id = id;
+        self.dead_letters.insert(id, dead_letter);
+        Ok(id)
+    }
+
+    async fn get_dead_letter(&self, id: u64) -> Result<Option<DeadLetter>, PersistenceError> {
+        self.ensure_open().await?;
+
+        Ok(self.dead_letters.get(&id).map(|d| d.value().clone()))
+    }
+
+    async fn list_dead_letters(
+        &self,
+        chain_id: ChainId,
+    ) -> Result<Vec<DeadLetter>, PersistenceError> {
+        self.ensure_open().await?;
+
+        let mut dead_letters: Vec<DeadLetter> = self
+            .dead_letters
+            .iter()
+            .filter(|d| d.chain_id == chain_id)
+            .map(|d| d.value().clone())
+            .collect();
+        dead_letters.sort_by_key(|d| d.id);
+        Ok(dead_letters)
+    }
+
+    async fn update_dead_letter(
+        &self,
+        dead_letter: &DeadLetter,
+        epoch: u64,
+    ) -> Result<(), PersistenceError> {
+        self.ensure_open().await?;
+
+        let _fence = self.fence.admit(dead_letter.chain_id, epoch)?;
+        match self.dead_letters.get_mut(&dead_letter.id) {
+            Some(mut existing) => {
+                *existing = dead_letter.clone();
+                Ok(())
+            }
+            None => Err(PersistenceError::NotFound),
+        }
+    }
+
+    async fn delete_dead_letter(&self, id: u64, epoch: u64) -> Result<(), PersistenceError> {
+        self.ensure_open().await?;
+
+        let chain_id = self
+            .dead_letters
+            .get(&id)
+            .map(|d| d.chain_id)
+            .ok_or(PersistenceError::NotFound)?;
+        let _fence = self.fence.admit(chain_id, epoch)?;
+        if self.dead_letters.remove(&id).is_none() {
+            return Err(PersistenceError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<(), PersistenceError> {
+        let mut closed = self.closed.write().await;
+        if *closed {
+            return Err(PersistenceError::StoreClosed);
+        }
+
+        *closed = true;
+        self.dead_letters.clear();
+
+        Ok(())
+    }
+}
+
+impl Default for InMemoryDeadLetterStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain_pollers::persistence::conformance::{
+        last_released_slot_round_trips, run_conformance_suite, run_dead_letter_conformance_suite,
+        run_lease_conformance_suite,
+    };
+    use crate::clients::solana::{SolanaProgramLog, SolanaSlot};
+    use crate::transaction_log_parser::DecodedLog;
+    use std::collections::HashMap;
+
+    fn log_with_slot(slot: u64, signature: &str, log_index: u64, event_name: &str) -> LogWithSlot {
+        let raw_log = SolanaProgramLog {
+            program_id: "Prog1111".to_string(),
+            log_index,
+            signature: signature.to_string(),
+            slot,
+            block_time: None,
+            log_message: format!("Program log: {}", event_name),
+            instruction_index: 0,
+            inner_instruction_index: None,
+            depth: 1,
+            parent_program_id: None,
+            logs_truncated: false,
+        };
+        LogWithSlot {
+            log: DecodedLog {
+                log_index,
+                address: raw_log.program_id.clone(),
+                arguments: Vec::new(),
+                event_name: event_name.to_string(),
+                output_data: HashMap::new(),
+                truncated: false,
+            },
+            raw_log,
+            compute_units: None,
+            return_data: None,
+            success: true,
+            error: None,
+            slot: SolanaSlot {
+                slot,
+                parent: Some(slot - 1),
+                blockhash: format!("hash-{}", slot),
+                block_time: None,
+                transactions: Vec::new(),
+                chain_id: 101,
+            },
+        }
+    }
+
+    fn transaction(slot: u64, signature: &str) -> SolanaTransaction {
+        SolanaTransaction {
+            signature: signature.to_string(),
+            slot,
+            block_time: None,
+            fee: 5000,
+            success: true,
+            error: None,
+            account_keys: vec!["Prog1111".to_string()],
+            program_ids: vec!["Prog1111".to_string()],
+            log_messages: Vec::new(),
+            pre_balances: Vec::new(),
+            post_balances: Vec::new(),
+            pre_token_balances: Vec::new(),
+            post_token_balances: Vec::new(),
+            instructions: Vec::new(),
+            inner_instructions: Vec::new(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn in_memory_store_passes_conformance_suite() {
+        run_conformance_suite(|| async {
+            Arc::new(InMemoryChainPollerPersistence::new()) as Arc<dyn ChainPollerPersistence>
+        })
+        .await;
+        last_released_slot_round_trips(Arc::new(InMemoryChainPollerPersistence::new())).await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn in_memory_lease_manager_passes_conformance_suite() {
+        run_lease_conformance_suite(|| async {
+            Arc::new(InMemoryLeaseManager::new()) as Arc<dyn LeaseManager>
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn in_memory_dead_letter_store_passes_conformance_suite() {
+        run_dead_letter_conformance_suite(|| async {
+            Arc::new(InMemoryDeadLetterStore::new()) as Arc<dyn DeadLetterStore>
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn event_store_queries_by_name_signature_and_slot_range() {
+        let store = InMemoryEventStore::new();
+        store.save_log(&log_with_slot(10, "sigA", 0, "Deposit"), 0).await.unwrap();
+        store.save_log(&log_with_slot(10, "sigA", 1, "Withdraw"), 0).await.unwrap();
+        store.save_log(&log_with_slot(12, "sigB", 0, "Deposit"), 0).await.unwrap();
+        store.save_log(&log_with_slot(15, "sigC", 0, "Deposit"), 0).await.unwrap();
+
+        let deposits = store
+            .query_logs(
+                101,
+                &EventQuery {
+                    event_name: Some("Deposit".to_string()),
+                    from_slot: Some(11),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            deposits.iter().map(|r| r.slot).collect::<Vec<_>>(),
+            vec![12, 15]
+        );
+
+        let by_signature = store
+            .query_logs(
+                101,
+                &EventQuery {
+                    signature: Some("sigA".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(by_signature.len(), 2);
+
+        let other_chain = store.query_logs(102, &EventQuery::default()).await.unwrap();
+        assert!(other_chain.is_empty());
+    }
+
+    #[tokio::test]
+    async fn event_store_saves_are_idempotent_and_reorg_deletes_slot() {
+        let store = InMemoryEventStore::new();
+        store.save_log(&log_with_slot(10, "sigA", 0, "Deposit"), 0).await.unwrap();
+        store.save_log(&log_with_slot(10, "sigA", 0, "Deposit"), 0).await.unwrap();
+        store.save_transaction(101, &transaction(10, "sigA"), 0).await.unwrap();
+        store.save_transaction(101, &transaction(10, "sigA"), 0).await.unwrap();
+        store.save_transaction(101, &transaction(11, "sigB"), 0).await.unwrap();
+
+        assert_eq!(store.query_logs(101, &EventQuery::default()).await.unwrap().len(), 1);
+        let in_program = store
+            .query_transactions(
+                101,
+                &TransactionQuery {
+                    program_id: Some("Prog1111".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(in_program.len(), 2);
+
+        store.delete_slot_events(101, 10, 0).await.unwrap();
+
+        assert!(store.query_logs(101, &EventQuery::default()).await.unwrap().is_empty());
+        assert!(store.get_transaction(101, "sigA").await.unwrap().is_none());
+        assert!(store.get_transaction(101, "sigB").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn event_store_retention_drops_old_slots() {
+        let store = InMemoryEventStore::with_retention(2);
+        store.save_log(&log_with_slot(10, "sigA", 0, "Deposit"), 0).await.unwrap();
+        store.save_transaction(101, &transaction(11, "sigB"), 0).await.unwrap();
+        store.save_log(&log_with_slot(12, "sigC", 0, "Deposit"), 0).await.unwrap();
+
+        let slots: Vec<_> = store
+            .query_logs(101, &EventQuery::default())
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| r.slot)
+            .collect();
+        assert_eq!(slots, vec![12]);
+        assert!(store.get_transaction(101, "sigB").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn event_store_rejects_writes_from_stale_epochs() {
+        let store = InMemoryEventStore::new();
+        store.save_log(&log_with_slot(10, "sigA", 0, "Deposit"), 2).await.unwrap();
+
+        assert!(matches!(
+            store.save_log(&log_with_slot(11, "sigB", 0, "Deposit"), 1).await,
+            Err(PersistenceError::LeaseLost)
+        ));
+        assert!(matches!(
+            store.delete_slot_events(101, 10, 1).await,
+            Err(PersistenceError::LeaseLost)
+        ));
+        assert_eq!(store.query_logs(101, &EventQuery::default()).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,539 @@
+use crate::chain_pollers::persistence::*;
+use crate::config::ChainId;
+use crate::clients::solana::SolanaProgramLog;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const CREATE_LEASES_TABLE: &str = "CREATE TABLE IF NOT EXISTS chain_poller_leases (
+    chain_id INTEGER PRIMARY KEY,
+    holder_id TEXT NOT NULL,
+    epoch INTEGER NOT NULL,
+    expires_at_ms INTEGER NOT NULL
+)";
+
+const CREATE_DEAD_LETTERS_TABLE: &str = "CREATE TABLE IF NOT EXISTS chain_poller_dead_letters (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chain_id INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    blockhash TEXT,
+    kind TEXT NOT NULL,
+    raw_log TEXT,
+    signature TEXT,
+    instruction_top_level_index INTEGER,
+    instruction_inner_index INTEGER,
+    account TEXT,
+    mint TEXT,
+    error TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    created_at_ms INTEGER NOT NULL
+)";
+
+const CREATE_DEAD_LETTER_EPOCHS_TABLE: &str = "CREATE TABLE IF NOT EXISTS chain_poller_dead_letter_epochs (
+    chain_id INTEGER PRIMARY KEY,
+    epoch INTEGER NOT NULL
+)";
+
+const DEAD_LETTER_COLUMNS: &str = "id, chain_id, slot, blockhash, kind, raw_log, signature,
+    instruction_top_level_index, instruction_inner_index, account, mint, error, attempts,
+    created_at_ms";
+
+fn sql_error(e: rusqlite::Error) -> PersistenceError {
+    PersistenceError::Other(e.to_string())
+}
+
+async fn with_conn<T, F>(conn: &Arc<Mutex<Connection>>, f: F) -> Result<T, PersistenceError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut Connection) -> Result<T, PersistenceError> + Send + 'static,
+{
+    let conn = conn.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = conn
+            .lock()
+            .map_err(|_| PersistenceError::Other("SQLite connection poisoned".to_string()))?;
+        f(&mut conn)
+    })
+    .await
+    .map_err(|e| PersistenceError::Other(e.to_string()))?
+}
+
+fn lease_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Lease> {
+    Ok(Lease {
+        chain_id: row.get::<_, i64>(0)? as ChainId,
+        holder_id: row.get(1)?,
+        epoch: row.get::<_, i64>(2)? as u64,
+        expires_at_ms: row.get::<_, i64>(3)? as u64,
+    })
+}
+
+/// `LeaseManager` backed by a SQLite database. Replicas sharing the database
+/// file elect a single holder per chain. SQLite files cannot be safely shared
+/// over network filesystems, so this only coordinates replicas on one host;
+/// replicas on separate hosts need a `LeaseManager` on a shared database.
+pub struct SqlLeaseManager {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqlLeaseManager {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, PersistenceError> {
+        let conn = Connection::open(path).map_err(sql_error)?;
+        conn.busy_timeout(Duration::from_secs(5)).map_err(sql_error)?;
+        Self::from_connection(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self, PersistenceError> {
+        Self::from_connection(Connection::open_in_memory().map_err(sql_error)?)
+    }
+
+    pub fn from_connection(conn: Connection) -> Result<Self, PersistenceError> {
+        conn.execute(CREATE_LEASES_TABLE, []).map_err(sql_error)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    async fn with_conn<T, F>(&self, f: F) -> Result<T, PersistenceError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        with_conn(&self.conn, move |conn| f(conn).map_err(sql_error)).await
+    }
+}
+
+#[async_trait::async_trait]
+impl LeaseManager for SqlLeaseManager {
+    async fn try_acquire(
+        &self,
+        chain_id: ChainId,
+        holder_id: &str,
+        ttl: Duration,
+    ) -> Result<Option<Lease>, PersistenceError> {
+        let holder_id = holder_id.to_string();
+        let now = unix_time_ms() as i64;
+        let expires_at_ms = now + ttl.as_millis() as i64;
+
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+
+            let inserted = tx.execute(
+                "INSERT INTO chain_poller_leases (chain_id, holder_id, epoch, expires_at_ms)
+                 VALUES (?1, ?2, 1, ?3)
+                 ON CONFLICT (chain_id) DO NOTHING",
+                params![chain_id as i64, holder_id, expires_at_ms],
+            )?;
+
+            if inserted == 0 {
+                tx.execute(
+                    "UPDATE chain_poller_leases
+                     SET epoch = CASE WHEN holder_id = ?2 THEN epoch ELSE epoch + 1 END,
+                         holder_id = ?2,
+                         expires_at_ms = ?3
+                     WHERE chain_id = ?1 AND (holder_id = ?2 OR expires_at_ms <= ?4)",
+                    params![chain_id as i64, holder_id, expires_at_ms, now],
+                )?;
+            }
+
+            let lease = tx
+                .query_row(
+                    "SELECT chain_id, holder_id, epoch, expires_at_ms
+                     FROM chain_poller_leases WHERE chain_id = ?1",
+                    params![chain_id as i64],
+                    lease_from_row,
+                )
+                .optional()?;
+            tx.commit()?;
+
+            Ok(lease.filter(|l| l.holder_id == holder_id))
+        })
+        .await
+    }
+
+    async fn validate_lease(&self, lease: &Lease) -> Result<(), PersistenceError> {
+        match self.get_lease(lease.chain_id).await? {
+            Some(current)
+                if current.holder_id == lease.holder_id
+                    && current.epoch == lease.epoch
+                    && !current.is_expired_at(unix_time_ms()) =>
+            {
+                Ok(())
+            }
+            _ => Err(PersistenceError::LeaseLost),
+        }
+    }
+
+    async fn release(&self, lease: &Lease) -> Result<(), PersistenceError> {
+        let lease = lease.clone();
+        self.with_conn(move |conn| {
+            conn.execute(
+                "UPDATE chain_poller_leases SET expires_at_ms = 0
+                 WHERE chain_id = ?1 AND holder_id = ?2 AND epoch = ?3",
+                params![lease.chain_id as i64, lease.holder_id, lease.epoch as i64],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_lease(&self, chain_id: ChainId) -> Result<Option<Lease>, PersistenceError> {
+        self.with_conn(move |conn| {
+            conn.query_row(
+                "SELECT chain_id, holder_id, epoch, expires_at_ms
+                 FROM chain_poller_leases WHERE chain_id = ?1",
+                params![chain_id as i64],
+                lease_from_row,
+            )
+            .optional()
+        })
+        .await
+    }
+}
+
+fn kind_to_str(kind: DeadLetterKind) -> &'static str {
+    match kind {
+        DeadLetterKind::Decode => "decode",
+        DeadLetterKind::Slot => "slot",
+        DeadLetterKind::Transaction => "transaction",
+        DeadLetterKind::Instruction => "instruction",
+        DeadLetterKind::Log => "log",
+        DeadLetterKind::ReorgSlot => "reorg_slot",
+        DeadLetterKind::AccountUpdate => "account_update",
+        DeadLetterKind::BalanceChange => "balance_change",
+    }
+}
+
+fn kind_from_str(kind: &str) -> Option<DeadLetterKind> {
+    Some(match kind {
+        "decode" => DeadLetterKind::Decode,
+        "slot" => DeadLetterKind::Slot,
+        "transaction" => DeadLetterKind::Transaction,
+        "instruction" => DeadLetterKind::Instruction,
+        "log" => DeadLetterKind::Log,
+        "reorg_slot" => DeadLetterKind::ReorgSlot,
+        "account_update" => DeadLetterKind::AccountUpdate,
+        "balance_change" => DeadLetterKind::BalanceChange,
+        _ => return None,
+    })
+}
+
+fn dead_letter_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<DeadLetter> {
+    let kind: String = row.get(4)?;
+    let kind = kind_from_str(&kind).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            4,
+            rusqlite::types::Type::Text,
+            format!("unknown dead letter kind {:?}", kind).into(),
+        )
+    })?;
+    let raw_log = row
+        .get::<_, Option<String>>(5)?
+        .map(|json| serde_json::from_str::<SolanaProgramLog>(&json))
+        .transpose()
+        .map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, e.into())
+        })?;
+    let instruction = row
+        .get::<_, Option<i64>>(7)?
+        .map(|top_level_index| -> rusqlite::Result<_> {
+            Ok(InstructionPosition {
+                top_level_index: top_level_index as usize,
+                inner_index: row.get::<_, Option<i64>>(8)?.map(|i| i as usize),
+            })
+        })
+        .transpose()?;
+
+    Ok(DeadLetter {
+        id: row.get::<_, i64>(0)? as u64,
+        chain_id: row.get::<_, i64>(1)? as ChainId,
+        slot: row.get::<_, i64>(2)? as u64,
+        blockhash: row.get(3)?,
+        kind,
+        raw_log,
+        signature: row.get(6)?,
+        instruction,
+        account: row.get(9)?,
+        mint: row.get(10)?,
+        error: row.get(11)?,
+        attempts: row.get::<_, i64>(12)? as u32,
+        created_at_ms: row.get::<_, i64>(13)? as u64,
+    })
+}
+
+/// Records `epoch` for the chain inside `tx`, or fails with `LeaseLost` if a
+/// newer epoch has already written.
+fn admit_epoch(
+    tx: &rusqlite::Transaction<'_>,
+    chain_id: ChainId,
+    epoch: u64,
+) -> Result<(), PersistenceError> {
+    let fenced: Option<i64> = tx
+        .query_row(
+            "SELECT epoch FROM chain_poller_dead_letter_epochs WHERE chain_id = ?1",
+            params![chain_id as i64],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(sql_error)?;
+    if fenced.is_some_and(|fenced| epoch < fenced as u64) {
+        return Err(PersistenceError::LeaseLost);
+    }
+    tx.execute(
+        "INSERT INTO chain_poller_dead_letter_epochs (chain_id, epoch) VALUES (?1, ?2)
+         ON CONFLICT (chain_id) DO UPDATE SET epoch = excluded.epoch",
+        params![chain_id as i64, epoch as i64],
+    )
+    .map_err(sql_error)?;
+    Ok(())
+}
+
+/// `DeadLetterStore` backed by a SQLite database. Epoch checks run in the
+/// same transaction as the write they guard.
+pub struct SqlDeadLetterStore {
+    conn: Arc<Mutex<Connection>>,
+    closed: AtomicBool,
+}
+
+impl SqlDeadLetterStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, PersistenceError> {
+        let conn = Connection::open(path).map_err(sql_error)?;
+        conn.busy_timeout(Duration::from_secs(5)).map_err(sql_error)?;
+        Self::from_connection(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self, PersistenceError> {
+        Self::from_connection(Connection::open_in_memory().map_err(sql_error)?)
+    }
+
+    pub fn from_connection(conn: Connection) -> Result<Self, PersistenceError> {
+        conn.execute(CREATE_DEAD_LETTERS_TABLE, []).map_err(sql_error)?;
+        conn.execute(CREATE_DEAD_LETTER_EPOCHS_TABLE, [])
+            .map_err(sql_error)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    async fn with_conn<T, F>(&self, f: F) -> Result<T, PersistenceError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T, PersistenceError> + Send + 'static,
+    {
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(PersistenceError::StoreClosed);
+        }
+        with_conn(&self.conn, f).await
+    }
+
+    /// Runs `f` in an immediate transaction after admitting `epoch` for the
+    /// chain, committing only if `f` succeeds.
+    async fn write_fenced<T, F>(
+        &self,
+        chain_id: ChainId,
+        epoch: u64,
+        f: F,
+    ) -> Result<T, PersistenceError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&rusqlite::Transaction<'_>) -> Result<T, PersistenceError> + Send + 'static,
+    {
+        self.with_conn(move |conn| {
+            let tx = conn
+                .transaction_with_behavior(TransactionBehavior::Immediate)
+                .map_err(sql_error)?;
+            admit_epoch(&tx, chain_id, epoch)?;
+            let result = f(&tx)?;
+            tx.commit().map_err(sql_error)?;
+            Ok(result)
+        })
+        .await
+    }
+}
+
+#[async_trait::async_trait]
+impl DeadLetterStore for SqlDeadLetterStore {
+    async fn save_dead_letter(
+        &self,
+        dead_letter: &DeadLetter,
+        epoch: u64,
+    ) -> Result<u64, PersistenceError> {
+        let dead_letter = dead_letter.clone();
+        let raw_log = dead_letter
+            .raw_log
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| PersistenceError::Other(e.to_string()))?;
+        self.write_fenced(dead_letter.chain_id, epoch, move |tx| {
+            tx.execute(
+                "INSERT INTO chain_poller_dead_letters (chain_id, slot, blockhash, kind, raw_log,
+                     signature, instruction_top_level_index, instruction_inner_index, account,
+                     mint, error, attempts, created_at_ms)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                params![
+                    dead_letter.chain_id as i64,
+                    dead_letter.slot as i64,
+                    dead_letter.blockhash,
+                    kind_to_str(dead_letter.kind),
+                    raw_log,
+                    dead_letter.signature,
+                    dead_letter.instruction.map(|p| p.top_level_index as i64),
+                    dead_letter
+                        .instruction
+                        .and_then(|p| p.inner_index)
+                        .map(|i| i as i64),
+                    dead_letter.account,
+                    dead_letter.mint,
+                    dead_letter.error,
+                    dead_letter.attempts as i64,
+                    dead_letter.created_at_ms as i64,
+                ],
+            )
+            .map_err(sql_error)?;
+            Ok(tx.last_insert_rowid() as u64)
+        })
+        .await
+    }
+
+    async fn get_dead_letter(&self, id: u64) -> Result<Option<DeadLetter>, PersistenceError> {
+        self.with_conn(move |conn| {
+            conn.query_row(
+                &format!(
+                    "SELECT {} FROM chain_poller_dead_letters WHERE id = ?1",
+                    DEAD_LETTER_COLUMNS
+                ),
+                params![id as i64],
+                dead_letter_from_row,
+            )
+            .optional()
+            .map_err(sql_error)
+        })
+        .await
+    }
+
+    async fn list_dead_letters(
+        &self,
+        chain_id: ChainId,
+    ) -> Result<Vec<DeadLetter>, PersistenceError> {
+        self.with_conn(move |conn| {
+            let mut statement = conn
+                .prepare(&format!(
+                    "SELECT {} FROM chain_poller_dead_letters WHERE chain_id = ?1 ORDER BY id",
+                    DEAD_LETTER_COLUMNS
+                ))
+                .map_err(sql_error)?;
+            let rows = statement
+                .query_map(params![chain_id as i64], dead_letter_from_row)
+                .map_err(sql_error)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>().map_err(sql_error)
+        })
+        .await
+    }
+
+    async fn update_dead_letter(
+        &self,
+        dead_letter: &DeadLetter,
+        epoch: u64,
+    ) -> Result<(), PersistenceError> {
+        let dead_letter = dead_letter.clone();
+        let raw_log = dead_letter
+            .raw_log
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| PersistenceError::Other(e.to_string()))?;
+        self.write_fenced(dead_letter.chain_id, epoch, move |tx| {
+            let updated = tx
+                .execute(
+                    "UPDATE chain_poller_dead_letters
+                     SET chain_id = ?2, slot = ?3, blockhash = ?4, kind = ?5, raw_log = ?6,
+                         signature = ?7, instruction_top_level_index = ?8,
+                         instruction_inner_index = ?9, account = ?10, mint = ?11,
+                         error = ?12, attempts = ?13, created_at_ms = ?14
+                     WHERE id = ?1",
+                    params![
+                        dead_letter.id as i64,
+                        dead_letter.chain_id as i64,
+                        dead_letter.slot as i64,
+                        dead_letter.blockhash,
+                        kind_to_str(dead_letter.kind),
+                        raw_log,
+                        dead_letter.signature,
+                        dead_letter.instruction.map(|p| p.top_level_index as i64),
+                        dead_letter
+                            .instruction
+                            .and_then(|p| p.inner_index)
+                            .map(|i| i as i64),
+                        dead_letter.account,
+                        dead_letter.mint,
+                        dead_letter.error,
+                        dead_letter.attempts as i64,
+                        dead_letter.created_at_ms as i64,
+                    ],
+                )
+                .map_err(sql_error)?;
+            if updated == 0 {
+                return Err(PersistenceError::NotFound);
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete_dead_letter(&self, id: u64, epoch: u64) -> Result<(), PersistenceError> {
+        let chain_id = self
+            .get_dead_letter(id)
+            .await?
+            .map(|d| d.chain_id)
+            .ok_or(PersistenceError::NotFound)?;
+        self.write_fenced(chain_id, epoch, move |tx| {
+            let deleted = tx
+                .execute(
+                    "DELETE FROM chain_poller_dead_letters WHERE id = ?1",
+                    params![id as i64],
+                )
+                .map_err(sql_error)?;
+            if deleted == 0 {
+                return Err(PersistenceError::NotFound);
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn close(&self) -> Result<(), PersistenceError> {
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return Err(PersistenceError::StoreClosed);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain_pollers::persistence::conformance::{
+        run_dead_letter_conformance_suite, run_lease_conformance_suite,
+    };
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn sqlite_lease_manager_passes_conformance_suite() {
+        run_lease_conformance_suite(|| async {
+            let manager = SqlLeaseManager::open_in_memory().expect("failed to open sqlite");
+            Arc::new(manager) as Arc<dyn LeaseManager>
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn sqlite_dead_letter_store_passes_conformance_suite() {
+        run_dead_letter_conformance_suite(|| async {
+            let store = SqlDeadLetterStore::open_in_memory().expect("failed to open sqlite");
+            Arc::new(store) as Arc<dyn DeadLetterStore>
+        })
+        .await;
+    }
+}
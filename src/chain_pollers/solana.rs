@@ -1,14 +1,112 @@
+use crate::chain_pollers::accounts::AccountTracker;
+use crate::chain_pollers::filter::TransactionFilter;
 use crate::chain_pollers::persistence::*;
-use crate::clients::solana::{Client, SolanaSlot};
+use crate::chain_pollers::stream::{EventSubscribers, IndexerEvent, IndexerEventStream};
+use crate::clients::solana::{BalanceChange, Client, SolanaAccount, SolanaProgramLog, SolanaSlot, SolanaTransaction};
 use crate::config::ChainId;
-use crate::contract_store::ContractStore;
-use crate::transaction_log_parser::LogParser;
+use crate::transaction_log_parser::{
+    merge_cpi_event_logs, parse_compute_units, InvocationTree, parse_return_data, DecodedInstruction, DecodedLog,
+    InstructionPosition, InstructionWithContext, LogParser, OnChainIdlFetcher, ReturnData,
+};
 use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::Mutex;
 use tokio::time::interval;
 use tracing::{debug, error, info, warn};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandlerFailureAction {
+    /// Log the failure and carry on with the next slot or log.
+    Skip,
+    /// Write the failure to the poller's `DeadLetterStore` and carry on.
+    /// Halts instead if no dead-letter store is configured.
+    DeadLetter,
+    /// Stop the poller; `start` returns the handler error.
+    Halt,
+}
+
+/// What `SolanaChainPoller` does with transactions whose logs the validator
+/// truncated. Affected logs are always flagged with `DecodedLog::truncated`.
+/// `emit_cpi!` events are unaffected: they are read from inner instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TruncatedLogRecovery {
+    /// Deliver the truncated logs as they are.
+    #[default]
+    FlagOnly,
+    /// Re-fetch the transaction's log messages through
+    /// `Client::get_archived_log_messages` and use them if they are complete.
+    Archival,
+}
+
+/// What `SolanaChainPoller` does with logs of failed transactions. Their
+/// state changes were rolled back, but their logs are still recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FailedTransactionLogs {
+    /// Deliver them like any other log, with `LogWithSlot::success` false.
+    #[default]
+    Include,
+    /// Skip them before decoding.
+    Drop,
+    /// Deliver them only to `SlotHandler::handle_failed_log`; they are not
+    /// saved to the event store or emitted to subscribers.
+    Route,
+}
+
+/// Holds back delivery of processed slots until a reorg can no longer orphan
+/// them, for consumers that cannot undo what they receive. Slots are still
+/// fetched, checked for reorgs and recorded as they arrive, but their
+/// handler calls, logs, event store writes and subscriber events wait in the
+/// buffer. A slot is released once either condition holds; orphaned slots
+/// still in the buffer are discarded without calling
+/// `SlotHandler::handle_reorg_slot`.
+///
+/// The buffer is rebuilt from the chain on restart, using the store's last
+/// released slot, so it never holds slots older than `slot_history_size`:
+/// fetching pauses until the oldest buffered slot is released. At least one
+/// condition must be set, and `depth` may not exceed `slot_history_size`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FinalityBuffer {
+    /// Release a slot once this many slots have been processed after it.
+    pub depth: Option<u64>,
+    /// Release a slot once the node reports it at finalized commitment.
+    pub finalized: bool,
+}
+
+/// How `SolanaChainPoller` reacts when a `SlotHandler` hook returns an error.
+/// The hook is retried `max_retries` times, doubling `retry_backoff` after
+/// each attempt, before `on_exhausted` is applied.
+///
+/// The default halts, including for `handle_slot` and `handle_reorg_slot`
+/// errors that earlier versions logged and ignored; use
+/// `HandlerFailureAction::Skip` to keep going.
+#[derive(Debug, Clone)]
+pub struct HandlerErrorPolicy {
+    pub max_retries: u32,
+    pub retry_backoff: Duration,
+    pub on_exhausted: HandlerFailureAction,
+}
+
+impl Default for HandlerErrorPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+            on_exhausted: HandlerFailureAction::Halt,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PollerError {
+    #[error("Poller halted by handler error policy: {0}")]
+    Halted(String),
+}
+
 pub struct SolanaChainPollerConfig {
     pub chain_id: ChainId,
     pub polling_interval: Duration,
@@ -16,6 +114,16 @@ pub struct SolanaChainPollerConfig {
     pub max_reorg_depth: usize,
     pub slot_history_size: usize,
     pub reorg_check_enabled: bool,
+    pub lease_ttl: Duration,
+    pub handler_error_policy: HandlerErrorPolicy,
+    pub truncated_log_recovery: TruncatedLogRecovery,
+    pub failed_transaction_logs: FailedTransactionLogs,
+    /// Replaces the interesting-program rule for which transactions are
+    /// delivered. Non-matching transactions and their logs never reach
+    /// handlers, but stay in the slot for account tracking and reorg checks.
+    pub transaction_filter: Option<TransactionFilter>,
+    /// Delay delivery until slots are final; `None` delivers immediately.
+    pub finality_buffer: Option<FinalityBuffer>,
 }
 
 impl Default for SolanaChainPollerConfig {
@@ -27,6 +135,12 @@ impl Default for SolanaChainPollerConfig {
             max_reorg_depth: 10,
             slot_history_size: 100,
             reorg_check_enabled: true,
+            lease_ttl: Duration::from_secs(30),
+            handler_error_policy: HandlerErrorPolicy::default(),
+            truncated_log_recovery: TruncatedLogRecovery::default(),
+            failed_transaction_logs: FailedTransactionLogs::default(),
+            transaction_filter: None,
+            finality_buffer: None,
         }
     }
 }
@@ -35,9 +149,27 @@ pub struct SolanaChainPoller {
     client: Arc<dyn Client>,
     log_parser: Arc<dyn LogParser>,
     config: SolanaChainPollerConfig,
-    contract_store: Arc<dyn ContractStore>,
     store: Arc<dyn ChainPollerPersistence>,
     slot_handler: Arc<dyn SlotHandler>,
+    event_store: Option<Arc<dyn EventStore>>,
+    dead_letter_store: Option<Arc<dyn DeadLetterStore>>,
+    idl_fetcher: Option<Arc<OnChainIdlFetcher>>,
+    account_tracker: Option<Arc<AccountTracker>>,
+    lease_manager: Option<Arc<dyn LeaseManager>>,
+    holder_id: String,
+    current_lease: Mutex<Option<Lease>>,
+    subscribers: EventSubscribers,
+    buffered_slots: Mutex<VecDeque<SolanaSlot>>,
+    /// Set until the finality buffer has been restored from the store, and
+    /// again whenever the lease is (re)acquired, since another replica may
+    /// have released slots in the meantime.
+    buffer_stale: AtomicBool,
+    /// Set until tracked accounts have been snapshotted, and again whenever
+    /// the lease is (re)acquired, since writes made while another replica
+    /// held it were never seen by this tracker.
+    accounts_stale: AtomicBool,
+    /// Highest slot announced with `IndexerEvent::Finalized`.
+    last_finalized: AtomicU64,
 }
 
 impl SolanaChainPoller {
@@ -45,10 +177,9 @@ impl SolanaChainPoller {
         client: Arc<dyn Client>,
         log_parser: Arc<dyn LogParser>,
         config: SolanaChainPollerConfig,
-        contract_store: Arc<dyn ContractStore>,
         store: Arc<dyn ChainPollerPersistence>,
         slot_handler: Arc<dyn SlotHandler>,
-    ) -> Self {
+    ) -> Result<Self> {
         let mut config = config;
         if config.max_reorg_depth == 0 {
             config.max_reorg_depth = 10;
@@ -59,6 +190,18 @@ impl SolanaChainPoller {
         if !config.reorg_check_enabled && config.max_reorg_depth > 0 {
             config.reorg_check_enabled = true;
         }
+        if let Some(buffer) = config.finality_buffer {
+            if buffer.depth.is_none() && !buffer.finalized {
+                anyhow::bail!("Finality buffer needs a depth, finalized release or both");
+            }
+            if buffer.depth.is_some_and(|depth| depth > config.slot_history_size as u64) {
+                anyhow::bail!(
+                    "Finality buffer depth {} exceeds slot history size {}",
+                    buffer.depth.unwrap_or_default(),
+                    config.slot_history_size
+                );
+            }
+        }
 
         info!(
             chain_id = config.chain_id,
@@ -70,14 +213,63 @@ impl SolanaChainPoller {
             info!("InterestingProgram {}: {}", i, program);
         }
 
-        Self {
+        Ok(Self {
             client,
             log_parser,
             config,
-            contract_store,
             store,
             slot_handler,
-        }
+            event_store: None,
+            dead_letter_store: None,
+            idl_fetcher: None,
+            account_tracker: None,
+            lease_manager: None,
+            holder_id: String::new(),
+            current_lease: Mutex::new(None),
+            subscribers: EventSubscribers::default(),
+            buffered_slots: Mutex::new(VecDeque::new()),
+            buffer_stale: AtomicBool::new(true),
+            accounts_stale: AtomicBool::new(true),
+            last_finalized: AtomicU64::new(0),
+        })
+    }
+
+    pub fn set_event_store(&mut self, event_store: Arc<dyn EventStore>) {
+        self.event_store = Some(event_store);
+    }
+
+    pub fn set_dead_letter_store(&mut self, dead_letter_store: Arc<dyn DeadLetterStore>) {
+        self.dead_letter_store = Some(dead_letter_store);
+    }
+
+    /// Refresh the on-chain IDLs of interesting programs before each slot's
+    /// logs are decoded. The fetcher's `IdlStore` should be the one the log
+    /// parser decodes with.
+    pub fn set_idl_fetcher(&mut self, idl_fetcher: Arc<OnChainIdlFetcher>) {
+        self.idl_fetcher = Some(idl_fetcher);
+    }
+
+    /// Track the accounts owned by interesting programs and deliver their
+    /// changes to `SlotHandler::handle_account_update`.
+    pub fn set_account_tracker(&mut self, account_tracker: Arc<AccountTracker>) {
+        self.account_tracker = Some(account_tracker);
+    }
+
+    /// Returns a stream of `IndexerEvent`s backed by a channel holding at most
+    /// `capacity` events. The poller waits whenever a subscriber's channel is
+    /// full, so every subscriber must be polled for the poller to progress.
+    pub fn subscribe(&mut self, capacity: usize) -> IndexerEventStream {
+        self.subscribers.subscribe(capacity)
+    }
+
+    /// Only process slots while holding the chain's lease. Replicas must share
+    /// the same `ChainPollerPersistence` so a standby resumes from the
+    /// primary's last processed slot. `lease_ttl` must exceed the time it
+    /// takes to process one slot. Handlers see a slot again after a failover
+    /// unless they commit it through `SlotHandler::commit_slot`.
+    pub fn set_lease_manager(&mut self, lease_manager: Arc<dyn LeaseManager>, holder_id: String) {
+        self.lease_manager = Some(lease_manager);
+        self.holder_id = holder_id;
     }
 
     pub async fn start(&self) -> Result<()> {
@@ -104,8 +296,7 @@ impl SolanaChainPoller {
                 .context("Error getting latest slot")?;
 
             let last_canon_slot = self
-                .client
-                .get_slot_by_number(latest_slot)
+                .fetch_slot(latest_slot)
                 .await
                 .context("Couldn't get last canonical slot")?;
 
@@ -118,7 +309,7 @@ impl SolanaChainPoller {
             };
 
             self.store
-                .save_slot(&record)
+                .save_slot(&record, self.lease_epoch().await)
                 .await
                 .context("Failed to save last processed slot")?;
 
@@ -131,23 +322,446 @@ impl SolanaChainPoller {
             last_slot_record.slot
         );
 
-        self.poll_for_slots().await;
-
-        Ok(())
+        self.poll_for_slots().await
     }
 
-    async fn poll_for_slots(&self) {
+    async fn poll_for_slots(&self) -> Result<()> {
         info!("Starting Solana Chain Listener poll loop");
         let mut interval = interval(self.config.polling_interval);
 
         loop {
             interval.tick().await;
+            if !self.hold_lease().await {
+                continue;
+            }
             if let Err(e) = self.process_next_slot().await {
+                if e.is::<PollerError>() {
+                    error!("Stopping Solana Chain Listener: {:#}", e);
+                    return Err(e);
+                }
                 error!("Error processing next slot: {}", e);
             }
         }
     }
 
+    async fn call_handler<F, Fut>(&self, mut call: F) -> (Result<()>, u32)
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let policy = &self.config.handler_error_policy;
+        let mut backoff = policy.retry_backoff;
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            match call().await {
+                Ok(()) => return (Ok(()), attempts),
+                Err(e) if attempts > policy.max_retries => return (Err(e), attempts),
+                Err(e) => {
+                    warn!(
+                        attempt = attempts,
+                        backoff = ?backoff,
+                        error = %e,
+                        "Handler failed, retrying"
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2);
+                }
+            }
+        }
+    }
+
+    fn new_dead_letter(&self, kind: DeadLetterKind, slot_number: u64, attempts: u32) -> DeadLetter {
+        DeadLetter {
+            id: 0,
+            chain_id: self.config.chain_id,
+            slot: slot_number,
+            blockhash: None,
+            kind,
+            raw_log: None,
+            signature: None,
+            instruction: None,
+            account: None,
+            mint: None,
+            error: String::new(),
+            attempts,
+            created_at_ms: unix_time_ms(),
+        }
+    }
+
+    fn new_slot_dead_letter(
+        &self,
+        kind: DeadLetterKind,
+        slot: &SolanaSlot,
+        attempts: u32,
+    ) -> DeadLetter {
+        let mut dead_letter = self.new_dead_letter(kind, slot.slot, attempts);
+        dead_letter.blockhash = Some(slot.blockhash.clone());
+        dead_letter
+    }
+
+    async fn apply_handler_failure_policy(
+        &self,
+        dead_letter: DeadLetter,
+        err: anyhow::Error,
+    ) -> Result<()> {
+        let action = self.config.handler_error_policy.on_exhausted;
+        error!(
+            kind = ?dead_letter.kind,
+            slot = dead_letter.slot,
+            signature = dead_letter.signature.as_deref(),
+            log_index = dead_letter.raw_log.as_ref().map(|l| l.log_index),
+            attempts = dead_letter.attempts,
+            action = ?action,
+            error = %err,
+            "Handler failed after all retries"
+        );
+
+        if action == HandlerFailureAction::Skip {
+            return Ok(());
+        }
+
+        if action == HandlerFailureAction::DeadLetter {
+            if self.dead_letter_store.is_some() {
+                return self.save_dead_letter(dead_letter, &err).await;
+            }
+            error!("No dead-letter store configured, halting poller");
+        }
+
+        Err(PollerError::Halted(format!(
+            "{:?} handler failed for slot {}: {:#}",
+            dead_letter.kind, dead_letter.slot, err
+        ))
+        .into())
+    }
+
+    async fn save_dead_letter(&self, mut dead_letter: DeadLetter, err: &anyhow::Error) -> Result<()> {
+        let Some(dead_letter_store) = &self.dead_letter_store else {
+            anyhow::bail!("No dead-letter store configured");
+        };
+
+        dead_letter.error = format!("{:#}", err);
+        let id = dead_letter_store
+            .save_dead_letter(&dead_letter, self.lease_epoch().await)
+            .await
+            .context("Failed to save dead letter")?;
+        warn!(
+            id = id,
+            kind = ?dead_letter.kind,
+            slot = dead_letter.slot,
+            "Dead-lettered failed item"
+        );
+        Ok(())
+    }
+
+    /// Undecodable logs are always dead-lettered when a dead-letter store is
+    /// configured, since decoding them again cannot succeed without a fix.
+    /// Otherwise they follow the handler error policy's `on_exhausted` action.
+    async fn handle_decode_failure(
+        &self,
+        slot: &SolanaSlot,
+        log: &SolanaProgramLog,
+        err: anyhow::Error,
+    ) -> Result<()> {
+        let mut dead_letter = self.new_slot_dead_letter(DeadLetterKind::Decode, slot, 1);
+        dead_letter.signature = Some(log.signature.clone());
+        dead_letter.raw_log = Some(log.clone());
+
+        if self.dead_letter_store.is_some() {
+            error!(
+                slot = slot.slot,
+                signature = log.signature,
+                log_index = log.log_index,
+                error = %err,
+                "Failed to decode log"
+            );
+            return self.save_dead_letter(dead_letter, &err).await;
+        }
+
+        self.apply_handler_failure_policy(dead_letter, err).await
+    }
+
+    /// Replays every dead letter for this chain, oldest first, and returns how
+    /// many succeeded. Successful items are removed from the store; failed
+    /// ones stay with their attempt count and error updated.
+    pub async fn replay_dead_letters(&self) -> Result<usize> {
+        let dead_letter_store = self
+            .dead_letter_store
+            .as_ref()
+            .context("No dead-letter store configured")?;
+
+        let dead_letters = dead_letter_store
+            .list_dead_letters(self.config.chain_id)
+            .await
+            .context("Failed to list dead letters")?;
+
+        let mut replayed = 0;
+        for dead_letter in dead_letters {
+            match self.replay_dead_letter(dead_letter.id).await {
+                Ok(()) => replayed += 1,
+                Err(e) => warn!(id = dead_letter.id, error = %e, "Dead letter replay failed"),
+            }
+        }
+
+        Ok(replayed)
+    }
+
+    pub async fn replay_dead_letter(&self, id: u64) -> Result<()> {
+        let dead_letter_store = self
+            .dead_letter_store
+            .as_ref()
+            .context("No dead-letter store configured")?;
+
+        let mut dead_letter = dead_letter_store
+            .get_dead_letter(id)
+            .await
+            .context("Failed to get dead letter")?
+            .context("Dead letter not found")?;
+
+        info!(
+            id = id,
+            kind = ?dead_letter.kind,
+            slot = dead_letter.slot,
+            attempts = dead_letter.attempts,
+            "Replaying dead letter"
+        );
+
+        match self.replay(&dead_letter).await {
+            Ok(()) => {
+                dead_letter_store
+                    .delete_dead_letter(id, self.lease_epoch().await)
+                    .await
+                    .context("Failed to delete replayed dead letter")?;
+                Ok(())
+            }
+            Err(e) => {
+                dead_letter.attempts += 1;
+                dead_letter.error = format!("{:#}", e);
+                dead_letter_store
+                    .update_dead_letter(&dead_letter, self.lease_epoch().await)
+                    .await
+                    .context("Failed to update dead letter")?;
+                Err(e)
+            }
+        }
+    }
+
+    async fn replay(&self, dead_letter: &DeadLetter) -> Result<()> {
+        match dead_letter.kind {
+            DeadLetterKind::Decode | DeadLetterKind::Log => {
+                let raw_log = dead_letter
+                    .raw_log
+                    .as_ref()
+                    .context("Dead letter has no raw log")?;
+                let slot = self.fetch_replay_slot(dead_letter).await?;
+                if !self.is_delivered_log(&slot, raw_log) {
+                    return Ok(());
+                }
+                let decoded_log = self
+                    .log_parser
+                    .decode_log(&raw_log.program_id, raw_log)
+                    .await
+                    .context("Failed to decode log")?;
+                let log_with_slot = self.log_with_slot(&slot, raw_log, decoded_log).await;
+                self.deliver_log(&log_with_slot).await?;
+                // Logs whose handler failed were already published when their
+                // slot was processed; undecodable ones never were.
+                if dead_letter.kind == DeadLetterKind::Decode {
+                    self.publish_log(log_with_slot).await?;
+                }
+                Ok(())
+            }
+            DeadLetterKind::Slot => {
+                let slot = self.fetch_replay_slot(dead_letter).await?;
+                self.slot_handler.handle_slot(&slot).await
+            }
+            DeadLetterKind::Transaction | DeadLetterKind::Instruction => {
+                let signature = dead_letter
+                    .signature
+                    .as_deref()
+                    .context("Dead letter has no signature")?;
+                let slot = self.fetch_replay_slot(dead_letter).await?;
+                let transaction = slot
+                    .transactions
+                    .iter()
+                    .find(|t| t.signature == signature)
+                    .context("Transaction not found in slot")?;
+
+                if dead_letter.kind == DeadLetterKind::Transaction {
+                    return self.slot_handler.handle_transaction(transaction, &slot).await;
+                }
+
+                let position = dead_letter
+                    .instruction
+                    .context("Dead letter has no instruction position")?;
+                let mut instruction = InstructionWithContext::from_transaction(transaction)
+                    .into_iter()
+                    .find(|i| i.position == position)
+                    .context("Instruction not found in transaction")?;
+                instruction.decoded = self.decode_instruction(&instruction).await;
+                self.slot_handler.handle_instruction(&instruction).await
+            }
+            DeadLetterKind::ReorgSlot => self.slot_handler.handle_reorg_slot(dead_letter.slot).await,
+            DeadLetterKind::AccountUpdate => {
+                let pubkey = dead_letter
+                    .account
+                    .as_deref()
+                    .context("Dead letter has no account")?;
+                let account = self
+                    .client
+                    .get_account_info(pubkey)
+                    .await
+                    .context("Failed to fetch account for replay")?;
+                let program_id = match &account {
+                    Some(account) => account.owner.clone(),
+                    None => self
+                        .account_tracker
+                        .as_ref()
+                        .and_then(|t| t.latest(pubkey))
+                        .map(|u| u.program_id)
+                        .unwrap_or_default(),
+                };
+                let update = self
+                    .account_update(pubkey, &program_id, dead_letter.slot, account, AccountUpdateSource::Replay)
+                    .await;
+                self.slot_handler.handle_account_update(&update).await
+            }
+            DeadLetterKind::BalanceChange => {
+                let signature = dead_letter
+                    .signature
+                    .as_deref()
+                    .context("Dead letter has no signature")?;
+                let account = dead_letter
+                    .account
+                    .as_deref()
+                    .context("Dead letter has no account")?;
+                let slot = self.fetch_replay_slot(dead_letter).await?;
+                let transaction = find_transaction(&slot, signature)
+                    .context("Transaction not found in slot")?;
+                let change = BalanceChange::from_transaction(transaction)
+                    .into_iter()
+                    .find(|c| c.account == account && c.mint == dead_letter.mint)
+                    .context("Balance change not found in transaction")?;
+                self.slot_handler.handle_balance_change(&change).await
+            }
+        }
+    }
+
+    /// Refetches a dead letter's slot, failing if a reorg has replaced the
+    /// block the dead letter was recorded for.
+    async fn fetch_replay_slot(&self, dead_letter: &DeadLetter) -> Result<SolanaSlot> {
+        let slot = self
+            .fetch_slot(dead_letter.slot)
+            .await
+            .context("Failed to fetch slot for replay")?;
+        if let Some(blockhash) = &dead_letter.blockhash {
+            if *blockhash != slot.blockhash {
+                anyhow::bail!(
+                    "Slot {} was orphaned: dead letter was recorded for block {}, slot is now block {}",
+                    dead_letter.slot,
+                    blockhash,
+                    slot.blockhash
+                );
+            }
+        }
+        Ok(slot)
+    }
+
+    async fn fetch_slot(&self, slot_number: u64) -> Result<SolanaSlot> {
+        let mut slot = self.client.get_slot_by_number(slot_number).await?;
+        slot.chain_id = self.config.chain_id;
+        Ok(slot)
+    }
+
+    async fn hold_lease(&self) -> bool {
+        let Some(lease_manager) = &self.lease_manager else {
+            return true;
+        };
+
+        let mut current_lease = self.current_lease.lock().await;
+        let acquired = match lease_manager
+            .try_acquire(self.config.chain_id, &self.holder_id, self.config.lease_ttl)
+            .await
+        {
+            Ok(acquired) => acquired,
+            Err(e) => {
+                error!("Failed to acquire or renew lease: {}", e);
+                None
+            }
+        };
+
+        match (&*current_lease, &acquired) {
+            (None, Some(lease)) => info!(
+                chain_id = self.config.chain_id,
+                holder_id = self.holder_id,
+                epoch = lease.epoch,
+                "Acquired poller lease"
+            ),
+            (Some(previous), Some(lease)) if previous.epoch != lease.epoch => warn!(
+                chain_id = self.config.chain_id,
+                holder_id = self.holder_id,
+                previous_epoch = previous.epoch,
+                epoch = lease.epoch,
+                "Re-acquired poller lease after losing it"
+            ),
+            (Some(previous), None) => warn!(
+                chain_id = self.config.chain_id,
+                holder_id = self.holder_id,
+                epoch = previous.epoch,
+                "Lost poller lease, standing by"
+            ),
+            (None, None) => debug!(
+                chain_id = self.config.chain_id,
+                holder_id = self.holder_id,
+                "Poller lease held by another replica, standing by"
+            ),
+            _ => {}
+        }
+
+        let newly_acquired = match (&*current_lease, &acquired) {
+            (None, Some(_)) => true,
+            (Some(previous), Some(lease)) => previous.epoch != lease.epoch,
+            _ => false,
+        };
+        if newly_acquired {
+            self.buffer_stale.store(true, Ordering::SeqCst);
+            self.accounts_stale.store(true, Ordering::SeqCst);
+        }
+
+        let holds_lease = acquired.is_some();
+        *current_lease = acquired;
+        holds_lease
+    }
+
+    /// Epoch the poller's writes are fenced with: the held lease's, or 0
+    /// without a lease manager.
+    async fn lease_epoch(&self) -> u64 {
+        self.current_lease
+            .lock()
+            .await
+            .as_ref()
+            .map_or(0, |lease| lease.epoch)
+    }
+
+    async fn ensure_lease(&self) -> Result<()> {
+        let Some(lease_manager) = &self.lease_manager else {
+            return Ok(());
+        };
+
+        let lease = self
+            .current_lease
+            .lock()
+            .await
+            .clone()
+            .ok_or(PersistenceError::LeaseLost)?;
+        lease_manager
+            .validate_lease(&lease)
+            .await
+            .context("Poller lease is no longer held")?;
+        Ok(())
+    }
+
     async fn process_next_slot(&self) -> Result<()> {
         let latest_slot_record = self
             .store
@@ -156,6 +770,20 @@ impl SolanaChainPoller {
             .context("Error getting last processed slot")?
             .context("Last processed slot must exist")?;
 
+        if self.config.finality_buffer.is_some() && self.buffer_stale.swap(false, Ordering::SeqCst) {
+            if let Err(e) = self.restore_buffered_slots(&latest_slot_record).await {
+                self.buffer_stale.store(true, Ordering::SeqCst);
+                return Err(e.context("Failed to restore finality buffer"));
+            }
+        }
+
+        if self.account_tracker.is_some() && self.accounts_stale.swap(false, Ordering::SeqCst) {
+            if let Err(e) = self.snapshot_accounts().await {
+                self.accounts_stale.store(true, Ordering::SeqCst);
+                return Err(e.context("Failed to snapshot program accounts"));
+            }
+        }
+
         let latest_slot_num = self
             .client
             .get_latest_slot()
@@ -168,7 +796,7 @@ impl SolanaChainPoller {
                 latest_slot = latest_slot_num,
                 "Skipping slot processing as the last observed slot is the same as the latest slot"
             );
-            return Ok(());
+            return self.release_buffered_slots(latest_slot_record.slot).await;
         }
 
         let mut slots_to_fetch = Vec::new();
@@ -181,99 +809,108 @@ impl SolanaChainPoller {
         let slots_count = slots_to_fetch.len();
         debug!("Fetching slots with logs: {} slots", slots_count);
 
+        let mut expected_parent = latest_slot_record.slot;
         for slot_num in slots_to_fetch {
+            if self.is_buffer_full(slot_num).await {
+                self.release_buffered_slots(expected_parent).await?;
+                if self.is_buffer_full(slot_num).await {
+                    warn!(
+                        slot_number = slot_num,
+                        "Finality buffer is full; waiting for buffered slots to be released"
+                    );
+                    return Ok(());
+                }
+            }
+            self.ensure_lease().await?;
+
             let new_canon_slot = self
-                .client
-                .get_slot_by_number(slot_num)
+                .fetch_slot(slot_num)
                 .await
                 .context("Failed to fetch slot for reorg check")?;
 
             let parent_slot = new_canon_slot.parent.unwrap_or(0);
-            if parent_slot != latest_slot_record.slot {
+            if parent_slot != expected_parent {
                 warn!(
                     slot_number = slot_num,
-                    expected_parent = latest_slot_record.slot,
+                    expected_parent,
                     actual_parent = parent_slot,
                     chain_id = self.config.chain_id,
                     "Blockchain reorganization detected"
                 );
 
                 if let Err(e) = self.reconcile_reorg(&new_canon_slot).await {
+                    if e.is::<PollerError>() {
+                        return Err(e);
+                    }
                     error!("Failed to reconcile reorg: {}", e);
                 }
                 return Ok(());
             }
 
-            if let Err(e) = self.slot_handler.handle_slot(&new_canon_slot).await {
-                error!("Error handling new slot: slot={}, error={}", slot_num, e);
-            }
-
-            let latest_slot_record = self
-                .process_slot_logs(&new_canon_slot)
-                .await
-                .context("Error fetching slot with logs")?;
+            // Buffered slots are released before the slot history is pruned
+            // past them.
+            let record = if self.config.finality_buffer.is_some() {
+                let record = self.save_slot_record(&new_canon_slot).await?;
+                self.buffered_slots.lock().await.push_back(new_canon_slot);
+                self.release_buffered_slots(record.slot).await?;
+                record
+            } else {
+                self.deliver_slot(&new_canon_slot).await?;
+                let record = self.save_slot_record(&new_canon_slot).await?;
+                self.subscribers
+                    .emit(IndexerEvent::SlotProcessed(new_canon_slot))
+                    .await;
+                self.emit_finalized(record.slot).await;
+                record
+            };
+            expected_parent = record.slot;
 
-            if let Some(record) = latest_slot_record {
-                if self.config.slot_history_size > 0
-                    && record.slot > self.config.slot_history_size as u64
+            if self.config.slot_history_size > 0
+                && record.slot > self.config.slot_history_size as u64
+            {
+                let old_slot_num = record.slot - self.config.slot_history_size as u64;
+                if let Err(e) = self
+                    .store
+                    .delete_slot(self.config.chain_id, old_slot_num, self.lease_epoch().await)
+                    .await
                 {
-                    let old_slot_num = record.slot - self.config.slot_history_size as u64;
-                    if let Err(e) = self
-                        .store
-                        .delete_slot(self.config.chain_id, old_slot_num)
-                        .await
-                    {
-                        debug!(
-                            "Failed to prune old slot: slot={}, error={}",
-                            old_slot_num, e
-                        );
-                    }
+                    debug!(
+                        "Failed to prune old slot: slot={}, error={}",
+                        old_slot_num, e
+                    );
                 }
             }
         }
 
         debug!("All slots processed: {} slots", slots_count);
-
         Ok(())
     }
 
-    async fn process_slot_logs(
-        &self,
-        slot: &SolanaSlot,
-    ) -> Result<Option<SlotRecord>> {
-        let logs = self
-            .fetch_logs_for_interesting_programs_for_slot(slot.slot)
-            .await
-            .context("Error fetching logs for slot")?;
-
-        info!(
-            latest_slot_num = slot.slot,
-            blockhash = slot.blockhash,
-            log_count = logs.len(),
-            "Slot fetched with logs"
-        );
-
-        for log in logs {
-            let decoded_log = self
-                .log_parser
-                .decode_log(&log.program_id, &log)
-                .await
-                .context("Failed to decode log")?;
-
-            let log_with_slot = LogWithSlot {
-                log: decoded_log,
-                raw_log: log.clone(),
-                slot: slot.clone(),
-            };
-
-            if let Err(e) = self.slot_handler.handle_log(&log_with_slot).await {
-                error!("Error handling log: {}", e);
-                return Err(e);
-            }
+    /// Calls `handle_slot`, then delivers the slot's transactions, logs and
+    /// account updates, and commits the slot if the lease is still held.
+    async fn deliver_slot(&self, slot: &SolanaSlot) -> Result<()> {
+        let (result, attempts) = self
+            .call_handler(|| self.slot_handler.handle_slot(slot))
+            .await;
+        if let Err(e) = result {
+            let dead_letter = self.new_slot_dead_letter(DeadLetterKind::Slot, slot, attempts);
+            self.apply_handler_failure_policy(dead_letter, e).await?;
         }
 
-        debug!("Processed logs for slot: {}", slot.slot);
+        self.process_slot_logs(slot)
+            .await
+            .context("Error fetching slot with logs")?;
+
+        // A replica that lost its lease mid-slot stops before committing, and
+        // its successor delivers the slot again.
+        self.ensure_lease().await?;
+        self.slot_handler
+            .commit_slot(slot, self.lease_epoch().await)
+            .await
+            .context("Failed to commit slot")
+    }
 
+    async fn save_slot_record(&self, slot: &SolanaSlot) -> Result<SlotRecord> {
         let slot_record = SlotRecord {
             slot: slot.slot,
             blockhash: slot.blockhash.clone(),
@@ -282,42 +919,717 @@ impl SolanaChainPoller {
             chain_id: self.config.chain_id,
         };
 
+        self.ensure_lease().await?;
         self.store
-            .save_slot(&slot_record)
+            .save_slot(&slot_record, self.lease_epoch().await)
             .await
             .context("Failed to save slot info")?;
+        Ok(slot_record)
+    }
+
+    /// Delivers buffered slots that are `depth` behind `tip` or finalized.
+    /// A slot leaves the buffer only once it has been delivered.
+    async fn release_buffered_slots(&self, tip: u64) -> Result<()> {
+        let Some(buffer) = self.config.finality_buffer else {
+            return Ok(());
+        };
+
+        let mut release_up_to = buffer.depth.map(|depth| tip.saturating_sub(depth));
+        if buffer.finalized {
+            match self.client.get_finalized_slot().await {
+                Ok(finalized) => release_up_to = release_up_to.max(Some(finalized)),
+                // Without a depth, nothing else can release the buffer.
+                Err(e) if buffer.depth.is_none() => {
+                    return Err(e.context("Failed to get finalized slot"));
+                }
+                Err(e) => warn!(error = %e, "Failed to get finalized slot"),
+            }
+        }
+        let Some(release_up_to) = release_up_to else {
+            return Ok(());
+        };
+
+        loop {
+            let slot = match self.buffered_slots.lock().await.front() {
+                Some(slot) if slot.slot <= release_up_to => slot.clone(),
+                _ => break,
+            };
+
+            self.ensure_lease().await?;
+            self.deliver_slot(&slot).await?;
+            self.store
+                .save_last_released_slot(self.config.chain_id, slot.slot, self.lease_epoch().await)
+                .await
+                .context("Failed to save last released slot")?;
+            self.buffered_slots.lock().await.pop_front();
+            debug!(slot = slot.slot, "Released buffered slot");
 
-        Ok(Some(slot_record))
+            self.subscribers
+                .emit(IndexerEvent::SlotProcessed(slot))
+                .await;
+        }
+
+        self.emit_finalized(tip).await;
+        Ok(())
     }
 
-    async fn fetch_logs_for_interesting_programs_for_slot(
-        &self,
-        slot_number: u64,
-    ) -> Result<Vec<crate::clients::solana::SolanaProgramLog>> {
-        let all_programs: Vec<String> = self
-            .config
-            .interesting_programs
-            .iter()
-            .filter(|p| !p.is_empty())
-            .map(|s| s.to_lowercase())
-            .collect();
+    /// Emits `Finalized` when the slots `max_reorg_depth` behind `tip` that
+    /// have left the finality buffer reach past the last announced slot.
+    async fn emit_finalized(&self, tip: u64) {
+        let released = match self.buffered_slots.lock().await.front() {
+            Some(slot) => slot.slot.saturating_sub(1),
+            None => tip,
+        };
+        let finalized = tip
+            .saturating_sub(self.config.max_reorg_depth as u64)
+            .min(released);
+        if finalized > self.last_finalized.fetch_max(finalized, Ordering::SeqCst) {
+            self.subscribers
+                .emit(IndexerEvent::Finalized { slot: finalized })
+                .await;
+        }
+    }
+
+    /// Whether recording `slot_number` would prune the oldest buffered slot
+    /// from the slot history before the depth rule releases it.
+    async fn is_buffer_full(&self, slot_number: u64) -> bool {
+        let Some(buffer) = self.config.finality_buffer else {
+            return false;
+        };
+        let Some(oldest) = self.buffered_slots.lock().await.front().map(|slot| slot.slot) else {
+            return false;
+        };
+
+        let pruned = slot_number.saturating_sub(self.config.slot_history_size as u64);
+        let released = buffer.depth.map_or(0, |depth| slot_number.saturating_sub(depth));
+        oldest <= pruned && oldest > released
+    }
+
+    /// Refetches the recorded slots that were never released, so a restarted
+    /// or standby poller delivers them.
+    async fn restore_buffered_slots(&self, last_slot_record: &SlotRecord) -> Result<()> {
+        let chain_id = self.config.chain_id;
+        let Some(last_released) = self.store.get_last_released_slot(chain_id).await? else {
+            self.store
+                .save_last_released_slot(chain_id, last_slot_record.slot, self.lease_epoch().await)
+                .await?;
+            return Ok(());
+        };
+
+        let oldest_recorded = last_slot_record
+            .slot
+            .saturating_sub(self.config.slot_history_size as u64);
+        let mut buffered_slots = self.buffered_slots.lock().await;
+        buffered_slots.clear();
+        for slot_number in (last_released.max(oldest_recorded) + 1)..=last_slot_record.slot {
+            if self.store.get_slot(chain_id, slot_number).await?.is_some() {
+                buffered_slots.push_back(self.fetch_slot(slot_number).await?);
+            }
+        }
 
         info!(
-            programs = ?all_programs,
-            slot = slot_number,
-            "Fetching logs for interesting programs"
+            last_released,
+            buffered = buffered_slots.len(),
+            "Restored finality buffer"
         );
+        Ok(())
+    }
 
-        let mut all_logs = Vec::new();
+    /// Removes `slot_number` from the finality buffer, returning whether it
+    /// was there and so never delivered.
+    async fn discard_buffered_slot(&self, slot_number: u64) -> bool {
+        let mut buffered_slots = self.buffered_slots.lock().await;
+        let len = buffered_slots.len();
+        buffered_slots.retain(|slot| slot.slot != slot_number);
+        buffered_slots.len() != len
+    }
 
-        for program in all_programs {
-            debug!(
-                program = program,
-                slot = slot_number,
-                "Fetching logs for program"
-            );
+    /// Delivers every account of each interesting program that matches the
+    /// tracker's filters.
+    async fn snapshot_accounts(&self) -> Result<()> {
+        let Some(account_tracker) = &self.account_tracker else {
+            return Ok(());
+        };
 
-            match self
+        for program_id in &self.config.interesting_programs {
+            let accounts = self
+                .client
+                .get_program_accounts(program_id, account_tracker.filters(program_id))
+                .await
+                .with_context(|| format!("Failed to fetch accounts of {}", program_id))?;
+            info!(program_id, count = accounts.len(), "Snapshotting program accounts");
+
+            for account in accounts {
+                let pubkey = account.pubkey.clone();
+                let slot = account.slot;
+                let update = self
+                    .account_update(&pubkey, program_id, slot, Some(account), AccountUpdateSource::Snapshot)
+                    .await;
+                if account_tracker.record(&update, self.keep_account_versions_from(slot)) {
+                    self.deliver_account_update(&update).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Refetches the accounts passed to interesting programs by the slot's
+    /// successful transactions. Programs can only write accounts they own
+    /// and were given, so this covers every account they changed. Runs
+    /// before any of the slot's hooks, so a failed fetch cannot cause them
+    /// to be delivered twice when the slot is retried.
+    async fn fetch_account_updates(&self, slot: &SolanaSlot) -> Result<Vec<AccountUpdate>> {
+        let Some(account_tracker) = &self.account_tracker else {
+            return Ok(Vec::new());
+        };
+
+        // Pubkey to the signature of the last transaction that touched it.
+        let mut touched: Vec<(String, String)> = Vec::new();
+        for transaction in slot.transactions.iter().filter(|t| t.success) {
+            for instruction in InstructionWithContext::from_transaction(transaction)
+                .into_iter()
+                .filter(|i| self.is_interesting_program(&i.program_id))
+            {
+                for account in instruction.accounts {
+                    let pubkey = account.pubkey;
+                    match touched.iter_mut().find(|(p, _)| *p == pubkey) {
+                        Some(entry) => entry.1 = transaction.signature.clone(),
+                        None => touched.push((pubkey, transaction.signature.clone())),
+                    }
+                }
+            }
+        }
+        if touched.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let pubkeys: Vec<String> = touched.iter().map(|(p, _)| p.clone()).collect();
+        let accounts = self
+            .client
+            .get_multiple_accounts(&pubkeys, Some(slot.slot))
+            .await
+            .context("Failed to refetch written accounts")?;
+
+        let mut updates = Vec::new();
+        for ((pubkey, signature), account) in touched.into_iter().zip(accounts) {
+            let account = account.filter(|a| {
+                self.is_interesting_program(&a.owner) && account_tracker.matches_filters(&a.owner, a)
+            });
+            let program_id = match &account {
+                Some(account) => account.owner.clone(),
+                None => match account_tracker.latest(&pubkey) {
+                    Some(latest) if latest.account.is_some() => latest.program_id,
+                    _ => continue,
+                },
+            };
+
+            updates.push(
+                self.account_update(&pubkey, &program_id, slot.slot, account, AccountUpdateSource::Transaction { signature })
+                    .await,
+            );
+        }
+
+        Ok(updates)
+    }
+
+    /// Records the updates fetched by `fetch_account_updates` and delivers
+    /// those that changed an account.
+    async fn deliver_account_updates(&self, slot: &SolanaSlot, updates: Vec<AccountUpdate>) -> Result<()> {
+        let Some(account_tracker) = &self.account_tracker else {
+            return Ok(());
+        };
+
+        for update in updates {
+            if account_tracker.record(&update, self.keep_account_versions_from(slot.slot)) {
+                self.deliver_account_update(&update).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn keep_account_versions_from(&self, slot: u64) -> u64 {
+        slot.saturating_sub(self.config.max_reorg_depth as u64)
+    }
+
+    async fn account_update(
+        &self,
+        pubkey: &str,
+        program_id: &str,
+        slot: u64,
+        account: Option<SolanaAccount>,
+        source: AccountUpdateSource,
+    ) -> AccountUpdate {
+        let decoded = match &account {
+            Some(account) => match self.log_parser.decode_account(program_id, &account.data).await {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    warn!(pubkey, program_id, error = %e, "Failed to decode account");
+                    None
+                }
+            },
+            None => None,
+        };
+
+        AccountUpdate {
+            pubkey: pubkey.to_string(),
+            program_id: program_id.to_string(),
+            slot,
+            context_slot: account.as_ref().map_or(slot, |a| a.slot),
+            account,
+            decoded,
+            source,
+        }
+    }
+
+    async fn deliver_account_update(&self, update: &AccountUpdate) -> Result<()> {
+        let (result, attempts) = self
+            .call_handler(|| self.slot_handler.handle_account_update(update))
+            .await;
+        if let Err(e) = result {
+            let mut dead_letter = self.new_dead_letter(DeadLetterKind::AccountUpdate, update.slot, attempts);
+            dead_letter.account = Some(update.pubkey.clone());
+            if let AccountUpdateSource::Transaction { signature } = &update.source {
+                dead_letter.signature = Some(signature.clone());
+            }
+            self.apply_handler_failure_policy(dead_letter, e).await?;
+        }
+
+        if !self.subscribers.is_empty() {
+            self.subscribers
+                .emit(IndexerEvent::AccountUpdate(Box::new(update.clone())))
+                .await;
+        }
+        Ok(())
+    }
+
+    /// A failed refresh keeps the previously loaded IDL, if any.
+    async fn refresh_idls(&self) {
+        let Some(idl_fetcher) = &self.idl_fetcher else {
+            return;
+        };
+
+        for program_id in &self.config.interesting_programs {
+            if let Err(e) = idl_fetcher.refresh(program_id).await {
+                warn!(program_id, error = %e, "Failed to refresh on-chain IDL");
+            }
+        }
+    }
+
+    async fn process_slot_logs(&self, slot: &SolanaSlot) -> Result<()> {
+        let mut logs = self
+            .fetch_logs_for_interesting_programs_for_slot(slot.slot)
+            .await
+            .context("Error fetching logs for slot")?;
+
+        self.recover_truncated_logs(slot, &mut logs).await;
+        self.refresh_idls().await;
+
+        // emit_cpi! events never reach log_messages; recover them from the
+        // programs' self-invocations.
+        merge_cpi_event_logs(&slot.transactions, &mut logs, |p| self.is_interesting_program(p));
+
+        info!(
+            latest_slot_num = slot.slot,
+            blockhash = slot.blockhash,
+            log_count = logs.len(),
+            "Slot fetched with logs"
+        );
+
+        let account_updates = self.fetch_account_updates(slot).await?;
+
+        for transaction in slot
+            .transactions
+            .iter()
+            .filter(|t| self.is_interesting_transaction(t))
+        {
+            self.process_transaction(slot, transaction).await?;
+        }
+
+        self.deliver_account_updates(slot, account_updates).await?;
+
+        for log in logs {
+            if !self.is_delivered_log(slot, &log) {
+                debug!(
+                    signature = log.signature,
+                    log_index = log.log_index,
+                    "Dropping log of failed or filtered-out transaction"
+                );
+                continue;
+            }
+
+            let decoded_log = match self.log_parser.decode_log(&log.program_id, &log).await {
+                Ok(decoded_log) => decoded_log,
+                Err(e) => {
+                    self.handle_decode_failure(slot, &log, e).await?;
+                    continue;
+                }
+            };
+
+            let log_with_slot = self.log_with_slot(slot, &log, decoded_log).await;
+
+            let (result, attempts) = self.call_handler(|| self.deliver_log(&log_with_slot)).await;
+            if let Err(e) = result {
+                let mut dead_letter = self.new_slot_dead_letter(DeadLetterKind::Log, slot, attempts);
+                dead_letter.signature = Some(log_with_slot.raw_log.signature.clone());
+                dead_letter.raw_log = Some(log_with_slot.raw_log.clone());
+                self.apply_handler_failure_policy(dead_letter, e).await?;
+            }
+
+            self.publish_log(log_with_slot).await?;
+        }
+
+        debug!("Processed logs for slot: {}", slot.slot);
+
+        Ok(())
+    }
+
+    async fn process_transaction(
+        &self,
+        slot: &SolanaSlot,
+        transaction: &SolanaTransaction,
+    ) -> Result<()> {
+        if let Some(event_store) = &self.event_store {
+            event_store
+                .save_transaction(self.config.chain_id, transaction, self.lease_epoch().await)
+                .await
+                .context("Failed to save transaction")?;
+        }
+
+        let (result, attempts) = self
+            .call_handler(|| self.slot_handler.handle_transaction(transaction, slot))
+            .await;
+        if let Err(e) = result {
+            let mut dead_letter =
+                self.new_slot_dead_letter(DeadLetterKind::Transaction, slot, attempts);
+            dead_letter.signature = Some(transaction.signature.clone());
+            self.apply_handler_failure_policy(dead_letter, e).await?;
+        }
+
+        for change in BalanceChange::from_transaction(transaction) {
+            let (result, attempts) = self
+                .call_handler(|| self.slot_handler.handle_balance_change(&change))
+                .await;
+            if let Err(e) = result {
+                let mut dead_letter =
+                    self.new_slot_dead_letter(DeadLetterKind::BalanceChange, slot, attempts);
+                dead_letter.signature = Some(transaction.signature.clone());
+                dead_letter.account = Some(change.account.clone());
+                dead_letter.mint = change.mint.clone();
+                self.apply_handler_failure_policy(dead_letter, e).await?;
+            }
+
+            if !self.subscribers.is_empty() {
+                self.subscribers
+                    .emit(IndexerEvent::BalanceChange(Box::new(change)))
+                    .await;
+            }
+        }
+
+        for mut instruction in InstructionWithContext::from_transaction(transaction)
+            .into_iter()
+            .filter(|i| {
+                self.is_interesting_program(&i.program_id)
+                    || i.invoked_by.iter().any(|p| self.is_interesting_program(p))
+            })
+        {
+            instruction.decoded = self.decode_instruction(&instruction).await;
+
+            let (result, attempts) = self
+                .call_handler(|| self.slot_handler.handle_instruction(&instruction))
+                .await;
+            if let Err(e) = result {
+                let mut dead_letter =
+                    self.new_slot_dead_letter(DeadLetterKind::Instruction, slot, attempts);
+                dead_letter.signature = Some(transaction.signature.clone());
+                dead_letter.instruction = Some(instruction.position);
+                self.apply_handler_failure_policy(dead_letter, e).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Warns about every transaction with truncated logs and, with
+    /// `TruncatedLogRecovery::Archival`, replaces the logs of all its
+    /// interesting programs with complete ones from the archive when
+    /// available. Truncation is detected from the transactions' log messages
+    /// too, since an interesting program invoked after the cut-off has no logs
+    /// to carry the flag.
+    async fn recover_truncated_logs(&self, slot: &SolanaSlot, logs: &mut Vec<SolanaProgramLog>) {
+        let mut truncated: Vec<String> = Vec::new();
+        let from_transactions = slot
+            .transactions
+            .iter()
+            .filter(|t| t.program_ids.iter().any(|p| self.is_interesting_program(p)))
+            .filter(|t| t.log_messages.iter().any(|l| l == "Log truncated"))
+            .map(|t| &t.signature);
+        let from_logs = logs.iter().filter(|l| l.logs_truncated).map(|l| &l.signature);
+        for signature in from_transactions.chain(from_logs) {
+            if !truncated.contains(signature) {
+                truncated.push(signature.clone());
+            }
+        }
+
+        for signature in truncated {
+            if self.config.truncated_log_recovery == TruncatedLogRecovery::Archival {
+                match self.client.get_archived_log_messages(&signature).await {
+                    Ok(Some(messages)) => {
+                        let tree = InvocationTree::parse(&messages);
+                        if !tree.truncated {
+                            let mut program_ids: Vec<String> = Vec::new();
+                            let invoked = tree
+                                .invocations
+                                .iter()
+                                .map(|i| &i.program_id)
+                                .chain(
+                                    logs.iter()
+                                        .filter(|l| l.signature == signature)
+                                        .map(|l| &l.program_id),
+                                );
+                            for program_id in invoked {
+                                if self.is_interesting_program(program_id)
+                                    && !program_ids.contains(program_id)
+                                {
+                                    program_ids.push(program_id.clone());
+                                }
+                            }
+
+                            for program_id in program_ids {
+                                let recovered = tree.program_logs(
+                                    &messages,
+                                    &program_id,
+                                    &signature,
+                                    slot.slot,
+                                    slot.block_time,
+                                );
+                                splice_transaction_logs(
+                                    slot,
+                                    logs,
+                                    &program_id,
+                                    &signature,
+                                    recovered,
+                                );
+                            }
+                            info!(signature, slot = slot.slot, "Recovered truncated logs from archive");
+                            continue;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!(
+                        signature,
+                        error = %e,
+                        "Failed to fetch archived logs"
+                    ),
+                }
+            }
+
+            warn!(
+                signature,
+                slot = slot.slot,
+                "Transaction logs were truncated; log-emitted events after the cut-off are missing"
+            );
+        }
+    }
+
+    /// Logs of failed transactions are skipped with
+    /// `FailedTransactionLogs::Drop`, and logs of transactions outside the
+    /// `transaction_filter` are never delivered.
+    fn is_delivered_log(&self, slot: &SolanaSlot, log: &SolanaProgramLog) -> bool {
+        let transaction = find_transaction(slot, &log.signature);
+        if self.config.failed_transaction_logs == FailedTransactionLogs::Drop
+            && transaction.is_some_and(|t| !t.success)
+        {
+            return false;
+        }
+        match &self.config.transaction_filter {
+            Some(filter) => transaction.is_some_and(|t| filter.matches(t)),
+            None => true,
+        }
+    }
+
+    fn is_routed_failed_log(&self, log_with_slot: &LogWithSlot) -> bool {
+        !log_with_slot.success
+            && self.config.failed_transaction_logs == FailedTransactionLogs::Route
+    }
+
+    async fn deliver_log(&self, log_with_slot: &LogWithSlot) -> Result<()> {
+        if self.is_routed_failed_log(log_with_slot) {
+            self.slot_handler.handle_failed_log(log_with_slot).await
+        } else {
+            self.slot_handler.handle_log(log_with_slot).await
+        }
+    }
+
+    /// Saves a delivered log to the event store and emits it to subscribers,
+    /// unless it was routed to `SlotHandler::handle_failed_log`.
+    async fn publish_log(&self, log_with_slot: LogWithSlot) -> Result<()> {
+        if self.is_routed_failed_log(&log_with_slot) {
+            return Ok(());
+        }
+
+        if let Some(event_store) = &self.event_store {
+            event_store
+                .save_log(&log_with_slot, self.lease_epoch().await)
+                .await
+                .context("Failed to save decoded log")?;
+        }
+
+        if !self.subscribers.is_empty() {
+            self.subscribers.emit(IndexerEvent::Log(Box::new(log_with_slot))).await;
+        }
+        Ok(())
+    }
+
+    /// Attaches typed compute unit and return data records to `raw_log`,
+    /// along with the outcome of the emitting transaction.
+    async fn log_with_slot(
+        &self,
+        slot: &SolanaSlot,
+        raw_log: &SolanaProgramLog,
+        mut log: DecodedLog,
+    ) -> LogWithSlot {
+        let compute_units = parse_compute_units(&raw_log.log_message).map(|(_, units)| units);
+
+        let return_data = match parse_return_data(&raw_log.log_message) {
+            Some(Ok((program_id, data))) => {
+                let mut return_data = ReturnData {
+                    program_id: program_id.to_string(),
+                    data,
+                    value: None,
+                    value_type: None,
+                };
+                self.decode_return_data(slot, raw_log, &mut return_data).await;
+                Some(return_data)
+            }
+            _ => None,
+        };
+
+        // Flag truncation even if a custom decoder ignored it.
+        log.truncated |= raw_log.logs_truncated;
+
+        let transaction = find_transaction(slot, &raw_log.signature);
+
+        LogWithSlot {
+            log,
+            raw_log: raw_log.clone(),
+            slot: slot.clone(),
+            compute_units,
+            return_data,
+            success: transaction.is_none_or(|t| t.success),
+            error: transaction.and_then(|t| t.error.clone()),
+        }
+    }
+
+    /// Decodes return data with the IDL return type of the instruction that
+    /// emitted it, located by the log's signature and instruction position.
+    async fn decode_return_data(
+        &self,
+        slot: &SolanaSlot,
+        raw_log: &SolanaProgramLog,
+        return_data: &mut ReturnData,
+    ) {
+        let position = InstructionPosition {
+            top_level_index: raw_log.instruction_index,
+            inner_index: raw_log.inner_instruction_index,
+        };
+        let Some(instruction) = find_transaction(slot, &raw_log.signature).and_then(|t| {
+            InstructionWithContext::from_transaction(t)
+                .into_iter()
+                .find(|i| i.position == position)
+        }) else {
+            return;
+        };
+
+        match self
+            .log_parser
+            .decode_return_data(&return_data.program_id, &instruction.data, &return_data.data)
+            .await
+        {
+            Ok(Some((value_type, value))) => {
+                return_data.value_type = Some(value_type);
+                return_data.value = Some(value);
+            }
+            Ok(None) => {}
+            Err(e) => warn!(
+                signature = raw_log.signature,
+                program_id = return_data.program_id,
+                error = %e,
+                "Failed to decode return data"
+            ),
+        }
+    }
+
+    async fn decode_instruction(
+        &self,
+        instruction: &InstructionWithContext,
+    ) -> Option<DecodedInstruction> {
+        match self
+            .log_parser
+            .decode_instruction(instruction)
+            .await
+        {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                warn!(
+                    signature = instruction.signature,
+                    program_id = instruction.program_id,
+                    position = ?instruction.position,
+                    error = %e,
+                    "Failed to decode instruction"
+                );
+                None
+            }
+        }
+    }
+
+    fn is_interesting_program(&self, program_id: &str) -> bool {
+        self.config
+            .interesting_programs
+            .iter()
+            .filter(|p| !p.is_empty())
+            .any(|p| p == program_id)
+    }
+
+    fn is_interesting_transaction(&self, transaction: &SolanaTransaction) -> bool {
+        match &self.config.transaction_filter {
+            Some(filter) => filter.matches(transaction),
+            None => transaction
+                .program_ids
+                .iter()
+                .any(|p| self.is_interesting_program(p)),
+        }
+    }
+
+    async fn fetch_logs_for_interesting_programs_for_slot(
+        &self,
+        slot_number: u64,
+    ) -> Result<Vec<crate::clients::solana::SolanaProgramLog>> {
+        let all_programs: Vec<String> = self
+            .config
+            .interesting_programs
+            .iter()
+            .filter(|p| !p.is_empty())
+            .cloned()
+            .collect();
+
+        info!(
+            programs = ?all_programs,
+            slot = slot_number,
+            "Fetching logs for interesting programs"
+        );
+
+        let mut all_logs = Vec::new();
+
+        for program in all_programs {
+            debug!(
+                program = program,
+                slot = slot_number,
+                "Fetching logs for program"
+            );
+
+            match self
                 .client
                 .get_program_logs(&program, slot_number, slot_number)
                 .await
@@ -365,6 +1677,8 @@ impl SolanaChainPoller {
     }
 
     async fn reconcile_reorg(&self, start_slot: &SolanaSlot) -> Result<()> {
+        self.ensure_lease().await?;
+
         let orphaned_slots = self
             .find_orphaned_slots(start_slot, self.config.max_reorg_depth)
             .await
@@ -375,11 +1689,19 @@ impl SolanaChainPoller {
         }
 
         for orphaned_slot in orphaned_slots {
-            self.slot_handler.handle_reorg_slot(orphaned_slot.slot).await;
+            if self.discard_buffered_slot(orphaned_slot.slot).await {
+                info!(slot = orphaned_slot.slot, "Discarded orphaned slot from finality buffer");
+            } else {
+                self.undo_slot(&orphaned_slot).await?;
+            }
 
             if let Err(e) = self
                 .store
-                .delete_slot(orphaned_slot.chain_id, orphaned_slot.slot)
+                .delete_slot(
+                    orphaned_slot.chain_id,
+                    orphaned_slot.slot,
+                    self.lease_epoch().await,
+                )
                 .await
             {
                 if !matches!(e, PersistenceError::NotFound) {
@@ -394,6 +1716,78 @@ impl SolanaChainPoller {
         Ok(())
     }
 
+    /// Tells handlers, the account tracker, subscribers and the event store
+    /// that a delivered slot was orphaned.
+    async fn undo_slot(&self, orphaned_slot: &SlotRecord) -> Result<()> {
+        self.discard_orphaned_dead_letters(orphaned_slot).await?;
+
+        let (result, attempts) = self
+            .call_handler(|| self.slot_handler.handle_reorg_slot(orphaned_slot.slot))
+            .await;
+        if let Err(e) = result {
+            let dead_letter =
+                self.new_dead_letter(DeadLetterKind::ReorgSlot, orphaned_slot.slot, attempts);
+            self.apply_handler_failure_policy(dead_letter, e).await?;
+        }
+
+        if let Some(account_tracker) = &self.account_tracker {
+            for update in account_tracker.rollback(orphaned_slot.slot) {
+                self.deliver_account_update(&update).await?;
+            }
+        }
+
+        self.subscribers
+            .emit(IndexerEvent::Reorg {
+                slot: orphaned_slot.slot,
+            })
+            .await;
+
+        if let Some(event_store) = &self.event_store {
+            event_store
+                .delete_slot_events(
+                    orphaned_slot.chain_id,
+                    orphaned_slot.slot,
+                    self.lease_epoch().await,
+                )
+                .await
+                .context("Failed to delete events for orphaned slot")?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the dead letters of an orphaned block, which can no longer be
+    /// replayed. Failed `handle_reorg_slot` calls for earlier orphans of the
+    /// same slot number are kept.
+    async fn discard_orphaned_dead_letters(&self, orphaned_slot: &SlotRecord) -> Result<()> {
+        let Some(dead_letter_store) = &self.dead_letter_store else {
+            return Ok(());
+        };
+
+        let dead_letters = dead_letter_store
+            .list_dead_letters(orphaned_slot.chain_id)
+            .await
+            .context("Failed to list dead letters")?;
+        for dead_letter in dead_letters.iter().filter(|d| {
+            d.slot == orphaned_slot.slot
+                && d.kind != DeadLetterKind::ReorgSlot
+                && d.blockhash
+                    .as_ref()
+                    .is_none_or(|blockhash| *blockhash == orphaned_slot.blockhash)
+        }) {
+            dead_letter_store
+                .delete_dead_letter(dead_letter.id, self.lease_epoch().await)
+                .await
+                .context("Failed to delete dead letter of orphaned slot")?;
+            info!(
+                id = dead_letter.id,
+                kind = ?dead_letter.kind,
+                slot = dead_letter.slot,
+                "Discarded dead letter of orphaned slot"
+            );
+        }
+        Ok(())
+    }
+
     async fn find_orphaned_slots(
         &self,
         start_slot: &SolanaSlot,
@@ -410,8 +1804,7 @@ impl SolanaChainPoller {
             }
 
             let canon_parent_slot = self
-                .client
-                .get_slot_by_number(parent_slot_num)
+                .fetch_slot(parent_slot_num)
                 .await
                 .context(format!("Failed to fetch slot {} from chain", parent_slot_num))?;
 
@@ -433,7 +1826,7 @@ impl SolanaChainPoller {
                         block_time: canon_parent_slot.block_time.unwrap_or(0) as u64,
                         chain_id: self.config.chain_id,
                     };
-                    if let Err(e) = self.store.save_slot(&record).await {
+                    if let Err(e) = self.store.save_slot(&record, self.lease_epoch().await).await {
                         warn!(
                             slot_number = parent_slot_num,
                             error = %e,
@@ -472,7 +1865,7 @@ impl SolanaChainPoller {
             );
 
             self.store
-                .save_slot(&parent_slot_record)
+                .save_slot(&parent_slot_record, self.lease_epoch().await)
                 .await
                 .context("Failed to save parent slot")?;
 
@@ -484,3 +1877,1345 @@ impl SolanaChainPoller {
     }
 }
 
+fn find_transaction<'a>(slot: &'a SolanaSlot, signature: &str) -> Option<&'a SolanaTransaction> {
+    slot.transactions.iter().find(|t| t.signature == signature)
+}
+
+/// Replaces one program's logs of a transaction with `recovered`, keeping the
+/// per-program, transaction-ordered layout of the fetched logs: the recovered
+/// logs go before that program's logs of the next transaction in the slot.
+fn splice_transaction_logs(
+    slot: &SolanaSlot,
+    logs: &mut Vec<SolanaProgramLog>,
+    program_id: &str,
+    signature: &str,
+    recovered: Vec<SolanaProgramLog>,
+) {
+    let same_program = |l: &SolanaProgramLog| l.program_id == program_id;
+    let position = |signature: &str| slot.transactions.iter().position(|t| t.signature == signature);
+
+    let first = logs
+        .iter()
+        .position(|l| same_program(l) && l.signature == signature);
+    logs.retain(|l| !(same_program(l) && l.signature == signature));
+
+    let index = first.unwrap_or_else(|| {
+        let transaction = position(signature);
+        logs.iter()
+            .position(|l| same_program(l) && position(&l.signature) > transaction)
+            .or_else(|| logs.iter().rposition(same_program).map(|i| i + 1))
+            .unwrap_or(logs.len())
+    });
+    logs.splice(index..index, recovered);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain_pollers::persistence::memory::{
+        InMemoryChainPollerPersistence, InMemoryDeadLetterStore,
+    };
+    use crate::chain_pollers::stream::NoopSlotHandler;
+    use crate::clients::solana::SolanaInstruction;
+    use crate::transaction_log_parser::TransactionLogParser;
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+    const PROGRAM: &str = "Prog1111";
+
+    struct FakeClient {
+        latest: AtomicU64,
+        slots: std::sync::Mutex<HashMap<u64, SolanaSlot>>,
+        logs: std::sync::Mutex<HashMap<u64, Vec<SolanaProgramLog>>>,
+        archive: std::sync::Mutex<HashMap<String, Vec<String>>>,
+        accounts: std::sync::Mutex<HashMap<String, SolanaAccount>>,
+        min_context_slots: std::sync::Mutex<Vec<Option<u64>>>,
+        finalized: AtomicU64,
+        fail_finalized: AtomicBool,
+        fail_account_fetches: AtomicBool,
+    }
+
+    impl FakeClient {
+        fn new() -> Self {
+            Self {
+                latest: AtomicU64::new(0),
+                slots: std::sync::Mutex::new(HashMap::new()),
+                logs: std::sync::Mutex::new(HashMap::new()),
+                archive: std::sync::Mutex::new(HashMap::new()),
+                accounts: std::sync::Mutex::new(HashMap::new()),
+                min_context_slots: std::sync::Mutex::new(Vec::new()),
+                finalized: AtomicU64::new(0),
+                fail_finalized: AtomicBool::new(false),
+                fail_account_fetches: AtomicBool::new(false),
+            }
+        }
+
+        fn set_account(&self, pubkey: &str, owner: &str, data: &[u8]) {
+            self.accounts.lock().unwrap().insert(
+                pubkey.to_string(),
+                SolanaAccount {
+                    pubkey: pubkey.to_string(),
+                    owner: owner.to_string(),
+                    lamports: 1,
+                    data: data.to_vec(),
+                    executable: false,
+                    slot: self.latest.load(Ordering::SeqCst),
+                },
+            );
+        }
+
+        fn push_slot(&self, slot: u64, parent: u64, blockhash: &str, log_count: u64) {
+            self.slots.lock().unwrap().insert(
+                slot,
+                SolanaSlot {
+                    slot,
+                    parent: Some(parent),
+                    blockhash: blockhash.to_string(),
+                    block_time: Some(1_700_000_000),
+                    transactions: Vec::new(),
+                    chain_id: 101,
+                },
+            );
+            let logs = (0..log_count)
+                .map(|i| SolanaProgramLog {
+                    program_id: PROGRAM.to_string(),
+                    log_index: i,
+                    signature: format!("sig-{}", slot),
+                    slot,
+                    block_time: None,
+                    log_message: format!("Program log: event {}", i),
+                    instruction_index: 0,
+                    inner_instruction_index: None,
+                    depth: 1,
+                    parent_program_id: None,
+                    logs_truncated: false,
+                })
+                .collect();
+            self.logs.lock().unwrap().insert(slot, logs);
+            self.latest.fetch_max(slot, Ordering::SeqCst);
+        }
+
+        fn set_transactions(&self, slot: u64, transactions: Vec<SolanaTransaction>) {
+            self.slots.lock().unwrap().get_mut(&slot).unwrap().transactions = transactions;
+        }
+    }
+
+    /// Genesis slot 1 and slot 2 with `log_count` logs of `PROGRAM`.
+    fn client_with_slots(log_count: u64) -> Arc<FakeClient> {
+        let client = Arc::new(FakeClient::new());
+        client.push_slot(1, 0, "h1", 0);
+        client.push_slot(2, 1, "h2", log_count);
+        client
+    }
+
+    /// A successful transaction in `slot`, signed `sig-<slot>` like the
+    /// slot's logs.
+    fn transaction(slot: u64) -> SolanaTransaction {
+        SolanaTransaction {
+            signature: format!("sig-{}", slot),
+            slot,
+            success: true,
+            ..Default::default()
+        }
+    }
+
+    fn instruction(program_id_index: usize, accounts: Vec<usize>) -> SolanaInstruction {
+        SolanaInstruction {
+            program_id_index,
+            accounts,
+            stack_height: Some(1),
+            ..Default::default()
+        }
+    }
+
+    #[async_trait]
+    impl Client for FakeClient {
+        async fn get_latest_slot(&self) -> Result<u64> {
+            Ok(self.latest.load(Ordering::SeqCst))
+        }
+
+        async fn get_slot_by_number(&self, slot_number: u64) -> Result<SolanaSlot> {
+            self.slots
+                .lock()
+                .unwrap()
+                .get(&slot_number)
+                .cloned()
+                .context("slot not found")
+        }
+
+        async fn get_program_logs(
+            &self,
+            program_id: &str,
+            from_slot: u64,
+            _to_slot: u64,
+        ) -> Result<Vec<SolanaProgramLog>> {
+            let logs = self.logs.lock().unwrap();
+            Ok(logs
+                .get(&from_slot)
+                .into_iter()
+                .flatten()
+                .filter(|l| l.program_id == program_id)
+                .cloned()
+                .collect())
+        }
+
+        async fn get_archived_log_messages(&self, signature: &str) -> Result<Option<Vec<String>>> {
+            Ok(self.archive.lock().unwrap().get(signature).cloned())
+        }
+
+        async fn get_finalized_slot(&self) -> Result<u64> {
+            if self.fail_finalized.load(Ordering::SeqCst) {
+                anyhow::bail!("finalized slot unavailable");
+            }
+            Ok(self.finalized.load(Ordering::SeqCst))
+        }
+
+        async fn get_account_info(&self, pubkey: &str) -> Result<Option<SolanaAccount>> {
+            Ok(self.accounts.lock().unwrap().get(pubkey).cloned())
+        }
+
+        async fn get_program_accounts(
+            &self,
+            program_id: &str,
+            filters: &[crate::clients::solana::AccountFilter],
+        ) -> Result<Vec<SolanaAccount>> {
+            Ok(self
+                .accounts
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|a| a.owner == program_id && filters.iter().all(|f| f.matches(&a.data)))
+                .cloned()
+                .collect())
+        }
+
+        async fn get_multiple_accounts(
+            &self,
+            pubkeys: &[String],
+            min_context_slot: Option<u64>,
+        ) -> Result<Vec<Option<SolanaAccount>>> {
+            if self.fail_account_fetches.load(Ordering::SeqCst) {
+                anyhow::bail!("RPC unavailable");
+            }
+            self.min_context_slots.lock().unwrap().push(min_context_slot);
+            let accounts = self.accounts.lock().unwrap();
+            Ok(pubkeys.iter().map(|p| accounts.get(p).cloned()).collect())
+        }
+    }
+
+    #[derive(Default)]
+    struct FailingLogHandler {
+        log_calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl SlotHandler for FailingLogHandler {
+        async fn handle_slot(&self, _slot: &SolanaSlot) -> Result<()> {
+            Ok(())
+        }
+
+        async fn handle_log(&self, _log_with_slot: &LogWithSlot) -> Result<()> {
+            self.log_calls.fetch_add(1, Ordering::SeqCst);
+            anyhow::bail!("handler is broken")
+        }
+
+        async fn handle_reorg_slot(&self, _slot_number: u64) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct CountingHandler {
+        log_calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl SlotHandler for CountingHandler {
+        async fn handle_slot(&self, _slot: &SolanaSlot) -> Result<()> {
+            Ok(())
+        }
+
+        async fn handle_log(&self, _log_with_slot: &LogWithSlot) -> Result<()> {
+            self.log_calls.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn handle_reorg_slot(&self, _slot_number: u64) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingHandler {
+        logs: std::sync::Mutex<Vec<LogWithSlot>>,
+        failed_logs: std::sync::Mutex<Vec<LogWithSlot>>,
+        account_updates: std::sync::Mutex<Vec<AccountUpdate>>,
+        balance_changes: std::sync::Mutex<Vec<BalanceChange>>,
+        fail_balance_changes: AtomicBool,
+        transactions: std::sync::Mutex<Vec<String>>,
+        instructions: std::sync::Mutex<Vec<InstructionWithContext>>,
+        slots: std::sync::Mutex<Vec<String>>,
+        reorg_slots: std::sync::Mutex<Vec<u64>>,
+        commits: std::sync::Mutex<Vec<(u64, u64)>>,
+    }
+
+    #[async_trait]
+    impl SlotHandler for RecordingHandler {
+        async fn handle_slot(&self, slot: &SolanaSlot) -> Result<()> {
+            self.slots.lock().unwrap().push(slot.blockhash.clone());
+            Ok(())
+        }
+
+        async fn handle_log(&self, log_with_slot: &LogWithSlot) -> Result<()> {
+            self.logs.lock().unwrap().push(log_with_slot.clone());
+            Ok(())
+        }
+
+        async fn handle_failed_log(&self, log_with_slot: &LogWithSlot) -> Result<()> {
+            self.failed_logs.lock().unwrap().push(log_with_slot.clone());
+            Ok(())
+        }
+
+        async fn handle_account_update(&self, update: &AccountUpdate) -> Result<()> {
+            self.account_updates.lock().unwrap().push(update.clone());
+            Ok(())
+        }
+
+        async fn handle_balance_change(&self, change: &BalanceChange) -> Result<()> {
+            if self.fail_balance_changes.load(Ordering::SeqCst) {
+                anyhow::bail!("balance handler is broken");
+            }
+            self.balance_changes.lock().unwrap().push(change.clone());
+            Ok(())
+        }
+
+        async fn handle_transaction(
+            &self,
+            transaction: &SolanaTransaction,
+            _slot: &SolanaSlot,
+        ) -> Result<()> {
+            self.transactions.lock().unwrap().push(transaction.signature.clone());
+            Ok(())
+        }
+
+        async fn handle_instruction(&self, instruction: &InstructionWithContext) -> Result<()> {
+            self.instructions.lock().unwrap().push(instruction.clone());
+            Ok(())
+        }
+
+        async fn handle_reorg_slot(&self, slot_number: u64) -> Result<()> {
+            self.reorg_slots.lock().unwrap().push(slot_number);
+            Ok(())
+        }
+
+        async fn commit_slot(&self, slot: &SolanaSlot, epoch: u64) -> Result<()> {
+            self.commits.lock().unwrap().push((slot.slot, epoch));
+            Ok(())
+        }
+    }
+
+    struct BreakableParser {
+        broken: AtomicBool,
+        inner: TransactionLogParser,
+    }
+
+    #[async_trait]
+    impl LogParser for BreakableParser {
+        async fn decode_log(
+            &self,
+            program_id: &str,
+            log: &SolanaProgramLog,
+        ) -> Result<crate::transaction_log_parser::DecodedLog> {
+            if self.broken.load(Ordering::SeqCst) {
+                anyhow::bail!("unknown event discriminator");
+            }
+            self.inner.decode_log(program_id, log).await
+        }
+    }
+
+    /// Polls `PROGRAM` and halts after two quick retries.
+    fn test_config() -> SolanaChainPollerConfig {
+        SolanaChainPollerConfig {
+            interesting_programs: vec![PROGRAM.to_string()],
+            handler_error_policy: HandlerErrorPolicy {
+                max_retries: 2,
+                retry_backoff: Duration::from_millis(1),
+                on_exhausted: HandlerFailureAction::Halt,
+            },
+            ..Default::default()
+        }
+    }
+
+    fn dead_letter_config() -> SolanaChainPollerConfig {
+        let mut config = test_config();
+        config.handler_error_policy.on_exhausted = HandlerFailureAction::DeadLetter;
+        config
+    }
+
+    async fn test_poller(
+        client: Arc<FakeClient>,
+        handler: Arc<dyn SlotHandler>,
+        config: SolanaChainPollerConfig,
+    ) -> SolanaChainPoller {
+        test_poller_with_parser(client, Arc::new(TransactionLogParser::new()), handler, config).await
+    }
+
+    /// A poller whose store has recorded the client's slot 1.
+    async fn test_poller_with_parser(
+        client: Arc<FakeClient>,
+        log_parser: Arc<dyn LogParser>,
+        handler: Arc<dyn SlotHandler>,
+        config: SolanaChainPollerConfig,
+    ) -> SolanaChainPoller {
+        let store = Arc::new(InMemoryChainPollerPersistence::new());
+        let genesis = client.get_slot_by_number(1).await.unwrap();
+        store
+            .save_slot(
+                &SlotRecord {
+                    slot: genesis.slot,
+                    blockhash: genesis.blockhash.clone(),
+                    parent: 0,
+                    block_time: 0,
+                    chain_id: 101,
+                },
+                0,
+            )
+            .await
+            .unwrap();
+
+        SolanaChainPoller::new(client, log_parser, config, store, handler).unwrap()
+    }
+
+    #[tokio::test]
+    async fn failing_log_handler_is_retried_then_dead_lettered() {
+        let handler = Arc::new(FailingLogHandler::default());
+        let dead_letters = Arc::new(InMemoryDeadLetterStore::new());
+
+        let mut poller = test_poller(client_with_slots(2), handler.clone(), dead_letter_config()).await;
+        poller.set_dead_letter_store(dead_letters.clone());
+
+        poller.process_next_slot().await.unwrap();
+
+        assert_eq!(handler.log_calls.load(Ordering::SeqCst), 6);
+        let last = poller.store.get_last_processed_slot(101).await.unwrap().unwrap();
+        assert_eq!(last.slot, 2);
+        let recorded = dead_letters.list_dead_letters(101).await.unwrap();
+        assert_eq!(recorded.len(), 2);
+        assert!(recorded.iter().all(|d| d.kind == DeadLetterKind::Log && d.attempts == 3));
+        assert_eq!(
+            recorded
+                .iter()
+                .map(|d| d.raw_log.as_ref().unwrap().log_index)
+                .collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+    }
+
+    #[tokio::test]
+    async fn balance_changes_are_delivered_and_replayed_per_mint() {
+        use crate::clients::solana::SolanaTokenBalance;
+
+        let token_balance = |amount| SolanaTokenBalance {
+            account_index: 1,
+            mint: "Mint1111".to_string(),
+            owner: Some("Owner".to_string()),
+            program_id: None,
+            amount,
+            decimals: 6,
+        };
+        let client = client_with_slots(0);
+        client.set_transactions(
+            2,
+            vec![SolanaTransaction {
+                fee: 5000,
+                account_keys: vec!["Payer".to_string(), "Token".to_string(), PROGRAM.to_string()],
+                program_ids: vec![PROGRAM.to_string()],
+                pre_balances: vec![100_000, 2_000, 1],
+                post_balances: vec![95_000, 7_000, 1],
+                pre_token_balances: vec![token_balance(10)],
+                post_token_balances: vec![token_balance(25)],
+                ..transaction(2)
+            }],
+        );
+        let handler = Arc::new(RecordingHandler::default());
+        let dead_letters = Arc::new(InMemoryDeadLetterStore::new());
+        let mut poller = test_poller(client, handler.clone(), dead_letter_config()).await;
+        poller.set_dead_letter_store(dead_letters.clone());
+
+        handler.fail_balance_changes.store(true, Ordering::SeqCst);
+        poller.process_next_slot().await.unwrap();
+        let recorded = dead_letters.list_dead_letters(101).await.unwrap();
+        let failed: Vec<_> = recorded
+            .iter()
+            .map(|d| (d.kind, d.account.as_deref(), d.mint.as_deref()))
+            .collect();
+        assert_eq!(
+            failed,
+            vec![
+                (DeadLetterKind::BalanceChange, Some("Payer"), None),
+                (DeadLetterKind::BalanceChange, Some("Token"), None),
+                (DeadLetterKind::BalanceChange, Some("Token"), Some("Mint1111")),
+            ]
+        );
+
+        // Replaying the token dead letter delivers only the token change.
+        handler.fail_balance_changes.store(false, Ordering::SeqCst);
+        poller.replay_dead_letter(recorded[2].id).await.unwrap();
+        let changes = handler.balance_changes.lock().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].mint.as_deref(), Some("Mint1111"));
+        assert_eq!((changes[0].pre, changes[0].post, changes[0].delta), (10, 25, 15));
+    }
+
+    #[tokio::test]
+    async fn halt_policy_stops_without_advancing() {
+        let handler = Arc::new(FailingLogHandler::default());
+
+        let poller = test_poller(client_with_slots(1), handler.clone(), test_config()).await;
+
+        let err = poller.process_next_slot().await.unwrap_err();
+        assert!(err.is::<PollerError>());
+        assert_eq!(handler.log_calls.load(Ordering::SeqCst), 3);
+        let last = poller.store.get_last_processed_slot(101).await.unwrap().unwrap();
+        assert_eq!(last.slot, 1);
+    }
+
+    #[tokio::test]
+    async fn undecodable_logs_are_dead_lettered_and_replayed_after_fix() {
+        let parser = Arc::new(BreakableParser {
+            broken: AtomicBool::new(true),
+            inner: TransactionLogParser::new(),
+        });
+        let handler = Arc::new(CountingHandler::default());
+        let dead_letters = Arc::new(InMemoryDeadLetterStore::new());
+
+        let mut poller = test_poller_with_parser(
+            client_with_slots(2),
+            parser.clone(),
+            handler.clone(),
+            test_config(),
+        )
+        .await;
+        poller.set_dead_letter_store(dead_letters.clone());
+        let mut events = poller.subscribe(16);
+
+        poller.process_next_slot().await.unwrap();
+
+        let last = poller.store.get_last_processed_slot(101).await.unwrap().unwrap();
+        assert_eq!(last.slot, 2);
+        assert_eq!(handler.log_calls.load(Ordering::SeqCst), 0);
+        let recorded = dead_letters.list_dead_letters(101).await.unwrap();
+        assert_eq!(recorded.len(), 2);
+        assert!(recorded.iter().all(|d| d.kind == DeadLetterKind::Decode));
+
+        assert_eq!(poller.replay_dead_letters().await.unwrap(), 0);
+        let retried = dead_letters.get_dead_letter(recorded[0].id).await.unwrap().unwrap();
+        assert_eq!(retried.attempts, 2);
+
+        parser.broken.store(false, Ordering::SeqCst);
+        assert_eq!(poller.replay_dead_letters().await.unwrap(), 2);
+        assert_eq!(handler.log_calls.load(Ordering::SeqCst), 2);
+        assert!(dead_letters.list_dead_letters(101).await.unwrap().is_empty());
+
+        // Replayed logs reach subscribers like freshly decoded ones.
+        use futures::{FutureExt, StreamExt};
+        let mut replayed_logs = 0;
+        while let Some(Some(event)) = events.next().now_or_never() {
+            if matches!(event, IndexerEvent::Log(_)) {
+                replayed_logs += 1;
+            }
+        }
+        assert_eq!(replayed_logs, 2);
+    }
+
+    #[tokio::test]
+    async fn dead_letters_of_orphaned_blocks_are_discarded_or_rejected() {
+        let client = client_with_slots(1);
+        let dead_letters = Arc::new(InMemoryDeadLetterStore::new());
+        let mut poller = test_poller(
+            client.clone(),
+            Arc::new(FailingLogHandler::default()),
+            dead_letter_config(),
+        )
+        .await;
+        poller.set_dead_letter_store(dead_letters.clone());
+
+        poller.process_next_slot().await.unwrap();
+        let recorded = dead_letters.list_dead_letters(101).await.unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].blockhash.as_deref(), Some("h2"));
+
+        // Slot 2 is orphaned, taking its dead letter with it.
+        client.slots.lock().unwrap().get_mut(&2).unwrap().blockhash = "h2b".to_string();
+        client.push_slot(3, 1, "h3", 0);
+        poller.process_next_slot().await.unwrap();
+        assert!(dead_letters.list_dead_letters(101).await.unwrap().is_empty());
+
+        // A dead letter whose block was replaced is never replayed.
+        let mut stale = recorded[0].clone();
+        stale.slot = 3;
+        stale.blockhash = Some("h3-orphaned".to_string());
+        let id = dead_letters.save_dead_letter(&stale, 0).await.unwrap();
+        let err = poller.replay_dead_letter(id).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("orphaned"));
+        assert_eq!(dead_letters.get_dead_letter(id).await.unwrap().unwrap().attempts, 4);
+    }
+
+    #[tokio::test]
+    async fn subscribers_receive_events_and_throttle_the_poller() {
+        use futures::StreamExt;
+
+        let mut poller =
+            test_poller(client_with_slots(2), Arc::new(NoopSlotHandler), test_config()).await;
+        let mut first = poller.subscribe(1);
+        let second = poller.subscribe(1);
+        let poller = Arc::new(poller);
+
+        let processing = tokio::spawn({
+            let poller = poller.clone();
+            async move { poller.process_next_slot().await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!processing.is_finished(), "full channel must throttle the poller");
+
+        let (first_events, second_events) = tokio::join!(
+            async {
+                let mut events = Vec::new();
+                for _ in 0..3 {
+                    events.push(first.next().await.unwrap());
+                }
+                events
+            },
+            second.take(3).collect::<Vec<_>>()
+        );
+        processing.await.unwrap().unwrap();
+
+        for events in [first_events, second_events] {
+            assert!(matches!(&events[0], IndexerEvent::Log(l) if l.raw_log.log_index == 0));
+            assert!(matches!(&events[1], IndexerEvent::Log(l) if l.raw_log.log_index == 1));
+            assert!(matches!(&events[2], IndexerEvent::SlotProcessed(s) if s.slot == 2));
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribers_see_finalized_slots_only_once_released() {
+        use futures::{FutureExt, StreamExt};
+
+        for buffered in [false, true] {
+            let client = Arc::new(FakeClient::new());
+            for slot in 1..=6 {
+                client.push_slot(slot, slot - 1, &format!("h{}", slot), 0);
+            }
+            // The buffer holds slots deeper than `max_reorg_depth`.
+            let config = SolanaChainPollerConfig {
+                max_reorg_depth: 2,
+                finality_buffer: buffered.then_some(FinalityBuffer {
+                    depth: Some(3),
+                    finalized: false,
+                }),
+                ..test_config()
+            };
+            let mut poller = test_poller(client, Arc::new(NoopSlotHandler), config).await;
+            let mut events = poller.subscribe(64);
+            poller.process_next_slot().await.unwrap();
+
+            let mut order = Vec::new();
+            while let Some(Some(event)) = events.next().now_or_never() {
+                match event {
+                    IndexerEvent::SlotProcessed(slot) => order.push(("processed", slot.slot)),
+                    IndexerEvent::Finalized { slot } => order.push(("finalized", slot)),
+                    _ => {}
+                }
+            }
+
+            let expected = if buffered {
+                vec![
+                    ("finalized", 1),
+                    ("processed", 2),
+                    ("finalized", 2),
+                    ("processed", 3),
+                    ("finalized", 3),
+                ]
+            } else {
+                vec![
+                    ("processed", 2),
+                    ("processed", 3),
+                    ("finalized", 1),
+                    ("processed", 4),
+                    ("finalized", 2),
+                    ("processed", 5),
+                    ("finalized", 3),
+                    ("processed", 6),
+                    ("finalized", 4),
+                ]
+            };
+            assert_eq!(order, expected, "buffered: {}", buffered);
+        }
+    }
+
+    #[tokio::test]
+    async fn truncated_logs_are_flagged_or_recovered_from_archive() {
+        let truncated: Vec<String> = [
+            "Program Prog1111 invoke [1]",
+            "Program log: first",
+            "Log truncated",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let complete: Vec<String> = [
+            "Program Prog1111 invoke [1]",
+            "Program log: first",
+            "Program log: second",
+            "Program Prog1111 success",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+
+        for recovery in [TruncatedLogRecovery::FlagOnly, TruncatedLogRecovery::Archival] {
+            let client = client_with_slots(0);
+            client.logs.lock().unwrap().insert(
+                2,
+                InvocationTree::parse(&truncated).program_logs(&truncated, PROGRAM, "sig", 2, None),
+            );
+            client.archive.lock().unwrap().insert("sig".to_string(), complete.clone());
+            let handler = Arc::new(RecordingHandler::default());
+
+            let config = SolanaChainPollerConfig {
+                truncated_log_recovery: recovery,
+                ..test_config()
+            };
+            let poller = test_poller(client, handler.clone(), config).await;
+            poller.process_next_slot().await.unwrap();
+
+            let logs = handler.logs.lock().unwrap();
+            let messages: Vec<_> = logs.iter().map(|l| l.raw_log.log_message.as_str()).collect();
+            match recovery {
+                TruncatedLogRecovery::FlagOnly => {
+                    assert_eq!(messages.len(), 3);
+                    assert!(logs.iter().all(|l| l.log.truncated));
+                }
+                TruncatedLogRecovery::Archival => {
+                    assert_eq!(messages.len(), 4);
+                    assert!(messages.contains(&"Program log: second"));
+                    assert!(logs.iter().all(|l| !l.log.truncated));
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn truncation_is_detected_from_transactions_and_recovered_in_place() {
+        let lines = |lines: &[&str]| lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        let client = client_with_slots(1);
+        client.set_transactions(
+            2,
+            vec![
+                SolanaTransaction {
+                    signature: "sig-cut".to_string(),
+                    program_ids: vec!["Other111".to_string(), PROGRAM.to_string()],
+                    log_messages: lines(&["Program Other111 invoke [1]", "Log truncated"]),
+                    ..transaction(2)
+                },
+                SolanaTransaction {
+                    program_ids: vec![PROGRAM.to_string()],
+                    ..transaction(2)
+                },
+            ],
+        );
+        client.archive.lock().unwrap().insert(
+            "sig-cut".to_string(),
+            lines(&[
+                "Program Other111 invoke [1]",
+                "Program Other111 success",
+                "Program Prog1111 invoke [1]",
+                "Program log: late",
+                "Program Prog1111 success",
+            ]),
+        );
+        let handler = Arc::new(RecordingHandler::default());
+
+        let config = SolanaChainPollerConfig {
+            truncated_log_recovery: TruncatedLogRecovery::Archival,
+            ..test_config()
+        };
+        let poller = test_poller(client, handler.clone(), config).await;
+        poller.process_next_slot().await.unwrap();
+
+        let logs = handler.logs.lock().unwrap();
+        let delivered: Vec<_> = logs
+            .iter()
+            .map(|l| (l.raw_log.signature.as_str(), l.raw_log.log_message.as_str()))
+            .collect();
+        assert!(delivered.contains(&("sig-cut", "Program log: late")));
+        assert!(delivered.iter().all(|(_, m)| !m.starts_with("Program Other111")));
+        assert_eq!(delivered.last(), Some(&("sig-2", "Program log: event 0")));
+        assert_eq!(delivered[0].0, "sig-cut");
+    }
+
+    #[tokio::test]
+    async fn failed_transaction_logs_are_tagged_dropped_or_routed() {
+        use solana_sdk::transaction::TransactionError;
+
+        for policy in [
+            FailedTransactionLogs::Include,
+            FailedTransactionLogs::Drop,
+            FailedTransactionLogs::Route,
+        ] {
+            let client = client_with_slots(2);
+            client.set_transactions(
+                2,
+                vec![SolanaTransaction {
+                    fee: 5000,
+                    success: false,
+                    error: Some(TransactionError::AccountInUse),
+                    ..transaction(2)
+                }],
+            );
+            let handler = Arc::new(RecordingHandler::default());
+
+            let config = SolanaChainPollerConfig {
+                failed_transaction_logs: policy,
+                ..test_config()
+            };
+            let poller = test_poller(client, handler.clone(), config).await;
+            poller.process_next_slot().await.unwrap();
+
+            let logs = handler.logs.lock().unwrap();
+            let failed_logs = handler.failed_logs.lock().unwrap();
+            match policy {
+                FailedTransactionLogs::Include => {
+                    assert_eq!(logs.len(), 2);
+                    assert!(logs.iter().all(|l| !l.success));
+                    assert_eq!(logs[0].error, Some(TransactionError::AccountInUse));
+                }
+                FailedTransactionLogs::Drop => {
+                    assert!(logs.is_empty() && failed_logs.is_empty());
+                }
+                FailedTransactionLogs::Route => {
+                    assert!(logs.is_empty());
+                    assert_eq!(failed_logs.len(), 2);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn hooks_fire_only_for_interesting_programs() {
+        use crate::clients::solana::SolanaInnerInstruction;
+
+        let client = client_with_slots(0);
+        client.set_transactions(
+            2,
+            vec![
+                // PROGRAM invokes Token111, which is delivered as its child.
+                SolanaTransaction {
+                    signature: "sig-a".to_string(),
+                    account_keys: vec![
+                        "Payer".to_string(),
+                        PROGRAM.to_string(),
+                        "Token111".to_string(),
+                    ],
+                    program_ids: vec![PROGRAM.to_string(), "Token111".to_string()],
+                    instructions: vec![instruction(1, vec![0])],
+                    inner_instructions: vec![SolanaInnerInstruction {
+                        index: 0,
+                        instructions: vec![SolanaInstruction {
+                            stack_height: Some(2),
+                            ..instruction(2, vec![0])
+                        }],
+                    }],
+                    ..transaction(2)
+                },
+                SolanaTransaction {
+                    signature: "sig-b".to_string(),
+                    account_keys: vec!["Payer".to_string(), "Other111".to_string()],
+                    program_ids: vec!["Other111".to_string()],
+                    instructions: vec![instruction(1, vec![0])],
+                    ..transaction(2)
+                },
+            ],
+        );
+        let handler = Arc::new(RecordingHandler::default());
+        let poller = test_poller(client, handler.clone(), test_config()).await;
+        poller.process_next_slot().await.unwrap();
+
+        assert_eq!(*handler.transactions.lock().unwrap(), vec!["sig-a".to_string()]);
+        let instructions = handler.instructions.lock().unwrap();
+        let summary: Vec<_> = instructions
+            .iter()
+            .map(|i| (i.signature.as_str(), i.program_id.as_str(), i.parent_program_id()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("sig-a", PROGRAM, None), ("sig-a", "Token111", Some(PROGRAM))]
+        );
+    }
+
+    #[tokio::test]
+    async fn return_data_is_decoded_with_the_emitting_instruction_idl() {
+        use crate::transaction_log_parser::{Idl, IdlStore};
+
+        let idl_store = Arc::new(IdlStore::new());
+        idl_store.add_idl(
+            PROGRAM,
+            Idl::from_json(
+                r#"{"instructions": [{"name": "quote", "discriminator": [5, 5, 5, 5, 5, 5, 5, 5], "returns": {"option": "u64"}}]}"#,
+            )
+            .unwrap(),
+        );
+        let client = client_with_slots(1);
+        client.logs.lock().unwrap().get_mut(&2).unwrap()[0].log_message =
+            format!("Program return: {} AQcAAAAAAAAA", PROGRAM);
+        client.set_transactions(
+            2,
+            vec![SolanaTransaction {
+                account_keys: vec!["Payer".to_string(), PROGRAM.to_string()],
+                program_ids: vec![PROGRAM.to_string()],
+                instructions: vec![SolanaInstruction {
+                    data: bs58::encode([5; 8]).into_string(),
+                    ..instruction(1, vec![0])
+                }],
+                ..transaction(2)
+            }],
+        );
+        let handler = Arc::new(RecordingHandler::default());
+        let poller = test_poller_with_parser(
+            client,
+            Arc::new(TransactionLogParser::with_idl_store(idl_store)),
+            handler.clone(),
+            test_config(),
+        )
+        .await;
+        poller.process_next_slot().await.unwrap();
+
+        let logs = handler.logs.lock().unwrap();
+        let return_data = logs[0].return_data.as_ref().expect("return data is parsed");
+        assert_eq!(return_data.value_type.as_deref(), Some("option<u64>"));
+        assert_eq!(return_data.value, Some(serde_json::json!("7")));
+    }
+
+    #[tokio::test]
+    async fn on_chain_idls_are_refreshed_before_instructions_are_decoded() {
+        use crate::transaction_log_parser::idl_account::{encode_idl_account, idl_address};
+        use crate::transaction_log_parser::IdlStore;
+
+        let program = solana_sdk::pubkey::Pubkey::new_unique();
+        let program_id = program.to_string();
+        let client = client_with_slots(0);
+        // Not owned by the upgradeable loader, so its IDL never changes.
+        client.set_account(&program_id, "BPFLoader2111111111111111111111111111111111", &[]);
+        client.set_account(
+            &idl_address(&program).unwrap().to_string(),
+            &program_id,
+            &encode_idl_account(
+                r#"{"instructions": [{"name": "quote", "discriminator": [5, 5, 5, 5, 5, 5, 5, 5]}]}"#,
+            ),
+        );
+        client.set_transactions(
+            2,
+            vec![SolanaTransaction {
+                account_keys: vec!["Payer".to_string(), program_id.clone()],
+                program_ids: vec![program_id.clone()],
+                instructions: vec![SolanaInstruction {
+                    data: bs58::encode([5; 8]).into_string(),
+                    ..instruction(1, vec![0])
+                }],
+                ..transaction(2)
+            }],
+        );
+        let handler = Arc::new(RecordingHandler::default());
+        let idl_store = Arc::new(IdlStore::new());
+        let config = SolanaChainPollerConfig {
+            interesting_programs: vec![program_id.clone()],
+            ..test_config()
+        };
+        let mut poller = test_poller_with_parser(
+            client.clone(),
+            Arc::new(TransactionLogParser::with_idl_store(idl_store.clone())),
+            handler.clone(),
+            config,
+        )
+        .await;
+        poller.set_idl_fetcher(Arc::new(OnChainIdlFetcher::new(client, idl_store.clone())));
+        poller.process_next_slot().await.unwrap();
+
+        assert!(idl_store.get_idl(&program_id).is_some());
+        let instructions = handler.instructions.lock().unwrap();
+        let decoded = instructions[0].decoded.as_ref().expect("instruction is decoded");
+        assert_eq!(decoded.name, "quote");
+    }
+
+    #[tokio::test]
+    async fn tracks_accounts_from_snapshot_updates_and_reorgs() {
+        let client = Arc::new(FakeClient::new());
+        client.push_slot(1, 0, "h1", 0);
+        client.set_account("Acc1", PROGRAM, &[1]);
+        client.set_account("Payer", "11111111111111111111111111111111", &[]);
+        let handler = Arc::new(RecordingHandler::default());
+        let mut poller = test_poller(client.clone(), handler.clone(), test_config()).await;
+        poller.set_account_tracker(Arc::new(AccountTracker::new()));
+
+        // The first poll snapshots the tracked accounts.
+        poller.process_next_slot().await.unwrap();
+
+        // Slot 2 writes Acc1; the payer is passed along but not program-owned.
+        client.push_slot(2, 1, "h2", 0);
+        client.set_transactions(
+            2,
+            vec![SolanaTransaction {
+                account_keys: vec!["Payer".to_string(), "Acc1".to_string(), PROGRAM.to_string()],
+                program_ids: vec![PROGRAM.to_string()],
+                instructions: vec![instruction(2, vec![0, 1])],
+                ..transaction(2)
+            }],
+        );
+        client.set_account("Acc1", PROGRAM, &[2]);
+        poller.process_next_slot().await.unwrap();
+        assert_eq!(*client.min_context_slots.lock().unwrap(), vec![Some(2)]);
+
+        // Slot 2 is orphaned: Acc1 goes back to its snapshot state.
+        client.slots.lock().unwrap().get_mut(&2).unwrap().blockhash = "h2b".to_string();
+        client.push_slot(3, 1, "h3", 0);
+        poller.process_next_slot().await.unwrap();
+
+        let updates = handler.account_updates.lock().unwrap();
+        let summary: Vec<(&str, u64, Option<Vec<u8>>, &AccountUpdateSource)> = updates
+            .iter()
+            .map(|u| {
+                (
+                    u.pubkey.as_str(),
+                    u.slot,
+                    u.account.as_ref().map(|a| a.data.clone()),
+                    &u.source,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Acc1", 1, Some(vec![1]), &AccountUpdateSource::Snapshot),
+                (
+                    "Acc1",
+                    2,
+                    Some(vec![2]),
+                    &AccountUpdateSource::Transaction {
+                        signature: "sig-2".to_string()
+                    }
+                ),
+                ("Acc1", 1, Some(vec![1]), &AccountUpdateSource::Rollback { orphaned_slot: 2 }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn accounts_are_snapshotted_again_when_the_lease_is_reacquired() {
+        use crate::chain_pollers::persistence::memory::InMemoryLeaseManager;
+
+        let client = Arc::new(FakeClient::new());
+        client.push_slot(1, 0, "h1", 0);
+        client.set_account("Acc1", PROGRAM, &[1]);
+        let handler = Arc::new(RecordingHandler::default());
+        let mut poller = test_poller(client.clone(), handler.clone(), test_config()).await;
+        poller.set_account_tracker(Arc::new(AccountTracker::new()));
+        let lease_manager = Arc::new(InMemoryLeaseManager::new());
+        poller.set_lease_manager(lease_manager.clone(), "a".to_string());
+
+        assert!(poller.hold_lease().await);
+        poller.process_next_slot().await.unwrap();
+
+        // Another replica takes over and Acc1 changes while it holds the lease.
+        let lease = lease_manager.get_lease(101).await.unwrap().unwrap();
+        lease_manager.release(&lease).await.unwrap();
+        let ttl = Duration::from_secs(30);
+        let other = lease_manager.try_acquire(101, "b", ttl).await.unwrap().unwrap();
+        client.set_account("Acc1", PROGRAM, &[2]);
+        lease_manager.release(&other).await.unwrap();
+
+        assert!(poller.hold_lease().await);
+        poller.process_next_slot().await.unwrap();
+
+        let updates = handler.account_updates.lock().unwrap();
+        let data: Vec<_> = updates
+            .iter()
+            .map(|u| u.account.as_ref().map(|a| a.data.clone()))
+            .collect();
+        assert_eq!(data, vec![Some(vec![1]), Some(vec![2])]);
+        assert!(updates.iter().all(|u| u.source == AccountUpdateSource::Snapshot));
+    }
+
+    #[tokio::test]
+    async fn slot_in_flight_during_failover_is_committed_once() {
+        use crate::chain_pollers::persistence::memory::InMemoryLeaseManager;
+
+        /// Hands the lease to replica "b" while replica "a" delivers a slot.
+        struct TakeoverHandler {
+            inner: Arc<RecordingHandler>,
+            lease_manager: Arc<InMemoryLeaseManager>,
+        }
+
+        #[async_trait]
+        impl SlotHandler for TakeoverHandler {
+            async fn handle_slot(&self, slot: &SolanaSlot) -> Result<()> {
+                let lease = self.lease_manager.get_lease(101).await?.unwrap();
+                self.lease_manager.release(&lease).await?;
+                self.lease_manager
+                    .try_acquire(101, "b", Duration::from_secs(30))
+                    .await?;
+                self.inner.handle_slot(slot).await
+            }
+
+            async fn handle_log(&self, log_with_slot: &LogWithSlot) -> Result<()> {
+                self.inner.handle_log(log_with_slot).await
+            }
+
+            async fn handle_reorg_slot(&self, slot_number: u64) -> Result<()> {
+                self.inner.handle_reorg_slot(slot_number).await
+            }
+
+            async fn commit_slot(&self, slot: &SolanaSlot, epoch: u64) -> Result<()> {
+                self.inner.commit_slot(slot, epoch).await
+            }
+        }
+
+        let client = client_with_slots(1);
+        let lease_manager = Arc::new(InMemoryLeaseManager::new());
+        let handler = Arc::new(RecordingHandler::default());
+        let takeover = Arc::new(TakeoverHandler {
+            inner: handler.clone(),
+            lease_manager: lease_manager.clone(),
+        });
+
+        let mut primary = test_poller(client.clone(), takeover, test_config()).await;
+        primary.set_lease_manager(lease_manager.clone(), "a".to_string());
+        let mut standby = test_poller(client, handler.clone(), test_config()).await;
+        standby.store = primary.store.clone();
+        standby.set_lease_manager(lease_manager.clone(), "b".to_string());
+
+        assert!(primary.hold_lease().await);
+        assert!(primary.process_next_slot().await.is_err());
+        assert!(handler.commits.lock().unwrap().is_empty());
+
+        assert!(standby.hold_lease().await);
+        standby.process_next_slot().await.unwrap();
+
+        // Delivered by both replicas, but committed once, by the new holder.
+        let epoch = lease_manager.get_lease(101).await.unwrap().unwrap().epoch;
+        assert_eq!(*handler.slots.lock().unwrap(), vec!["h2", "h2"]);
+        assert_eq!(*handler.commits.lock().unwrap(), vec![(2, epoch)]);
+        let last = primary.store.get_last_processed_slot(101).await.unwrap().unwrap();
+        assert_eq!(last.slot, 2);
+    }
+
+    #[tokio::test]
+    async fn failed_account_refetch_does_not_redeliver_transaction_hooks() {
+        let client = client_with_slots(0);
+        client.set_transactions(
+            2,
+            vec![SolanaTransaction {
+                account_keys: vec!["Acc1".to_string(), PROGRAM.to_string()],
+                program_ids: vec![PROGRAM.to_string()],
+                instructions: vec![instruction(1, vec![0])],
+                ..transaction(2)
+            }],
+        );
+        let handler = Arc::new(RecordingHandler::default());
+        let mut poller = test_poller(client.clone(), handler.clone(), test_config()).await;
+        poller.set_account_tracker(Arc::new(AccountTracker::new()));
+
+        client.fail_account_fetches.store(true, Ordering::SeqCst);
+        assert!(poller.process_next_slot().await.is_err());
+        assert!(handler.transactions.lock().unwrap().is_empty());
+
+        client.fail_account_fetches.store(false, Ordering::SeqCst);
+        poller.process_next_slot().await.unwrap();
+        assert_eq!(*handler.transactions.lock().unwrap(), vec!["sig-2".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn transaction_filter_selects_what_is_indexed() {
+        let signed_by_bot = TransactionFilter::SignedBy(vec!["Bot".to_string()]);
+        for (filter, indexed) in [
+            (None, false),
+            (Some(signed_by_bot.clone()), true),
+            (Some(TransactionFilter::Not(Box::new(signed_by_bot))), false),
+        ] {
+            let client = client_with_slots(2);
+            client.set_account("Acc1", PROGRAM, &[1]);
+            // Signed by Bot and writes Acc1 through PROGRAM, but PROGRAM is
+            // missing from `program_ids`, so the default rule skips it.
+            client.set_transactions(
+                2,
+                vec![SolanaTransaction {
+                    account_keys: vec!["Bot".to_string(), "Acc1".to_string(), PROGRAM.to_string()],
+                    instructions: vec![instruction(2, vec![1])],
+                    ..transaction(2)
+                }],
+            );
+            let handler = Arc::new(RecordingHandler::default());
+
+            let config = SolanaChainPollerConfig {
+                transaction_filter: filter.clone(),
+                ..test_config()
+            };
+            let mut poller = test_poller(client, handler.clone(), config).await;
+            poller.set_account_tracker(Arc::new(AccountTracker::new()));
+            poller.process_next_slot().await.unwrap();
+
+            let transactions = handler.transactions.lock().unwrap();
+            let logs = handler.logs.lock().unwrap();
+            assert_eq!(!transactions.is_empty(), indexed, "{:?}", filter);
+            if filter.is_some() {
+                assert_eq!(logs.len(), if indexed { 2 } else { 0 }, "{:?}", filter);
+            }
+            // Filtered-out transactions still refresh the accounts they write.
+            let updates = handler.account_updates.lock().unwrap();
+            assert!(
+                updates.iter().any(|u| u.slot == 2 && u.pubkey == "Acc1"),
+                "{:?}",
+                filter
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn finality_buffer_delays_delivery_and_absorbs_reorgs() {
+        let client = Arc::new(FakeClient::new());
+        client.push_slot(1, 0, "h1", 0);
+        client.push_slot(2, 1, "h2", 1);
+        client.push_slot(3, 2, "h3", 1);
+        let handler = Arc::new(RecordingHandler::default());
+        let buffer = FinalityBuffer {
+            depth: Some(2),
+            finalized: true,
+        };
+        let config = || SolanaChainPollerConfig {
+            finality_buffer: Some(buffer),
+            ..test_config()
+        };
+        let poller = test_poller(client.clone(), handler.clone(), config()).await;
+        let delivered = || handler.slots.lock().unwrap().clone();
+
+        // Slots 2 and 3 arrive in one tick and are only buffered.
+        poller.process_next_slot().await.unwrap();
+        assert!(delivered().is_empty());
+        assert!(handler.logs.lock().unwrap().is_empty());
+
+        // Slot 4 builds on 2, orphaning the buffered slot 3.
+        client.slots.lock().unwrap().get_mut(&3).unwrap().blockhash = "h3b".to_string();
+        client.push_slot(4, 2, "h4", 1);
+        poller.process_next_slot().await.unwrap();
+
+        // The new fork gets a slot 3 after all and grows to 5; 2 and 3 are
+        // now two slots deep.
+        client.push_slot(3, 2, "h3c", 1);
+        client.push_slot(4, 3, "h4", 1);
+        client.push_slot(5, 4, "h5", 1);
+        poller.process_next_slot().await.unwrap();
+        assert_eq!(delivered(), vec!["h2", "h3c"]);
+        assert!(handler.reorg_slots.lock().unwrap().is_empty());
+
+        // A standby taking over the store rebuilds the unreleased slots.
+        let mut standby = test_poller(client.clone(), Arc::new(NoopSlotHandler), config()).await;
+        standby.store = poller.store.clone();
+        standby.process_next_slot().await.unwrap();
+        let restored: Vec<u64> = standby.buffered_slots.lock().await.iter().map(|s| s.slot).collect();
+        assert_eq!(restored, vec![4, 5]);
+
+        // Finalized slots are released without waiting for more depth.
+        client.finalized.store(5, Ordering::SeqCst);
+        poller.process_next_slot().await.unwrap();
+        assert_eq!(delivered(), vec!["h2", "h3c", "h4", "h5"]);
+        assert_eq!(handler.logs.lock().unwrap().len(), 4);
+        assert_eq!(poller.store.get_last_released_slot(101).await.unwrap(), Some(5));
+    }
+
+    #[tokio::test]
+    async fn finality_buffer_config_is_validated() {
+        for (depth, finalized, valid) in [
+            (None, false, false),
+            (Some(101), false, false),
+            (Some(100), false, true),
+            (None, true, true),
+        ] {
+            let config = SolanaChainPollerConfig {
+                finality_buffer: Some(FinalityBuffer { depth, finalized }),
+                ..test_config()
+            };
+            let poller = SolanaChainPoller::new(
+                Arc::new(FakeClient::new()),
+                Arc::new(TransactionLogParser::new()),
+                config,
+                Arc::new(InMemoryChainPollerPersistence::new()),
+                Arc::new(NoopSlotHandler),
+            );
+            assert_eq!(poller.is_ok(), valid, "{:?} {}", depth, finalized);
+        }
+    }
+
+    #[tokio::test]
+    async fn finalized_only_buffer_applies_backpressure() {
+        use futures::{FutureExt, StreamExt};
+
+        let client = Arc::new(FakeClient::new());
+        for slot in 1..=6 {
+            client.push_slot(slot, slot - 1, &format!("h{}", slot), 1);
+        }
+        let handler = Arc::new(RecordingHandler::default());
+        let config = SolanaChainPollerConfig {
+            max_reorg_depth: 1,
+            slot_history_size: 3,
+            finality_buffer: Some(FinalityBuffer {
+                depth: None,
+                finalized: true,
+            }),
+            ..test_config()
+        };
+        let mut poller = test_poller(client.clone(), handler.clone(), config).await;
+        let mut events = poller.subscribe(64);
+        let buffered = || async {
+            let slots: Vec<u64> = poller.buffered_slots.lock().await.iter().map(|s| s.slot).collect();
+            slots
+        };
+
+        // Slot 5 would prune slot 2 from the history, so fetching stops.
+        poller.process_next_slot().await.unwrap();
+        assert_eq!(buffered().await, vec![2, 3, 4]);
+        assert!(poller.store.get_slot(101, 2).await.unwrap().is_some());
+
+        // Without a depth, a failing finalized lookup is an error.
+        client.fail_finalized.store(true, Ordering::SeqCst);
+        assert!(poller.process_next_slot().await.is_err());
+
+        client.fail_finalized.store(false, Ordering::SeqCst);
+        client.finalized.store(4, Ordering::SeqCst);
+        poller.process_next_slot().await.unwrap();
+        assert_eq!(*handler.slots.lock().unwrap(), vec!["h2", "h3", "h4"]);
+        assert_eq!(buffered().await, vec![5, 6]);
+
+        // Finalized events never run ahead of released slots.
+        let mut finalized = Vec::new();
+        while let Some(Some(event)) = events.next().now_or_never() {
+            if let IndexerEvent::Finalized { slot } = event {
+                finalized.push(slot);
+            }
+        }
+        assert_eq!(finalized, vec![1, 3, 4]);
+
+        // Finalization keeps advancing as later releases catch up with the
+        // depth, even though no new slots are fetched.
+        client.finalized.store(6, Ordering::SeqCst);
+        poller.process_next_slot().await.unwrap();
+        assert!(buffered().await.is_empty());
+        let mut finalized = Vec::new();
+        while let Some(Some(event)) = events.next().now_or_never() {
+            if let IndexerEvent::Finalized { slot } = event {
+                finalized.push(slot);
+            }
+        }
+        assert_eq!(finalized, vec![5]);
+    }
+}
@@ -0,0 +1,72 @@
+use crate::chain_pollers::persistence::*;
+use crate::clients::solana::{BalanceChange, SolanaSlot};
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+#[derive(Debug, Clone)]
+pub enum IndexerEvent {
+    /// A slot and all of its logs have been handled and saved.
+    SlotProcessed(SolanaSlot),
+    Log(Box<LogWithSlot>),
+    /// A SOL or SPL token balance changed by a transaction that invokes an
+    /// interesting program.
+    BalanceChange(Box<BalanceChange>),
+    /// An account owned by an interesting program changed or was rolled back.
+    AccountUpdate(Box<AccountUpdate>),
+    /// A previously processed slot was orphaned by a reorg.
+    Reorg { slot: u64 },
+    /// Every processed slot up to and including `slot` is deeper than
+    /// `max_reorg_depth`, has left the finality buffer, and will no longer be
+    /// reorganized by this poller.
+    Finalized { slot: u64 },
+}
+
+/// Stream returned by `SolanaChainPoller::subscribe`. Implements
+/// `futures::Stream<Item = IndexerEvent>`.
+pub type IndexerEventStream = ReceiverStream<IndexerEvent>;
+
+/// Bounded fan-out to every subscriber. Emitting waits for room in each
+/// subscriber's channel, so a slow consumer throttles the poller.
+#[derive(Default)]
+pub(crate) struct EventSubscribers {
+    senders: Vec<mpsc::Sender<IndexerEvent>>,
+}
+
+impl EventSubscribers {
+    pub(crate) fn subscribe(&mut self, capacity: usize) -> IndexerEventStream {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        self.senders.push(sender);
+        ReceiverStream::new(receiver)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    pub(crate) async fn emit(&self, event: IndexerEvent) {
+        for sender in &self.senders {
+            // A dropped stream just stops receiving; it never blocks the poller.
+            let _ = sender.send(event.clone()).await;
+        }
+    }
+}
+
+/// `SlotHandler` that does nothing, for pollers consumed only through
+/// `SolanaChainPoller::subscribe`.
+pub struct NoopSlotHandler;
+
+#[async_trait]
+impl SlotHandler for NoopSlotHandler {
+    async fn handle_slot(&self, _slot: &SolanaSlot) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn handle_log(&self, _log_with_slot: &LogWithSlot) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn handle_reorg_slot(&self, _slot_number: u64) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use crate::clients::solana::{SolanaTokenBalance, SolanaTransaction};
+
+/// Decimals of native SOL balances, which are in lamports.
+pub const SOL_DECIMALS: u8 = 9;
+
+/// Change of one account's SOL or SPL token balance over a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalanceChange {
+    pub signature: String,
+    pub slot: u64,
+    pub account: String,
+    /// Owner of the token account; `None` for SOL balances.
+    pub owner: Option<String>,
+    /// Token mint, or `None` for the account's SOL balance in lamports.
+    pub mint: Option<String>,
+    pub pre: u64,
+    pub post: u64,
+    pub delta: i128,
+    pub decimals: u8,
+}
+
+impl BalanceChange {
+    /// Every non-zero SOL and token balance change of `transaction`, SOL
+    /// first, in account order. The fee payer's SOL change includes the fee.
+    /// Token accounts created or closed by the transaction count as having a
+    /// zero balance on the side they are missing from.
+    pub fn from_transaction(transaction: &SolanaTransaction) -> Vec<Self> {
+        let account = |index: usize| transaction.account_keys.get(index).cloned().unwrap_or_default();
+        let change = |account: String, owner, mint, pre: u64, post: u64, decimals| Self {
+            signature: transaction.signature.clone(),
+            slot: transaction.slot,
+            account,
+            owner,
+            mint,
+            pre,
+            post,
+            delta: post as i128 - pre as i128,
+            decimals,
+        };
+
+        let mut changes: Vec<Self> = transaction
+            .pre_balances
+            .iter()
+            .zip(&transaction.post_balances)
+            .enumerate()
+            .filter(|(_, (pre, post))| pre != post)
+            .map(|(index, (&pre, &post))| change(account(index), None, None, pre, post, SOL_DECIMALS))
+            .collect();
+
+        let mut token_accounts: Vec<(usize, &str)> = transaction
+            .pre_token_balances
+            .iter()
+            .chain(&transaction.post_token_balances)
+            .map(|b| (b.account_index, b.mint.as_str()))
+            .collect();
+        token_accounts.sort_unstable();
+        token_accounts.dedup();
+
+        for (index, mint) in token_accounts {
+            let find = |balances: &'_ [SolanaTokenBalance]| {
+                balances
+                    .iter()
+                    .find(|b| b.account_index == index && b.mint == mint)
+                    .cloned()
+            };
+            let pre = find(&transaction.pre_token_balances);
+            let post = find(&transaction.post_token_balances);
+            let pre_amount = pre.as_ref().map_or(0, |b| b.amount);
+            let post_amount = post.as_ref().map_or(0, |b| b.amount);
+            if pre_amount == post_amount {
+                continue;
+            }
+
+            let latest = post.or(pre).expect("token account comes from one of the balances");
+            changes.push(change(
+                account(index),
+                latest.owner,
+                Some(mint.to_string()),
+                pre_amount,
+                post_amount,
+                latest.decimals,
+            ));
+        }
+
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balance_changes_cover_sol_and_tokens() {
+        let token_balance = |account_index, owner: &str, amount| SolanaTokenBalance {
+            account_index,
+            mint: "Mint".to_string(),
+            owner: Some(owner.to_string()),
+            program_id: None,
+            amount,
+            decimals: 6,
+        };
+        // Payer pays the fee and rent for a new token account (2) that
+        // receives tokens from an existing one (1), which is left unchanged
+        // in SOL.
+        let transaction = SolanaTransaction {
+            signature: "sig".to_string(),
+            slot: 7,
+            block_time: None,
+            fee: 5000,
+            success: true,
+            error: None,
+            account_keys: vec!["Payer".to_string(), "Source".to_string(), "Dest".to_string()],
+            program_ids: Vec::new(),
+            log_messages: Vec::new(),
+            pre_balances: vec![1_000_000, 2_039_280, 0],
+            post_balances: vec![955_720, 2_039_280, 2_039_280],
+            pre_token_balances: vec![token_balance(1, "Alice", 100)],
+            post_token_balances: vec![token_balance(1, "Alice", 40), token_balance(2, "Bob", 60)],
+            instructions: Vec::new(),
+            inner_instructions: Vec::new(),
+            ..Default::default()
+        };
+
+        let summary: Vec<_> = BalanceChange::from_transaction(&transaction)
+            .into_iter()
+            .map(|c| (c.account, c.owner, c.mint, c.pre, c.post, c.delta, c.decimals))
+            .collect();
+        let mint = Some("Mint".to_string());
+        assert_eq!(
+            summary,
+            vec![
+                ("Payer".to_string(), None, None, 1_000_000, 955_720, -44_280, SOL_DECIMALS),
+                ("Dest".to_string(), None, None, 0, 2_039_280, 2_039_280, SOL_DECIMALS),
+                ("Source".to_string(), Some("Alice".to_string()), mint.clone(), 100, 40, -60, 6),
+                ("Dest".to_string(), Some("Bob".to_string()), mint, 0, 60, 60, 6),
+            ]
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::clients::solana::types::*;
+use crate::transaction_log_parser::InvocationTree;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -15,12 +16,14 @@ struct RPCRequest {
 }
 
 #[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
 struct RPCError {
     code: i64,
     message: String,
 }
 
 #[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)]
 struct RPCResponse {
     jsonrpc: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -31,6 +34,352 @@ struct RPCResponse {
     error: Option<RPCError>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcBlock {
+    blockhash: String,
+    parent_slot: u64,
+    block_time: Option<i64>,
+    #[serde(default)]
+    transactions: Vec<RpcTransactionWithMeta>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RpcTransactionWithMeta {
+    transaction: RpcTransaction,
+    meta: Option<RpcTransactionMeta>,
+    #[serde(default)]
+    version: Option<solana_sdk::transaction::TransactionVersion>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RpcTransaction {
+    signatures: Vec<String>,
+    message: RpcMessage,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcMessage {
+    account_keys: Vec<String>,
+    #[serde(default)]
+    header: Option<RpcMessageHeader>,
+    #[serde(default)]
+    recent_blockhash: String,
+    instructions: Vec<RpcInstruction>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcMessageHeader {
+    num_required_signatures: usize,
+    num_readonly_signed_accounts: usize,
+    num_readonly_unsigned_accounts: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcReturnData {
+    program_id: String,
+    /// `[data, "base64"]`.
+    data: (String, String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcInstruction {
+    program_id_index: usize,
+    accounts: Vec<usize>,
+    data: String,
+    #[serde(default)]
+    stack_height: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RpcInnerInstruction {
+    index: usize,
+    instructions: Vec<RpcInstruction>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct RpcLoadedAddresses {
+    writable: Vec<String>,
+    readonly: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RpcAccount {
+    lamports: u64,
+    owner: String,
+    /// `[data, encoding]`, requested as base64.
+    data: (String, String),
+    executable: bool,
+}
+
+impl RpcAccount {
+    fn into_account(self, pubkey: &str, slot: u64) -> Result<SolanaAccount> {
+        use base64::Engine;
+
+        anyhow::ensure!(
+            self.data.1 == "base64",
+            "Unexpected account data encoding: {}",
+            self.data.1
+        );
+        Ok(SolanaAccount {
+            pubkey: pubkey.to_string(),
+            owner: self.owner,
+            lamports: self.lamports,
+            data: base64::engine::general_purpose::STANDARD
+                .decode(&self.data.0)
+                .context("Invalid account data")?,
+            executable: self.executable,
+            slot,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcTransactionMeta {
+    err: Option<serde_json::Value>,
+    fee: u64,
+    pre_balances: Vec<u64>,
+    post_balances: Vec<u64>,
+    #[serde(default)]
+    inner_instructions: Option<Vec<RpcInnerInstruction>>,
+    #[serde(default)]
+    log_messages: Option<Vec<String>>,
+    #[serde(default)]
+    loaded_addresses: Option<RpcLoadedAddresses>,
+    #[serde(default)]
+    pre_token_balances: Option<Vec<RpcTokenBalance>>,
+    #[serde(default)]
+    post_token_balances: Option<Vec<RpcTokenBalance>>,
+    #[serde(default)]
+    compute_units_consumed: Option<u64>,
+    #[serde(default)]
+    return_data: Option<RpcReturnData>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcTokenBalance {
+    account_index: usize,
+    mint: String,
+    #[serde(default)]
+    owner: Option<String>,
+    #[serde(default)]
+    program_id: Option<String>,
+    ui_token_amount: RpcUiTokenAmount,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct RpcUiTokenAmount {
+    amount: String,
+    decimals: u8,
+}
+
+impl RpcTokenBalance {
+    fn into_token_balance(self) -> Option<SolanaTokenBalance> {
+        Some(SolanaTokenBalance {
+            account_index: self.account_index,
+            mint: self.mint,
+            owner: self.owner,
+            program_id: self.program_id,
+            amount: self.ui_token_amount.amount.parse().ok()?,
+            decimals: self.ui_token_amount.decimals,
+        })
+    }
+}
+
+fn token_balances(balances: Option<&Vec<RpcTokenBalance>>) -> Vec<SolanaTokenBalance> {
+    balances
+        .into_iter()
+        .flatten()
+        .cloned()
+        .filter_map(RpcTokenBalance::into_token_balance)
+        .collect()
+}
+
+impl From<RpcInstruction> for SolanaInstruction {
+    fn from(instruction: RpcInstruction) -> Self {
+        Self {
+            program_id_index: instruction.program_id_index,
+            accounts: instruction.accounts,
+            data: instruction.data,
+            stack_height: instruction.stack_height,
+        }
+    }
+}
+
+impl RpcBlock {
+    fn into_slot(self, slot_number: u64) -> SolanaSlot {
+        let transactions = self
+            .transactions
+            .into_iter()
+            .map(|tx| tx.into_transaction(slot_number, self.block_time))
+            .collect();
+
+        SolanaSlot {
+            slot: slot_number,
+            parent: Some(self.parent_slot),
+            blockhash: self.blockhash,
+            block_time: self.block_time,
+            transactions,
+            chain_id: 0,
+        }
+    }
+}
+
+impl RpcTransactionWithMeta {
+    fn into_transaction(self, slot: u64, block_time: Option<i64>) -> SolanaTransaction {
+        let meta = self.meta;
+        let loaded_addresses = meta
+            .as_ref()
+            .and_then(|m| m.loaded_addresses.clone())
+            .unwrap_or_default();
+
+        // Address lookup table keys follow the static keys, writable first.
+        // Only static keys can sign.
+        let mut account_keys = self.transaction.message.account_keys;
+        let static_keys = account_keys.len();
+        let (mut signer_flags, mut writable_flags): (Vec<bool>, Vec<bool>) =
+            match &self.transaction.message.header {
+                Some(header) => (0..static_keys)
+                    .map(|i| {
+                        let signed = header.num_required_signatures;
+                        let writable = if i < signed {
+                            i < signed.saturating_sub(header.num_readonly_signed_accounts)
+                        } else {
+                            i < static_keys.saturating_sub(header.num_readonly_unsigned_accounts)
+                        };
+                        (i < signed, writable)
+                    })
+                    .unzip(),
+                None => (Vec::new(), Vec::new()),
+            };
+        if !signer_flags.is_empty() {
+            let loaded = loaded_addresses.writable.len() + loaded_addresses.readonly.len();
+            signer_flags.extend(std::iter::repeat_n(false, loaded));
+            writable_flags.extend(std::iter::repeat_n(true, loaded_addresses.writable.len()));
+            writable_flags.extend(std::iter::repeat_n(false, loaded_addresses.readonly.len()));
+        }
+        account_keys.extend(loaded_addresses.writable);
+        account_keys.extend(loaded_addresses.readonly);
+
+        let instructions: Vec<SolanaInstruction> = self
+            .transaction
+            .message
+            .instructions
+            .into_iter()
+            .map(SolanaInstruction::from)
+            .collect();
+
+        let inner_instructions: Vec<SolanaInnerInstruction> = meta
+            .as_ref()
+            .and_then(|m| m.inner_instructions.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|inner| SolanaInnerInstruction {
+                index: inner.index,
+                instructions: inner
+                    .instructions
+                    .into_iter()
+                    .map(SolanaInstruction::from)
+                    .collect(),
+            })
+            .collect();
+
+        let mut program_ids: Vec<String> = Vec::new();
+        for instruction in instructions
+            .iter()
+            .chain(inner_instructions.iter().flat_map(|i| i.instructions.iter()))
+        {
+            if let Some(program_id) = account_keys.get(instruction.program_id_index) {
+                if !program_ids.contains(program_id) {
+                    program_ids.push(program_id.clone());
+                }
+            }
+        }
+
+        let (compute_unit_limit, compute_unit_price) = compute_budget(&account_keys, &instructions);
+
+        SolanaTransaction {
+            signature: self.transaction.signatures.first().cloned().unwrap_or_default(),
+            slot,
+            block_time,
+            fee: meta.as_ref().map(|m| m.fee).unwrap_or(0),
+            success: meta.as_ref().map(|m| m.err.is_none()).unwrap_or(false),
+            error: meta
+                .as_ref()
+                .and_then(|m| m.err.clone())
+                .and_then(|err| serde_json::from_value(err).ok()),
+            account_keys,
+            program_ids,
+            log_messages: meta
+                .as_ref()
+                .and_then(|m| m.log_messages.clone())
+                .unwrap_or_default(),
+            pre_balances: meta.as_ref().map(|m| m.pre_balances.clone()).unwrap_or_default(),
+            post_balances: meta.as_ref().map(|m| m.post_balances.clone()).unwrap_or_default(),
+            pre_token_balances: token_balances(meta.as_ref().and_then(|m| m.pre_token_balances.as_ref())),
+            post_token_balances: token_balances(meta.as_ref().and_then(|m| m.post_token_balances.as_ref())),
+            instructions,
+            inner_instructions,
+            version: self.version,
+            recent_blockhash: self.transaction.message.recent_blockhash,
+            signer_flags,
+            writable_flags,
+            compute_units_consumed: meta.as_ref().and_then(|m| m.compute_units_consumed),
+            compute_unit_limit,
+            compute_unit_price,
+            raw_error: meta.as_ref().and_then(|m| m.err.clone()),
+            return_data: meta
+                .as_ref()
+                .and_then(|m| m.return_data.as_ref())
+                .and_then(|r| {
+                    use base64::Engine;
+                    Some(TransactionReturnData {
+                        program_id: r.program_id.clone(),
+                        data: base64::engine::general_purpose::STANDARD.decode(&r.data.0).ok()?,
+                    })
+                }),
+        }
+    }
+}
+
+pub const COMPUTE_BUDGET_PROGRAM_ID: &str = "ComputeBudget111111111111111111111111111111";
+
+/// Compute unit limit and price set by the transaction's top-level compute
+/// budget instructions. A transaction that sets either twice fails, so a
+/// successful one carries at most one instruction of each kind.
+fn compute_budget(account_keys: &[String], instructions: &[SolanaInstruction]) -> (Option<u32>, Option<u64>) {
+    let mut limit = None;
+    let mut price = None;
+    for instruction in instructions {
+        if account_keys.get(instruction.program_id_index).map(String::as_str)
+            != Some(COMPUTE_BUDGET_PROGRAM_ID)
+        {
+            continue;
+        }
+        let Ok(data) = bs58::decode(&instruction.data).into_vec() else {
+            continue;
+        };
+        match data.split_first() {
+            // SetComputeUnitLimit(u32)
+            Some((2, rest)) if rest.len() >= 4 => {
+                limit = Some(u32::from_le_bytes(rest[..4].try_into().expect("4 bytes")));
+            }
+            // SetComputeUnitPrice(u64)
+            Some((3, rest)) if rest.len() >= 8 => {
+                price = Some(u64::from_le_bytes(rest[..8].try_into().expect("8 bytes")));
+            }
+            _ => {}
+        }
+    }
+    (limit, price)
+}
+
 #[async_trait]
 pub trait Client: Send + Sync {
     async fn get_latest_slot(&self) -> Result<u64>;
@@ -41,29 +390,59 @@ pub trait Client: Send + Sync {
         from_slot: u64,
         to_slot: u64,
     ) -> Result<Vec<SolanaProgramLog>>;
+
+    /// Highest slot the node has at finalized commitment.
+    async fn get_finalized_slot(&self) -> Result<u64> {
+        anyhow::bail!("Finalized slots are not supported by this client")
+    }
+
+    /// Full log messages of a transaction from an archival source, used to
+    /// recover truncated logs. Returns `Ok(None)` when no archive is configured.
+    async fn get_archived_log_messages(&self, _signature: &str) -> Result<Option<Vec<String>>> {
+        Ok(None)
+    }
+
+    /// Current state of an account, or `Ok(None)` if it does not exist.
+    async fn get_account_info(&self, _pubkey: &str) -> Result<Option<SolanaAccount>> {
+        anyhow::bail!("getAccountInfo is not supported by this client")
+    }
+
+    /// Every account owned by `program_id` that matches all `filters`.
+    async fn get_program_accounts(
+        &self,
+        _program_id: &str,
+        _filters: &[AccountFilter],
+    ) -> Result<Vec<SolanaAccount>> {
+        anyhow::bail!("getProgramAccounts is not supported by this client")
+    }
+
+    /// Accounts in the order of `pubkeys`, `None` for those that do not
+    /// exist, read from a node that has reached at least `min_context_slot`.
+    async fn get_multiple_accounts(
+        &self,
+        _pubkeys: &[String],
+        _min_context_slot: Option<u64>,
+    ) -> Result<Vec<Option<SolanaAccount>>> {
+        anyhow::bail!("getMultipleAccounts is not supported by this client")
+    }
 }
 
 pub struct SolanaClient {
     http_client: reqwest::Client,
     base_url: String,
+    archival_url: Option<String>,
     block_commitment: BlockCommitment,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct SolanaClientConfig {
     pub base_url: String,
+    /// RPC endpoint queried for the full log messages of transactions whose
+    /// logs were truncated.
+    pub archival_url: Option<String>,
     pub block_commitment: BlockCommitment,
 }
 
-impl Default for SolanaClientConfig {
-    fn default() -> Self {
-        Self {
-            base_url: String::new(),
-            block_commitment: BlockCommitment::default(),
-        }
-    }
-}
-
 impl SolanaClient {
     pub fn new(config: SolanaClientConfig) -> Result<Self> {
         let http_client = reqwest::Client::builder()
@@ -76,6 +455,7 @@ impl SolanaClient {
         Ok(Self {
             http_client,
             base_url: config.base_url,
+            archival_url: config.archival_url,
             block_commitment: config.block_commitment,
         })
     }
@@ -85,10 +465,14 @@ impl SolanaClient {
     }
 
     async fn call(&self, request: RPCRequest) -> Result<RPCResponse> {
-        let backoffs = vec![1, 3, 5, 10, 20, 30, 60];
+        self.call_endpoint(&self.base_url, request).await
+    }
+
+    async fn call_endpoint(&self, url: &str, request: RPCRequest) -> Result<RPCResponse> {
+        let backoffs = [1, 3, 5, 10, 20, 30, 60];
 
         for (attempt, &backoff) in backoffs.iter().enumerate() {
-            let response = self.call_internal(&request).await;
+            let response = self.call_internal(url, &request).await;
 
             match response {
                 Ok(resp) => {
@@ -116,7 +500,7 @@ impl SolanaClient {
         anyhow::bail!("Exceeded retries for call")
     }
 
-    async fn call_internal(&self, request: &RPCRequest) -> Result<RPCResponse> {
+    async fn call_internal(&self, url: &str, request: &RPCRequest) -> Result<RPCResponse> {
         let request_body = serde_json::to_string(request)
             .context("Failed to serialize request")?;
 
@@ -124,7 +508,7 @@ impl SolanaClient {
 
         let response = self
             .http_client
-            .post(&self.base_url)
+            .post(url)
             .header("Content-Type", "application/json")
             .header("Accept", "application/json")
             .body(request_body)
@@ -172,6 +556,20 @@ impl Client for SolanaClient {
         Ok(slot)
     }
 
+    async fn get_finalized_slot(&self) -> Result<u64> {
+        let request = RPCRequest {
+            jsonrpc: "2.0".to_string(),
+            id: 1,
+            method: "getSlot".to_string(),
+            params: Some(serde_json::json!([{ "commitment": "finalized" }])),
+        };
+
+        let response = self.call(request).await?;
+
+        serde_json::from_value(response.result.context("No result in response")?)
+            .context("Failed to parse slot")
+    }
+
     async fn get_slot_by_number(&self, slot_number: u64) -> Result<SolanaSlot> {
         let request = RPCRequest {
             jsonrpc: "2.0".to_string(),
@@ -182,6 +580,7 @@ impl Client for SolanaClient {
                 {
                     "encoding": "json",
                     "transactionDetails": "full",
+                    "maxSupportedTransactionVersion": 0,
                     "rewards": false,
                     "commitment": self.block_commitment.as_str()
                 }
@@ -190,13 +589,12 @@ impl Client for SolanaClient {
 
         let response = self.call(request).await?;
 
-        let mut slot: SolanaSlot = serde_json::from_value(
+        let block: RpcBlock = serde_json::from_value(
             response.result.context("No result in response")?,
         )
         .context("Failed to parse slot")?;
 
-        slot.slot = slot_number;
-        Ok(slot)
+        Ok(block.into_slot(slot_number))
     }
 
     async fn get_program_logs(
@@ -248,24 +646,436 @@ impl Client for SolanaClient {
         )
         .context("Failed to parse program logs")?;
 
-        let mut logs = Vec::new();
-        for entry in result.value {
-            for (i, log_msg) in entry.logs.iter().enumerate() {
-                if log_msg.starts_with(&format!("Program {}", program_id)) {
-                    logs.push(SolanaProgramLog {
-                        program_id: program_id.to_string(),
-                        log_index: i as u64,
-                        signature: entry.signature.clone(),
-                        slot: result.context.slot,
-                        block_time: None,
-                        log_message: log_msg.clone(),
-                        instruction_index: 0,
-                    });
+        let logs = result
+            .value
+            .iter()
+            .flat_map(|entry| {
+                InvocationTree::parse(&entry.logs).program_logs(
+                    &entry.logs,
+                    program_id,
+                    &entry.signature,
+                    result.context.slot,
+                    None,
+                )
+            })
+            .collect();
+
+        Ok(logs)
+    }
+
+    async fn get_archived_log_messages(&self, signature: &str) -> Result<Option<Vec<String>>> {
+        let Some(archival_url) = &self.archival_url else {
+            return Ok(None);
+        };
+
+        let request = RPCRequest {
+            jsonrpc: "2.0".to_string(),
+            id: 1,
+            method: "getTransaction".to_string(),
+            params: Some(serde_json::json!([
+                signature,
+                {
+                    "encoding": "json",
+                    "maxSupportedTransactionVersion": 0,
+                    "commitment": self.block_commitment.as_str()
+                }
+            ])),
+        };
+
+        let response = self.call_endpoint(archival_url, request).await?;
+
+        #[derive(Deserialize)]
+        struct TransactionResult {
+            meta: Option<RpcTransactionMeta>,
+        }
+
+        let result: Option<TransactionResult> = serde_json::from_value(
+            response.result.context("No result in response")?,
+        )
+        .context("Failed to parse transaction")?;
+
+        Ok(result
+            .and_then(|r| r.meta)
+            .and_then(|m| m.log_messages))
+    }
+
+    async fn get_account_info(&self, pubkey: &str) -> Result<Option<SolanaAccount>> {
+        let request = RPCRequest {
+            jsonrpc: "2.0".to_string(),
+            id: 1,
+            method: "getAccountInfo".to_string(),
+            params: Some(serde_json::json!([
+                pubkey,
+                {
+                    "encoding": "base64",
+                    "commitment": self.block_commitment.as_str()
                 }
+            ])),
+        };
+
+        let response = self.call(request).await?;
+
+        #[derive(Deserialize)]
+        struct AccountInfoResult {
+            context: ContextInfo,
+            value: Option<RpcAccount>,
+        }
+
+        #[derive(Deserialize)]
+        struct ContextInfo {
+            slot: u64,
+        }
+
+        let result: AccountInfoResult = serde_json::from_value(
+            response.result.context("No result in response")?,
+        )
+        .context("Failed to parse account info")?;
+
+        result
+            .value
+            .map(|account| account.into_account(pubkey, result.context.slot))
+            .transpose()
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &str,
+        filters: &[AccountFilter],
+    ) -> Result<Vec<SolanaAccount>> {
+        let request = RPCRequest {
+            jsonrpc: "2.0".to_string(),
+            id: 1,
+            method: "getProgramAccounts".to_string(),
+            params: Some(serde_json::json!([
+                program_id,
+                {
+                    "encoding": "base64",
+                    "commitment": self.block_commitment.as_str(),
+                    "withContext": true,
+                    "filters": filters.iter().map(AccountFilter::to_rpc).collect::<Vec<_>>()
+                }
+            ])),
+        };
+
+        let response = self.call(request).await?;
+
+        #[derive(Deserialize)]
+        struct ProgramAccountsResult {
+            context: ContextInfo,
+            value: Vec<KeyedAccount>,
+        }
+
+        #[derive(Deserialize)]
+        struct ContextInfo {
+            slot: u64,
+        }
+
+        #[derive(Deserialize)]
+        struct KeyedAccount {
+            pubkey: String,
+            account: RpcAccount,
+        }
+
+        let result: ProgramAccountsResult = serde_json::from_value(
+            response.result.context("No result in response")?,
+        )
+        .context("Failed to parse program accounts")?;
+
+        result
+            .value
+            .into_iter()
+            .map(|keyed| keyed.account.into_account(&keyed.pubkey, result.context.slot))
+            .collect()
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[String],
+        min_context_slot: Option<u64>,
+    ) -> Result<Vec<Option<SolanaAccount>>> {
+        #[derive(Deserialize)]
+        struct MultipleAccountsResult {
+            context: ContextInfo,
+            value: Vec<Option<RpcAccount>>,
+        }
+
+        #[derive(Deserialize)]
+        struct ContextInfo {
+            slot: u64,
+        }
+
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        // getMultipleAccounts accepts at most 100 keys per request.
+        for chunk in pubkeys.chunks(100) {
+            let mut config = serde_json::json!({
+                "encoding": "base64",
+                "commitment": self.block_commitment.as_str()
+            });
+            if let Some(min_context_slot) = min_context_slot {
+                config["minContextSlot"] = min_context_slot.into();
+            }
+
+            let request = RPCRequest {
+                jsonrpc: "2.0".to_string(),
+                id: 1,
+                method: "getMultipleAccounts".to_string(),
+                params: Some(serde_json::json!([chunk, config])),
+            };
+
+            let response = self.call(request).await?;
+
+            let result: MultipleAccountsResult = serde_json::from_value(
+                response.result.context("No result in response")?,
+            )
+            .context("Failed to parse accounts")?;
+            anyhow::ensure!(
+                result.value.len() == chunk.len(),
+                "Expected {} accounts, got {}",
+                chunk.len(),
+                result.value.len()
+            );
+
+            for (pubkey, account) in chunk.iter().zip(result.value) {
+                accounts.push(
+                    account
+                        .map(|account| account.into_account(pubkey, result.context.slot))
+                        .transpose()?,
+                );
             }
         }
 
-        Ok(logs)
+        Ok(accounts)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_block_payload_converts_to_slot() {
+        let block: RpcBlock = serde_json::from_value(serde_json::json!({
+            "blockhash": "Hash1",
+            "previousBlockhash": "Hash0",
+            "parentSlot": 99,
+            "blockHeight": 90,
+            "blockTime": 1_700_000_000,
+            "transactions": [{
+                "transaction": {
+                    "signatures": ["Sig1"],
+                    "message": {
+                        "accountKeys": ["Payer", "Prog"],
+                        "recentBlockhash": "Hash0",
+                        "instructions": [
+                            {"programIdIndex": 1, "accounts": [0, 2], "data": "3Bxs", "stackHeight": null}
+                        ]
+                    }
+                },
+                "meta": {
+                    "err": null,
+                    "fee": 5000,
+                    "preBalances": [10, 1],
+                    "postBalances": [5, 1],
+                    "logMessages": ["Program Prog invoke [1]", "Program Prog success"],
+                    "innerInstructions": [
+                        {"index": 0, "instructions": [
+                            {"programIdIndex": 3, "accounts": [], "data": "", "stackHeight": 2}
+                        ]}
+                    ],
+                    "loadedAddresses": {"writable": ["Vault"], "readonly": ["Token"]},
+                    "preTokenBalances": [],
+                    "postTokenBalances": [{
+                        "accountIndex": 2,
+                        "mint": "Mint",
+                        "owner": "Payer",
+                        "programId": "Token",
+                        "uiTokenAmount": {"amount": "18446744073709551615", "decimals": 6, "uiAmount": null, "uiAmountString": "18446744073709.551615"}
+                    }]
+                },
+                "version": 0
+            }]
+        }))
+        .unwrap();
+
+        let slot = block.into_slot(100);
+        assert_eq!(slot.slot, 100);
+        assert_eq!(slot.parent, Some(99));
+        let tx = &slot.transactions[0];
+        assert_eq!(tx.signature, "Sig1");
+        assert!(tx.success);
+        assert_eq!(tx.account_keys, vec!["Payer", "Prog", "Vault", "Token"]);
+        assert_eq!(tx.program_ids, vec!["Prog", "Token"]);
+        assert_eq!(tx.instructions.len(), 1);
+        assert_eq!(tx.inner_instructions[0].instructions[0].stack_height, Some(2));
+        assert_eq!(tx.log_messages.len(), 2);
+        assert!(tx.pre_token_balances.is_empty());
+        let balance = &tx.post_token_balances[0];
+        assert_eq!((balance.account_index, balance.mint.as_str()), (2, "Mint"));
+        assert_eq!(balance.owner.as_deref(), Some("Payer"));
+        assert_eq!((balance.amount, balance.decimals), (u64::MAX, 6));
+    }
+
+    #[test]
+    fn transaction_metadata_is_captured() {
+        let mut set_limit = vec![2];
+        set_limit.extend_from_slice(&300_000u32.to_le_bytes());
+        let mut set_price = vec![3];
+        set_price.extend_from_slice(&25_000u64.to_le_bytes());
+
+        let tx: RpcTransactionWithMeta = serde_json::from_value(serde_json::json!({
+            "transaction": {
+                "signatures": ["Sig1"],
+                "message": {
+                    "accountKeys": ["Payer", "Cosigner", "Vault", COMPUTE_BUDGET_PROGRAM_ID, "Prog"],
+                    "header": {
+                        "numRequiredSignatures": 2,
+                        "numReadonlySignedAccounts": 1,
+                        "numReadonlyUnsignedAccounts": 2
+                    },
+                    "recentBlockhash": "Hash0",
+                    "instructions": [
+                        {"programIdIndex": 3, "accounts": [], "data": bs58::encode(set_limit).into_string()},
+                        {"programIdIndex": 3, "accounts": [], "data": bs58::encode(set_price).into_string()},
+                        {"programIdIndex": 4, "accounts": [0, 2, 5], "data": ""}
+                    ]
+                }
+            },
+            "meta": {
+                "err": {"InstructionError": [2, {"Custom": 1}]},
+                "fee": 12500,
+                "preBalances": [],
+                "postBalances": [],
+                "computeUnitsConsumed": 4321,
+                "returnData": {"programId": "Prog", "data": ["AQI=", "base64"]},
+                "loadedAddresses": {"writable": ["Pool"], "readonly": ["Oracle"]}
+            },
+            "version": 0
+        }))
+        .unwrap();
+
+        let tx = tx.into_transaction(1, None);
+        assert_eq!(tx.fee_payer(), Some("Payer"));
+        assert_eq!(tx.version, Some(solana_sdk::transaction::TransactionVersion::Number(0)));
+        assert_eq!(tx.recent_blockhash, "Hash0");
+        assert_eq!(tx.signer_flags, vec![true, true, false, false, false, false, false]);
+        assert_eq!(tx.writable_flags, vec![true, false, true, false, false, true, false]);
+        assert!(tx.is_writable(5) && !tx.is_signer(5) && !tx.is_writable(99));
+        assert_eq!(tx.compute_units_consumed, Some(4321));
+        assert_eq!(tx.compute_unit_limit, Some(300_000));
+        assert_eq!(tx.compute_unit_price, Some(25_000));
+        assert_eq!(
+            tx.raw_error,
+            Some(serde_json::json!({"InstructionError": [2, {"Custom": 1}]}))
+        );
+        assert_eq!(
+            tx.return_data,
+            Some(TransactionReturnData {
+                program_id: "Prog".to_string(),
+                data: vec![1, 2],
+            })
+        );
+
+        let legacy: RpcTransactionWithMeta = serde_json::from_value(serde_json::json!({
+            "transaction": {"signatures": ["Sig2"], "message": {"accountKeys": [], "instructions": []}},
+            "meta": null,
+            "version": "legacy"
+        }))
+        .unwrap();
+        assert_eq!(
+            legacy.into_transaction(1, None).version,
+            Some(solana_sdk::transaction::TransactionVersion::LEGACY)
+        );
+    }
+
+    #[test]
+    fn instructions_resolve_to_pubkeys_and_flags() {
+        use solana_sdk::pubkey::Pubkey;
+
+        let [payer, program, vault, token] = [(); 4].map(|_| Pubkey::new_unique());
+        let tx: RpcTransactionWithMeta = serde_json::from_value(serde_json::json!({
+            "transaction": {
+                "signatures": ["Sig1"],
+                "message": {
+                    "accountKeys": [payer.to_string(), program.to_string()],
+                    "header": {
+                        "numRequiredSignatures": 1,
+                        "numReadonlySignedAccounts": 0,
+                        "numReadonlyUnsignedAccounts": 1
+                    },
+                    "instructions": [{"programIdIndex": 1, "accounts": [0, 2], "data": "3Bxs"}]
+                }
+            },
+            "meta": {
+                "err": null,
+                "fee": 5000,
+                "preBalances": [],
+                "postBalances": [],
+                "innerInstructions": [
+                    {"index": 0, "instructions": [
+                        {"programIdIndex": 3, "accounts": [2], "data": "", "stackHeight": 2},
+                        {"programIdIndex": 1, "accounts": [2], "data": "", "stackHeight": 3}
+                    ]}
+                ],
+                "loadedAddresses": {"writable": [vault.to_string()], "readonly": [token.to_string()]}
+            }
+        }))
+        .unwrap();
+        let tx = tx.into_transaction(1, None);
+
+        let instructions = crate::transaction_log_parser::InstructionWithContext::from_transaction(&tx);
+        let summary: Vec<_> = instructions
+            .iter()
+            .map(|i| (i.program_id.clone(), i.stack_height))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (program.to_string(), 1),
+                (token.to_string(), 2),
+                (program.to_string(), 3),
+            ]
+        );
+        assert_eq!(instructions[0].data, bs58::decode("3Bxs").into_vec().unwrap());
+        let instruction = solana_sdk::instruction::Instruction::try_from(&instructions[0]).unwrap();
+        assert_eq!(instruction.program_id, program);
+        assert_eq!(
+            instruction.accounts,
+            vec![
+                solana_sdk::instruction::AccountMeta::new(payer, true),
+                solana_sdk::instruction::AccountMeta::new(vault, false),
+            ]
+        );
+
+        let mut bad = tx.clone();
+        bad.instructions[0].accounts.push(9);
+        let bad = crate::transaction_log_parser::InstructionWithContext::from_transaction(&bad);
+        assert!(solana_sdk::instruction::Instruction::try_from(&bad[0]).is_err());
+    }
+
+    #[test]
+    fn failed_transaction_error_is_decoded() {
+        let tx: RpcTransactionWithMeta = serde_json::from_value(serde_json::json!({
+            "transaction": {
+                "signatures": ["Sig1"],
+                "message": {"accountKeys": ["Payer", "Prog"], "instructions": []}
+            },
+            "meta": {
+                "err": {"InstructionError": [0, {"Custom": 6001}]},
+                "fee": 5000,
+                "preBalances": [],
+                "postBalances": []
+            }
+        }))
+        .unwrap();
+
+        let tx = tx.into_transaction(1, None);
+        assert!(!tx.success);
+        assert_eq!(
+            tx.error,
+            Some(solana_sdk::transaction::TransactionError::InstructionError(
+                0,
+                solana_sdk::instruction::InstructionError::Custom(6001)
+            ))
+        );
+    }
+}
@@ -1,6 +1,7 @@
+pub mod balances;
 pub mod client;
 pub mod types;
 
+pub use balances::*;
 pub use client::*;
 pub use types::*;
-
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::{TransactionError, TransactionVersion};
 use std::str::FromStr;
 
 use crate::config::ChainId;
@@ -11,22 +12,104 @@ pub struct SolanaSlot {
     pub blockhash: String,
     pub block_time: Option<i64>,
     pub transactions: Vec<SolanaTransaction>,
+    #[serde(default)]
     pub chain_id: ChainId,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SolanaTransaction {
     pub signature: String,
     pub slot: u64,
     pub block_time: Option<i64>,
     pub fee: u64,
     pub success: bool,
+    /// Why the transaction failed, when `success` is false and the error
+    /// could be decoded.
+    #[serde(default)]
+    pub error: Option<TransactionError>,
     pub account_keys: Vec<String>,
     pub program_ids: Vec<String>,
     pub log_messages: Vec<String>,
     pub pre_balances: Vec<u64>,
     pub post_balances: Vec<u64>,
+    #[serde(default)]
+    pub pre_token_balances: Vec<SolanaTokenBalance>,
+    #[serde(default)]
+    pub post_token_balances: Vec<SolanaTokenBalance>,
+    #[serde(default)]
+    pub instructions: Vec<SolanaInstruction>,
     pub inner_instructions: Vec<SolanaInnerInstruction>,
+    /// `None` when the node did not report a version, which it only does
+    /// when asked for `maxSupportedTransactionVersion`.
+    #[serde(default)]
+    pub version: Option<TransactionVersion>,
+    #[serde(default)]
+    pub recent_blockhash: String,
+    /// Signer flag of each of `account_keys`.
+    #[serde(default)]
+    pub signer_flags: Vec<bool>,
+    /// Writable flag of each of `account_keys`, as requested by the message.
+    /// The runtime still treats program IDs and reserved accounts as
+    /// read-only.
+    #[serde(default)]
+    pub writable_flags: Vec<bool>,
+    #[serde(default)]
+    pub compute_units_consumed: Option<u64>,
+    /// Limit set by a `SetComputeUnitLimit` compute budget instruction.
+    #[serde(default)]
+    pub compute_unit_limit: Option<u32>,
+    /// Priority fee in micro-lamports per compute unit, set by a
+    /// `SetComputeUnitPrice` compute budget instruction.
+    #[serde(default)]
+    pub compute_unit_price: Option<u64>,
+    /// The RPC node's `err`, kept as-is even when `error` cannot decode it.
+    #[serde(default)]
+    pub raw_error: Option<serde_json::Value>,
+    /// Data set by the last program that called `set_return_data`.
+    #[serde(default)]
+    pub return_data: Option<TransactionReturnData>,
+}
+
+impl SolanaTransaction {
+    /// The first signer, which pays the fee.
+    pub fn fee_payer(&self) -> Option<&str> {
+        self.account_keys.first().map(String::as_str)
+    }
+
+    /// Whether account `index` signed. The fee payer always does, even when
+    /// the node sent no message header.
+    pub fn is_signer(&self, index: usize) -> bool {
+        index == 0 || self.signer_flags.get(index).copied().unwrap_or(false)
+    }
+
+    /// Whether account `index` is writable. The fee payer always is.
+    pub fn is_writable(&self, index: usize) -> bool {
+        index == 0 || self.writable_flags.get(index).copied().unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionReturnData {
+    pub program_id: String,
+    pub data: Vec<u8>,
+}
+
+/// SPL token balance of one of a transaction's accounts, from the
+/// transaction's `preTokenBalances` or `postTokenBalances`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SolanaTokenBalance {
+    /// Index into the transaction's `account_keys`.
+    pub account_index: usize,
+    pub mint: String,
+    /// Owner of the token account; missing on very old transactions.
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Token program that owns the account.
+    #[serde(default)]
+    pub program_id: Option<String>,
+    /// Raw amount, in the mint's smallest unit.
+    pub amount: u64,
+    pub decimals: u8,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,11 +118,13 @@ pub struct SolanaInnerInstruction {
     pub instructions: Vec<SolanaInstruction>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SolanaInstruction {
     pub program_id_index: usize,
     pub accounts: Vec<usize>,
     pub data: String,
+    #[serde(default)]
+    pub stack_height: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,6 +136,19 @@ pub struct SolanaProgramLog {
     pub block_time: Option<i64>,
     pub log_message: String,
     pub instruction_index: usize,
+    /// Position among the inner instructions of `instruction_index` when the
+    /// log was written by a CPI; `None` for the top-level instruction.
+    #[serde(default)]
+    pub inner_instruction_index: Option<usize>,
+    /// Invocation depth of the program that wrote the log; 1 for top-level.
+    #[serde(default)]
+    pub depth: usize,
+    /// Program that invoked `program_id` through CPI, if any.
+    #[serde(default)]
+    pub parent_program_id: Option<String>,
+    /// The transaction's log messages ended with `Log truncated`.
+    #[serde(default)]
+    pub logs_truncated: bool,
 }
 
 impl SolanaProgramLog {
@@ -60,8 +158,51 @@ impl SolanaProgramLog {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Account state as served by the RPC node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SolanaAccount {
+    pub pubkey: String,
+    pub owner: String,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub executable: bool,
+    /// Slot the node had reached when it served the account.
+    pub slot: u64,
+}
+
+/// `getProgramAccounts` filter, also evaluated locally on account updates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountFilter {
+    DataSize(u64),
+    Memcmp { offset: usize, bytes: Vec<u8> },
+}
+
+impl AccountFilter {
+    pub fn matches(&self, data: &[u8]) -> bool {
+        match self {
+            AccountFilter::DataSize(size) => data.len() as u64 == *size,
+            AccountFilter::Memcmp { offset, bytes } => data
+                .get(*offset..)
+                .is_some_and(|data| data.starts_with(bytes)),
+        }
+    }
+
+    pub fn to_rpc(&self) -> serde_json::Value {
+        match self {
+            AccountFilter::DataSize(size) => serde_json::json!({ "dataSize": size }),
+            AccountFilter::Memcmp { offset, bytes } => serde_json::json!({
+                "memcmp": {
+                    "offset": offset,
+                    "bytes": bs58::encode(bytes).into_string()
+                }
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum BlockCommitment {
+    #[default]
     Finalized,
     Confirmed,
     Processed,
@@ -77,9 +218,3 @@ impl BlockCommitment {
     }
 }
 
-impl Default for BlockCommitment {
-    fn default() -> Self {
-        BlockCommitment::Finalized
-    }
-}
-
@@ -0,0 +1,685 @@
+use crate::transaction_log_parser::idl::*;
+use crate::transaction_log_parser::Argument;
+use anyhow::Context;
+use serde_json::{Map, Value};
+use solana_sdk::pubkey::Pubkey;
+
+pub struct BorshReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> BorshReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, offset: 0 }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.offset
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        anyhow::ensure!(
+            self.remaining() >= len,
+            "Unexpected end of data: need {} bytes at offset {}, have {}",
+            len,
+            self.offset,
+            self.remaining()
+        );
+        let bytes = &self.data[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+        Ok(array)
+    }
+
+    pub fn read_u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_pubkey(&mut self) -> anyhow::Result<Pubkey> {
+        Ok(Pubkey::new_from_array(self.read_array()?))
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Skips padding so the next read starts `align`-aligned relative to `base`.
+    pub fn align(&mut self, base: usize, align: usize) -> anyhow::Result<()> {
+        let misalignment = (self.offset - base) % align;
+        if misalignment != 0 {
+            self.read_bytes(align - misalignment)?;
+        }
+        Ok(())
+    }
+
+    fn read_len(&mut self) -> anyhow::Result<usize> {
+        let len = self.read_u32()? as usize;
+        anyhow::ensure!(
+            len <= self.remaining(),
+            "Length prefix {} exceeds remaining {} bytes",
+            len,
+            self.remaining()
+        );
+        Ok(len)
+    }
+}
+
+/// Layout values are read with.
+#[derive(Debug, Clone, Copy)]
+enum Layout {
+    Borsh,
+    /// `#[repr(C)]` memory layout of zero-copy (bytemuck) types, with field
+    /// padding measured from `base`, the offset of the outermost value.
+    Pod { base: usize },
+}
+
+/// Decodes one value of type `ty` into JSON.
+///
+/// Integers of 64 bits and wider are encoded as decimal strings, since JSON
+/// consumers commonly lose precision above 2^53. Zero-copy types are read
+/// with their C layout, with `u128` aligned to 8 bytes as on SBF.
+pub fn decode_type(idl: &Idl, ty: &IdlType, reader: &mut BorshReader) -> anyhow::Result<Value> {
+    decode(idl, ty, reader, Layout::Borsh)
+}
+
+/// Decodes a value of the type definition `name`.
+pub fn decode_defined(idl: &Idl, name: &str, reader: &mut BorshReader) -> anyhow::Result<Value> {
+    decode_defined_type(idl, name, &[], reader, Layout::Borsh)
+}
+
+fn decode(idl: &Idl, ty: &IdlType, reader: &mut BorshReader, layout: Layout) -> anyhow::Result<Value> {
+    if let Layout::Pod { .. } = layout {
+        anyhow::ensure!(
+            !matches!(
+                ty,
+                IdlType::Bytes
+                    | IdlType::String
+                    | IdlType::Option(_)
+                    | IdlType::COption(_)
+                    | IdlType::Vec(_)
+                    | IdlType::Map(..)
+                    | IdlType::Set(_)
+            ),
+            "Type {} is not valid in a zero-copy layout",
+            ty
+        );
+    }
+
+    Ok(match ty {
+        IdlType::Bool => Value::Bool(reader.read_u8()? != 0),
+        IdlType::U8 => Value::from(reader.read_u8()?),
+        IdlType::I8 => Value::from(i8::from_le_bytes(reader.read_array()?)),
+        IdlType::U16 => Value::from(u16::from_le_bytes(reader.read_array()?)),
+        IdlType::I16 => Value::from(i16::from_le_bytes(reader.read_array()?)),
+        IdlType::U32 => Value::from(reader.read_u32()?),
+        IdlType::I32 => Value::from(i32::from_le_bytes(reader.read_array()?)),
+        IdlType::F32 => Value::from(f32::from_le_bytes(reader.read_array()?)),
+        IdlType::U64 => Value::String(reader.read_u64()?.to_string()),
+        IdlType::I64 => Value::String(i64::from_le_bytes(reader.read_array()?).to_string()),
+        IdlType::F64 => Value::from(f64::from_le_bytes(reader.read_array()?)),
+        IdlType::U128 => Value::String(u128::from_le_bytes(reader.read_array()?).to_string()),
+        IdlType::I128 => Value::String(i128::from_le_bytes(reader.read_array()?).to_string()),
+        IdlType::U256 => Value::String(le_bytes_to_decimal(reader.read_array()?, false)),
+        IdlType::I256 => Value::String(le_bytes_to_decimal(reader.read_array()?, true)),
+        IdlType::Bytes => {
+            let len = reader.read_len()?;
+            Value::from(reader.read_bytes(len)?.to_vec())
+        }
+        IdlType::String => {
+            let len = reader.read_len()?;
+            Value::String(
+                String::from_utf8(reader.read_bytes(len)?.to_vec())
+                    .context("Invalid UTF-8 string")?,
+            )
+        }
+        IdlType::Pubkey => Value::String(reader.read_pubkey()?.to_string()),
+        IdlType::Option(inner) => match reader.read_u8()? {
+            0 => Value::Null,
+            1 => decode(idl, inner, reader, layout)?,
+            tag => anyhow::bail!("Invalid option tag: {}", tag),
+        },
+        // The value's bytes are present, zeroed, even when the option is empty.
+        IdlType::COption(inner) => match reader.read_u32()? {
+            0 => {
+                let size = fixed_size(idl, inner)?
+                    .with_context(|| format!("COption of variable-size type {}", inner))?;
+                reader.read_bytes(size)?;
+                Value::Null
+            }
+            1 => decode(idl, inner, reader, layout)?,
+            tag => anyhow::bail!("Invalid coption tag: {}", tag),
+        },
+        IdlType::Vec(inner) => {
+            let len = reader.read_u32()? as usize;
+            let mut items = Vec::with_capacity(len.min(reader.remaining()));
+            for _ in 0..len {
+                items.push(decode(idl, inner, reader, layout)?);
+            }
+            Value::Array(items)
+        }
+        IdlType::Array(inner, len) => {
+            let len = array_len(len)?;
+            let mut items = Vec::with_capacity(len.min(reader.remaining()));
+            for _ in 0..len {
+                items.push(decode(idl, inner, reader, layout)?);
+            }
+            Value::Array(items)
+        }
+        IdlType::Tuple(types) => Value::Array(
+            types
+                .iter()
+                .map(|ty| decode(idl, ty, reader, layout))
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        IdlType::Set(inner) => {
+            let len = reader.read_u32()? as usize;
+            let mut items = Vec::with_capacity(len.min(reader.remaining()));
+            for _ in 0..len {
+                items.push(decode(idl, inner, reader, layout)?);
+            }
+            Value::Array(items)
+        }
+        IdlType::Map(key, value) => {
+            let len = reader.read_u32()? as usize;
+            let mut object = Map::new();
+            for _ in 0..len {
+                let key = match decode(idl, key, reader, layout)? {
+                    Value::String(key) => key,
+                    key => key.to_string(),
+                };
+                object.insert(key, decode(idl, value, reader, layout)?);
+            }
+            Value::Object(object)
+        }
+        IdlType::Defined { name, generics } => decode_defined_type(idl, name, generics, reader, layout)?,
+        IdlType::Generic(name) => anyhow::bail!("Unbound IDL generic {}", name),
+    })
+}
+
+fn decode_defined_type(
+    idl: &Idl,
+    name: &str,
+    generics: &[IdlGenericArg],
+    reader: &mut BorshReader,
+    layout: Layout,
+) -> anyhow::Result<Value> {
+    let type_def = idl
+        .type_def(name)
+        .with_context(|| format!("Type {} not found in IDL", name))?;
+    let ty = type_def.instantiate(generics)?;
+
+    let layout = match layout {
+        Layout::Borsh if type_def.serialization.is_zero_copy() => Layout::Pod {
+            base: reader.offset(),
+        },
+        layout => layout,
+    };
+    match layout {
+        Layout::Borsh => decode_type_def(idl, &ty, reader, layout),
+        Layout::Pod { base } => {
+            let packed = type_def.is_packed();
+            let value = match &ty {
+                IdlTypeDefTy::Struct { fields } => {
+                    decode_pod_fields(idl, fields.as_ref(), packed, reader, base)?
+                }
+                IdlTypeDefTy::Type { alias } => decode(idl, alias, reader, layout)?,
+                IdlTypeDefTy::Enum { .. } => {
+                    anyhow::bail!("Enum {} is not valid in a zero-copy layout", name)
+                }
+            };
+            if !packed {
+                reader.align(base, type_def_align(idl, type_def, &ty)?)?;
+            }
+            Ok(value)
+        }
+    }
+}
+
+fn decode_type_def(
+    idl: &Idl,
+    ty: &IdlTypeDefTy,
+    reader: &mut BorshReader,
+    layout: Layout,
+) -> anyhow::Result<Value> {
+    match ty {
+        IdlTypeDefTy::Struct { fields } => decode_defined_fields(idl, fields.as_ref(), reader, layout),
+        IdlTypeDefTy::Enum { variants } => {
+            let tag = reader.read_u8()? as usize;
+            let variant = variants
+                .get(tag)
+                .with_context(|| format!("Invalid enum variant index: {}", tag))?;
+            match &variant.fields {
+                None => Ok(Value::String(variant.name.clone())),
+                Some(fields) => {
+                    let mut object = Map::new();
+                    object.insert(
+                        variant.name.clone(),
+                        decode_defined_fields(idl, Some(fields), reader, layout)?,
+                    );
+                    Ok(Value::Object(object))
+                }
+            }
+        }
+        IdlTypeDefTy::Type { alias } => decode(idl, alias, reader, layout),
+    }
+}
+
+fn decode_defined_fields(
+    idl: &Idl,
+    fields: Option<&IdlDefinedFields>,
+    reader: &mut BorshReader,
+    layout: Layout,
+) -> anyhow::Result<Value> {
+    match fields {
+        None => Ok(Value::Object(Map::new())),
+        Some(IdlDefinedFields::Named(fields)) => {
+            let mut object = Map::new();
+            for field in fields {
+                object.insert(field.name.clone(), decode(idl, &field.ty, reader, layout)?);
+            }
+            Ok(Value::Object(object))
+        }
+        Some(IdlDefinedFields::Tuple(types)) => {
+            let mut items = Vec::with_capacity(types.len());
+            for ty in types {
+                items.push(decode(idl, ty, reader, layout)?);
+            }
+            Ok(Value::Array(items))
+        }
+    }
+}
+
+/// Struct fields of a zero-copy type, each aligned unless the type is packed.
+fn decode_pod_fields(
+    idl: &Idl,
+    fields: Option<&IdlDefinedFields>,
+    packed: bool,
+    reader: &mut BorshReader,
+    base: usize,
+) -> anyhow::Result<Value> {
+    let layout = Layout::Pod { base };
+    let mut read = |ty: &IdlType| -> anyhow::Result<Value> {
+        if !packed {
+            reader.align(base, pod_align(idl, ty)?)?;
+        }
+        decode(idl, ty, reader, layout)
+    };
+
+    match fields {
+        None => Ok(Value::Object(Map::new())),
+        Some(IdlDefinedFields::Named(fields)) => {
+            let mut object = Map::new();
+            for field in fields {
+                object.insert(field.name.clone(), read(&field.ty)?);
+            }
+            Ok(Value::Object(object))
+        }
+        Some(IdlDefinedFields::Tuple(types)) => {
+            Ok(Value::Array(types.iter().map(read).collect::<anyhow::Result<_>>()?))
+        }
+    }
+}
+
+/// Alignment of `ty` in a zero-copy layout.
+fn pod_align(idl: &Idl, ty: &IdlType) -> anyhow::Result<usize> {
+    Ok(match ty {
+        IdlType::Bool | IdlType::U8 | IdlType::I8 | IdlType::Pubkey => 1,
+        IdlType::U16 | IdlType::I16 => 2,
+        IdlType::U32 | IdlType::I32 | IdlType::F32 => 4,
+        IdlType::U64
+        | IdlType::I64
+        | IdlType::F64
+        | IdlType::U128
+        | IdlType::I128
+        | IdlType::U256
+        | IdlType::I256 => 8,
+        IdlType::Array(inner, _) => pod_align(idl, inner)?,
+        IdlType::Defined { name, generics } => {
+            let type_def = idl
+                .type_def(name)
+                .with_context(|| format!("Type {} not found in IDL", name))?;
+            type_def_align(idl, type_def, &type_def.instantiate(generics)?)?
+        }
+        other => anyhow::bail!("Type {} is not valid in a zero-copy layout", other),
+    })
+}
+
+fn type_def_align(idl: &Idl, type_def: &IdlTypeDef, ty: &IdlTypeDefTy) -> anyhow::Result<usize> {
+    let natural = if type_def.is_packed() {
+        1
+    } else {
+        match ty {
+            IdlTypeDefTy::Struct { fields: None } => 1,
+            IdlTypeDefTy::Struct {
+                fields: Some(IdlDefinedFields::Named(fields)),
+            } => fields
+                .iter()
+                .map(|f| pod_align(idl, &f.ty))
+                .try_fold(1, |max, align| align.map(|a| max.max(a)))?,
+            IdlTypeDefTy::Struct {
+                fields: Some(IdlDefinedFields::Tuple(types)),
+            } => types
+                .iter()
+                .map(|ty| pod_align(idl, ty))
+                .try_fold(1, |max, align| align.map(|a| max.max(a)))?,
+            IdlTypeDefTy::Type { alias } => pod_align(idl, alias)?,
+            IdlTypeDefTy::Enum { .. } => {
+                anyhow::bail!("Enum {} is not valid in a zero-copy layout", type_def.name)
+            }
+        }
+    };
+    let declared = type_def.repr.as_ref().and_then(|r| r.align).unwrap_or(1);
+    Ok(natural.max(declared))
+}
+
+/// Decodes program-owned account data with the IDL account definition it
+/// matches, returning the account type's name and value. Accounts without a
+/// discriminator or declared size, as in Shank IDLs, match when their layout
+/// has a fixed size equal to the data length; data matching several such
+/// accounts is rejected as ambiguous.
+pub fn decode_account(idl: &Idl, data: &[u8]) -> anyhow::Result<Option<(String, Value)>> {
+    let account = match idl.account_by_discriminator(data) {
+        Some(account) => account,
+        None => {
+            let mut matching = Vec::new();
+            for account in idl
+                .accounts
+                .iter()
+                .filter(|a| a.discriminator.is_none() && a.size.is_none())
+            {
+                let ty = IdlType::Defined {
+                    name: account.name.clone(),
+                    generics: Vec::new(),
+                };
+                if fixed_size(idl, &ty)? == Some(data.len()) {
+                    matching.push(account);
+                }
+            }
+            match matching.as_slice() {
+                [] => return Ok(None),
+                [account] => *account,
+                accounts => anyhow::bail!(
+                    "Account data of {} bytes matches several accounts: {}",
+                    data.len(),
+                    accounts.iter().map(|a| a.name.as_str()).collect::<Vec<_>>().join(", ")
+                ),
+            }
+        }
+    };
+
+    let skip = account.discriminator.as_ref().map_or(0, Vec::len);
+    let value = decode_defined(idl, &account.name, &mut BorshReader::new(&data[skip..]))
+        .with_context(|| format!("Failed to decode account {}", account.name))?;
+    Ok(Some((account.name.clone(), value)))
+}
+
+/// Borsh-encoded size of `ty`, or `None` if it varies with the value.
+fn fixed_size(idl: &Idl, ty: &IdlType) -> anyhow::Result<Option<usize>> {
+    Ok(match ty {
+        IdlType::Bool | IdlType::U8 | IdlType::I8 => Some(1),
+        IdlType::U16 | IdlType::I16 => Some(2),
+        IdlType::U32 | IdlType::I32 | IdlType::F32 => Some(4),
+        IdlType::U64 | IdlType::I64 | IdlType::F64 => Some(8),
+        IdlType::U128 | IdlType::I128 => Some(16),
+        IdlType::U256 | IdlType::I256 | IdlType::Pubkey => Some(32),
+        IdlType::COption(inner) => fixed_size(idl, inner)?.map(|size| 4 + size),
+        IdlType::Tuple(types) => {
+            let mut total = 0;
+            for ty in types {
+                match fixed_size(idl, ty)? {
+                    Some(size) => total += size,
+                    None => return Ok(None),
+                }
+            }
+            Some(total)
+        }
+        IdlType::Array(inner, len) => {
+            let len = array_len(len)?;
+            fixed_size(idl, inner)?.map(|size| size * len)
+        }
+        IdlType::Defined { name, generics } => {
+            let type_def = idl
+                .type_def(name)
+                .with_context(|| format!("Type {} not found in IDL", name))?;
+            let types: Vec<IdlType> = match type_def.instantiate(generics)? {
+                IdlTypeDefTy::Struct { fields: None } => Vec::new(),
+                IdlTypeDefTy::Struct {
+                    fields: Some(IdlDefinedFields::Named(fields)),
+                } => fields.into_iter().map(|f| f.ty).collect(),
+                IdlTypeDefTy::Struct {
+                    fields: Some(IdlDefinedFields::Tuple(types)),
+                } => types,
+                IdlTypeDefTy::Type { alias } => vec![alias],
+                IdlTypeDefTy::Enum { variants } if variants.iter().all(|v| v.fields.is_none()) => {
+                    return Ok(Some(1));
+                }
+                IdlTypeDefTy::Enum { .. } => return Ok(None),
+            };
+            let mut total = 0;
+            for ty in &types {
+                match fixed_size(idl, ty)? {
+                    Some(size) => total += size,
+                    None => return Ok(None),
+                }
+            }
+            Some(total)
+        }
+        _ => None,
+    })
+}
+
+fn array_len(len: &IdlArrayLen) -> anyhow::Result<usize> {
+    match len {
+        IdlArrayLen::Value(len) => Ok(*len),
+        IdlArrayLen::Generic(name) => anyhow::bail!("Unbound IDL generic {}", name),
+    }
+}
+
+/// Decimal representation of a 256-bit little-endian integer.
+fn le_bytes_to_decimal(mut bytes: [u8; 32], signed: bool) -> String {
+    let negative = signed && bytes[31] & 0x80 != 0;
+    if negative {
+        let mut carry = true;
+        for byte in bytes.iter_mut() {
+            let (value, overflow) = (!*byte).overflowing_add(carry as u8);
+            *byte = value;
+            carry = overflow;
+        }
+    }
+
+    let mut limbs = [0u32; 8];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks_exact(4)) {
+        *limb = u32::from_le_bytes(chunk.try_into().expect("4-byte chunk"));
+    }
+
+    let mut digits = Vec::new();
+    while limbs.iter().any(|&limb| limb != 0) {
+        let mut remainder = 0u64;
+        for limb in limbs.iter_mut().rev() {
+            let current = (remainder << 32) | *limb as u64;
+            *limb = (current / 10) as u32;
+            remainder = current % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+    if digits.is_empty() {
+        digits.push(b'0');
+    }
+    if negative {
+        digits.push(b'-');
+    }
+    digits.reverse();
+    String::from_utf8(digits).expect("ASCII digits")
+}
+
+/// Decodes `fields` in order into `Argument`s, with `arg_type` set to the
+/// field's IDL type.
+pub fn decode_fields(
+    idl: &Idl,
+    fields: &[IdlField],
+    reader: &mut BorshReader,
+) -> anyhow::Result<Vec<Argument>> {
+    fields
+        .iter()
+        .map(|field| {
+            let value = decode_type(idl, &field.ty, reader)
+                .with_context(|| format!("Failed to decode field {}", field.name))?;
+            Ok(Argument {
+                name: field.name.clone(),
+                arg_type: field.ty.to_string(),
+                value,
+                indexed: false,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const IDL: &str = r#"{
+        "address": "Prog1111111111111111111111111111111111111111",
+        "metadata": {"name": "prog", "version": "0.1.0", "spec": "0.1.0"},
+        "instructions": [],
+        "types": [
+            {
+                "name": "Side",
+                "type": {"kind": "enum", "variants": [
+                    {"name": "Bid"},
+                    {"name": "Limit", "fields": [{"name": "price", "type": "u64"}]},
+                    {"name": "Pair", "fields": ["u8", {"defined": {"name": "Point"}}]}
+                ]}
+            },
+            {
+                "name": "Point",
+                "type": {"kind": "struct", "fields": [{"name": "x", "type": "i16"}, {"name": "y", "type": "i16"}]}
+            },
+            {
+                "name": "Wrapper",
+                "generics": [{"kind": "type", "name": "T"}, {"kind": "const", "name": "N", "type": "usize"}],
+                "type": {"kind": "struct", "fields": [
+                    {"name": "items", "type": {"array": [{"generic": "T"}, {"generic": "N"}]}},
+                    {"name": "extra", "type": {"option": {"generic": "T"}}}
+                ]}
+            },
+            {
+                "name": "Pod",
+                "serialization": "bytemuck",
+                "repr": {"kind": "c"},
+                "type": {"kind": "struct", "fields": [
+                    {"name": "flag", "type": "u8"},
+                    {"name": "amount", "type": "u64"},
+                    {"name": "points", "type": {"array": [{"defined": {"name": "Inner"}}, 2]}}
+                ]}
+            },
+            {
+                "name": "Inner",
+                "serialization": "bytemuck",
+                "repr": {"kind": "c"},
+                "type": {"kind": "struct", "fields": [{"name": "a", "type": "u32"}, {"name": "b", "type": "u8"}]}
+            }
+        ]
+    }"#;
+
+    fn idl() -> Idl {
+        Idl::from_json(IDL).unwrap()
+    }
+
+    fn decode_json(ty: serde_json::Value, data: &[u8]) -> anyhow::Result<(Value, usize)> {
+        let ty: IdlType = serde_json::from_value(ty)?;
+        let mut reader = BorshReader::new(data);
+        let value = decode_type(&idl(), &ty, &mut reader)?;
+        Ok((value, reader.remaining()))
+    }
+
+    #[test]
+    fn encodes_big_integers_as_strings() {
+        let (value, _) = decode_json(json!("u64"), &u64::MAX.to_le_bytes()).unwrap();
+        assert_eq!(value, json!("18446744073709551615"));
+        let (value, _) = decode_json(json!("i128"), &(-5i128).to_le_bytes()).unwrap();
+        assert_eq!(value, json!("-5"));
+
+        let mut u256 = [0u8; 32];
+        u256[8] = 1;
+        let (value, _) = decode_json(json!("u256"), &u256).unwrap();
+        assert_eq!(value, json!("18446744073709551616"));
+        let (value, _) = decode_json(json!("i256"), &[0xff; 32]).unwrap();
+        assert_eq!(value, json!("-1"));
+    }
+
+    #[test]
+    fn decodes_enum_variants_and_coption() {
+        let mut data = vec![1];
+        data.extend_from_slice(&9u64.to_le_bytes());
+        let (value, _) = decode_json(json!({"defined": "Side"}), &data).unwrap();
+        assert_eq!(value, json!({"Limit": {"price": "9"}}));
+
+        let data = [2, 7, 1, 0, 0xfe, 0xff];
+        let (value, _) = decode_json(json!({"defined": "Side"}), &data).unwrap();
+        assert_eq!(value, json!({"Pair": [7, {"x": 1, "y": -2}]}));
+
+        // An empty COption still occupies the value's bytes.
+        let mut data = 0u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&[0; 32]);
+        data.push(3);
+        let (value, remaining) = decode_json(json!({"coption": "pubkey"}), &data).unwrap();
+        assert_eq!((value, remaining), (Value::Null, 1));
+
+        let mut data = 1u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&[0; 32]);
+        let (value, _) = decode_json(json!({"coption": "pubkey"}), &data).unwrap();
+        assert_eq!(value, json!(Pubkey::default().to_string()));
+    }
+
+    #[test]
+    fn substitutes_generic_arguments() {
+        let ty = json!({"defined": {"name": "Wrapper", "generics": [
+            {"kind": "type", "type": "u16"},
+            {"kind": "const", "value": "2"}
+        ]}});
+        assert_eq!(
+            serde_json::from_value::<IdlType>(ty.clone()).unwrap().to_string(),
+            "Wrapper<u16, 2>"
+        );
+
+        let (value, remaining) = decode_json(ty, &[1, 0, 2, 0, 1, 3, 0]).unwrap();
+        assert_eq!(value, json!({"items": [1, 2], "extra": 3}));
+        assert_eq!(remaining, 0);
+
+        let unbound = decode_json(json!({"defined": "Wrapper"}), &[0; 8]);
+        assert!(unbound.is_err());
+    }
+
+    #[test]
+    fn reads_zero_copy_types_with_c_layout() {
+        let mut data = vec![1, 0, 0, 0, 0, 0, 0, 0];
+        data.extend_from_slice(&5u64.to_le_bytes());
+        for (a, b) in [(10u32, 1u8), (20, 2)] {
+            data.extend_from_slice(&a.to_le_bytes());
+            data.extend_from_slice(&[b, 0, 0, 0]);
+        }
+
+        let (value, remaining) = decode_json(json!({"defined": {"name": "Pod"}}), &data).unwrap();
+        assert_eq!(
+            value,
+            json!({"flag": 1, "amount": "5", "points": [{"a": 10, "b": 1}, {"a": 20, "b": 2}]})
+        );
+        assert_eq!(remaining, 0);
+    }
+}
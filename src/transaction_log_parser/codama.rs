@@ -0,0 +1,639 @@
+//! Conversion of Codama IDLs (`"standard": "codama"`) into `Idl`.
+//!
+//! Codama describes types as a tree of nodes. Nodes with a Borsh equivalent
+//! are mapped onto `IdlType`; inline struct and enum nodes are hoisted into
+//! named type definitions. Types, accounts and instructions that use
+//! big-endian numbers, custom size prefixes or other encodings Borsh cannot
+//! express are skipped with a warning. Instructions and accounts identified
+//! by a field or constant discriminator at offset 0 get that prefix as their
+//! `discriminator`, with the discriminator field removed from their arguments
+//! or layout; instructions without one are skipped.
+
+use crate::transaction_log_parser::idl::*;
+use anyhow::Context;
+use base64::Engine;
+use serde_json::Value;
+use std::collections::HashSet;
+use tracing::warn;
+
+pub fn idl_from_codama(root: &Value) -> anyhow::Result<Idl> {
+    let program = root.get("program").context("Codama IDL has no program node")?;
+    let mut converter = Converter {
+        types: Vec::new(),
+        reserved: nodes(program, "definedTypes")
+            .chain(nodes(program, "accounts"))
+            .filter_map(|node| node.get("name").and_then(Value::as_str))
+            .map(str::to_string)
+            .collect(),
+    };
+
+    for defined in nodes(program, "definedTypes") {
+        converter.try_convert("type", defined, |c| {
+            let name = str_field(defined, "name")?;
+            let ty = c.type_def_ty(field(defined, "type")?, name)?;
+            c.push_type(name.to_string(), ty);
+            Ok(())
+        });
+    }
+
+    let accounts = nodes(program, "accounts")
+        .filter_map(|account| converter.try_convert("account", account, |c| c.account(account)))
+        .collect();
+    let instructions = nodes(program, "instructions")
+        .filter_map(|instruction| {
+            converter
+                .try_convert("instruction", instruction, |c| c.instruction(instruction))
+                .flatten()
+        })
+        .collect();
+
+    Ok(Idl {
+        address: program
+            .get("publicKey")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        name: program.get("name").and_then(Value::as_str).map(str::to_string),
+        metadata: Some(IdlMetadata {
+            name: program.get("name").and_then(Value::as_str).map(str::to_string),
+            version: program.get("version").and_then(Value::as_str).map(str::to_string),
+            spec: root.get("version").and_then(Value::as_str).map(str::to_string),
+            origin: Some("codama".to_string()),
+        }),
+        instructions,
+        accounts,
+        events: Vec::new(),
+        types: converter.types,
+    })
+}
+
+struct Converter {
+    types: Vec<IdlTypeDef>,
+    /// Names of the program's defined types and accounts, which hoisted
+    /// types must not take.
+    reserved: HashSet<String>,
+}
+
+impl Converter {
+    fn push_type(&mut self, name: String, ty: IdlTypeDefTy) {
+        self.types.push(IdlTypeDef {
+            name,
+            serialization: IdlSerialization::default(),
+            repr: None,
+            generics: Vec::new(),
+            ty,
+        });
+    }
+
+    /// Runs `convert` on a defined type, account or instruction node. On
+    /// error, the types it hoisted are dropped and the node is skipped.
+    fn try_convert<T>(
+        &mut self,
+        node_kind: &str,
+        node: &Value,
+        convert: impl FnOnce(&mut Self) -> anyhow::Result<T>,
+    ) -> Option<T> {
+        let types = self.types.len();
+        match convert(self) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.types.truncate(types);
+                let name = node.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+                warn!(
+                    node_kind,
+                    name,
+                    error = %e,
+                    "Skipping unsupported Codama node"
+                );
+                None
+            }
+        }
+    }
+
+    fn account(&mut self, account: &Value) -> anyhow::Result<IdlAccountDef> {
+        let name = str_field(account, "name")?;
+        let data = field(account, "data")?;
+        let mut fields = nodes(data, "fields").collect::<Vec<_>>();
+        let mut discriminator = None;
+        let mut size = None;
+
+        for node in nodes(account, "discriminators") {
+            match kind(node)? {
+                "sizeDiscriminatorNode" => {
+                    size = Some(field(node, "size")?.as_u64().context("Invalid size")? as usize)
+                }
+                _ => discriminator = Some(discriminator_bytes(node, &mut fields)?),
+            }
+        }
+
+        let ty = self.struct_ty(fields, name)?;
+        self.push_type(name.to_string(), ty);
+        Ok(IdlAccountDef {
+            name: name.to_string(),
+            discriminator,
+            size,
+            ty: None,
+        })
+    }
+
+    /// `None` for instructions without a discriminator.
+    fn instruction(&mut self, instruction: &Value) -> anyhow::Result<Option<IdlInstruction>> {
+        let name = str_field(instruction, "name")?;
+        let mut arguments = nodes(instruction, "arguments").collect::<Vec<_>>();
+        let Some(discriminator) = nodes(instruction, "discriminators")
+            .find(|d| kind(d).is_ok_and(|k| k != "sizeDiscriminatorNode"))
+            .map(|d| discriminator_bytes(d, &mut arguments))
+            .transpose()?
+        else {
+            return Ok(None);
+        };
+
+        let args = arguments
+            .into_iter()
+            .map(|arg| {
+                let arg_name = str_field(arg, "name")?;
+                Ok(IdlField {
+                    name: arg_name.to_string(),
+                    ty: self.ty(field(arg, "type")?, &format!("{}_{}", name, arg_name))?,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let accounts = nodes(instruction, "accounts")
+            .map(|account| {
+                Ok(IdlInstructionAccountItem::Single(IdlInstructionAccount {
+                    name: str_field(account, "name")?.to_string(),
+                    writable: account.get("isWritable").and_then(Value::as_bool) == Some(true),
+                    // "either" signers are not required to sign.
+                    signer: account.get("isSigner").and_then(Value::as_bool) == Some(true),
+                    optional: account.get("isOptional").and_then(Value::as_bool) == Some(true),
+                }))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Some(IdlInstruction {
+            name: name.to_string(),
+            discriminator: Some(discriminator),
+            accounts,
+            args,
+            returns: None,
+            discriminant: None,
+        }))
+    }
+
+    /// `hint`, with a numeric suffix if a defined type, account or previously
+    /// hoisted type already has that name.
+    fn hoisted_name(&self, hint: &str) -> String {
+        let taken =
+            |name: &str| self.reserved.contains(name) || self.types.iter().any(|t| t.name == name);
+        if !taken(hint) {
+            return hint.to_string();
+        }
+        (2..)
+            .map(|i| format!("{}_{}", hint, i))
+            .find(|name| !taken(name))
+            .expect("suffixes are unbounded")
+    }
+
+    /// `hint` names a type definition hoisted from an inline struct or enum.
+    fn ty(&mut self, node: &Value, hint: &str) -> anyhow::Result<IdlType> {
+        Ok(match kind(node)? {
+            "numberTypeNode" => number_type(node)?,
+            "amountTypeNode" | "dateTimeTypeNode" | "solAmountTypeNode" => {
+                number_type(field(node, "number")?)?
+            }
+            "booleanTypeNode" => {
+                if let Some(size) = node.get("size") {
+                    anyhow::ensure!(number_type(size)? == IdlType::U8, "Unsupported boolean size");
+                }
+                IdlType::Bool
+            }
+            "publicKeyTypeNode" => IdlType::Pubkey,
+            "sizePrefixTypeNode" => {
+                anyhow::ensure!(
+                    number_type(field(node, "prefix")?)? == IdlType::U32,
+                    "Unsupported size prefix"
+                );
+                let inner = field(node, "type")?;
+                match kind(inner)? {
+                    "stringTypeNode" => {
+                        anyhow::ensure!(
+                            inner.get("encoding").and_then(Value::as_str).unwrap_or("utf8") == "utf8",
+                            "Unsupported string encoding"
+                        );
+                        IdlType::String
+                    }
+                    "bytesTypeNode" => IdlType::Bytes,
+                    other => anyhow::bail!("Unsupported size-prefixed {}", other),
+                }
+            }
+            "fixedSizeTypeNode" => {
+                let size = field(node, "size")?.as_u64().context("Invalid size")? as usize;
+                match kind(field(node, "type")?)? {
+                    "bytesTypeNode" | "stringTypeNode" => {
+                        IdlType::Array(Box::new(IdlType::U8), IdlArrayLen::Value(size))
+                    }
+                    other => anyhow::bail!("Unsupported fixed-size {}", other),
+                }
+            }
+            "arrayTypeNode" | "setTypeNode" => {
+                let item = self.ty(field(node, "item")?, hint)?;
+                let count = field(node, "count")?;
+                match (kind(node)?, kind(count)?) {
+                    ("arrayTypeNode", "fixedCountNode") => IdlType::Array(
+                        Box::new(item),
+                        IdlArrayLen::Value(field(count, "value")?.as_u64().context("Invalid count")? as usize),
+                    ),
+                    (node_kind, "prefixedCountNode") => {
+                        anyhow::ensure!(
+                            number_type(field(count, "prefix")?)? == IdlType::U32,
+                            "Unsupported count prefix"
+                        );
+                        if node_kind == "setTypeNode" {
+                            IdlType::Set(Box::new(item))
+                        } else {
+                            IdlType::Vec(Box::new(item))
+                        }
+                    }
+                    (_, other) => anyhow::bail!("Unsupported count {}", other),
+                }
+            }
+            "mapTypeNode" => {
+                let count = field(node, "count")?;
+                anyhow::ensure!(
+                    kind(count)? == "prefixedCountNode"
+                        && number_type(field(count, "prefix")?)? == IdlType::U32,
+                    "Unsupported map count"
+                );
+                IdlType::Map(
+                    Box::new(self.ty(field(node, "key")?, hint)?),
+                    Box::new(self.ty(field(node, "value")?, hint)?),
+                )
+            }
+            "optionTypeNode" => {
+                let item = Box::new(self.ty(field(node, "item")?, hint)?);
+                let prefix = match node.get("prefix") {
+                    Some(prefix) => number_type(prefix)?,
+                    None => IdlType::U8,
+                };
+                let fixed = node.get("fixed").and_then(Value::as_bool) == Some(true);
+                match (prefix, fixed) {
+                    (IdlType::U8, false) => IdlType::Option(item),
+                    (IdlType::U32, true) => IdlType::COption(item),
+                    _ => anyhow::bail!("Unsupported option prefix"),
+                }
+            }
+            "tupleTypeNode" => IdlType::Tuple(
+                nodes(node, "items")
+                    .enumerate()
+                    .map(|(i, item)| self.ty(item, &format!("{}_{}", hint, i)))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            "definedTypeLinkNode" => IdlType::Defined {
+                name: str_field(node, "name")?.to_string(),
+                generics: Vec::new(),
+            },
+            "structTypeNode" | "enumTypeNode" => {
+                let name = self.hoisted_name(hint);
+                let ty = self.type_def_ty(node, &name)?;
+                self.push_type(name.clone(), ty);
+                IdlType::Defined {
+                    name,
+                    generics: Vec::new(),
+                }
+            }
+            other => anyhow::bail!("Unsupported Codama type node {}", other),
+        })
+    }
+
+    fn type_def_ty(&mut self, node: &Value, name: &str) -> anyhow::Result<IdlTypeDefTy> {
+        match kind(node)? {
+            "structTypeNode" => self.struct_ty(nodes(node, "fields").collect(), name),
+            "enumTypeNode" => {
+                if let Some(size) = node.get("size") {
+                    anyhow::ensure!(number_type(size)? == IdlType::U8, "Unsupported enum size");
+                }
+                let variants = nodes(node, "variants")
+                    .enumerate()
+                    .map(|(i, variant)| {
+                        let variant_name = str_field(variant, "name")?;
+                        if let Some(discriminator) = variant.get("discriminator").and_then(Value::as_u64) {
+                            anyhow::ensure!(
+                                discriminator == i as u64,
+                                "Unsupported enum variant discriminator"
+                            );
+                        }
+                        let hint = format!("{}_{}", name, variant_name);
+                        let fields = match kind(variant)? {
+                            "enumEmptyVariantTypeNode" => None,
+                            "enumStructVariantTypeNode" => {
+                                match self.struct_ty(nodes(field(variant, "struct")?, "fields").collect(), &hint)? {
+                                    IdlTypeDefTy::Struct { fields } => fields,
+                                    _ => unreachable!("struct_ty returns a struct"),
+                                }
+                            }
+                            "enumTupleVariantTypeNode" => Some(IdlDefinedFields::Tuple(
+                                nodes(field(variant, "tuple")?, "items")
+                                    .enumerate()
+                                    .map(|(j, item)| self.ty(item, &format!("{}_{}", hint, j)))
+                                    .collect::<anyhow::Result<_>>()?,
+                            )),
+                            other => anyhow::bail!("Unsupported enum variant {}", other),
+                        };
+                        Ok(IdlEnumVariant {
+                            name: variant_name.to_string(),
+                            fields,
+                        })
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                Ok(IdlTypeDefTy::Enum { variants })
+            }
+            _ => Ok(IdlTypeDefTy::Type {
+                alias: self.ty(node, name)?,
+            }),
+        }
+    }
+
+    fn struct_ty(&mut self, fields: Vec<&Value>, name: &str) -> anyhow::Result<IdlTypeDefTy> {
+        let fields = fields
+            .into_iter()
+            .map(|f| {
+                let field_name = str_field(f, "name")?;
+                Ok(IdlField {
+                    name: field_name.to_string(),
+                    ty: self.ty(field(f, "type")?, &format!("{}_{}", name, field_name))?,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(IdlTypeDefTy::Struct {
+            fields: Some(IdlDefinedFields::Named(fields)),
+        })
+    }
+}
+
+/// Bytes of a field or constant discriminator, which must sit at offset 0.
+/// A field discriminator's field is removed from `fields`.
+fn discriminator_bytes(node: &Value, fields: &mut Vec<&Value>) -> anyhow::Result<Vec<u8>> {
+    anyhow::ensure!(
+        node.get("offset").and_then(Value::as_u64).unwrap_or(0) == 0,
+        "Unsupported discriminator offset"
+    );
+    match kind(node)? {
+        "fieldDiscriminatorNode" => {
+            let name = str_field(node, "name")?;
+            let position = fields
+                .iter()
+                .position(|f| f.get("name").and_then(Value::as_str) == Some(name))
+                .with_context(|| format!("Discriminator field {} not found", name))?;
+            anyhow::ensure!(position == 0, "Discriminator field {} is not first", name);
+            let discriminator = fields.remove(0);
+            value_bytes(
+                field(discriminator, "type")?,
+                discriminator
+                    .get("defaultValue")
+                    .with_context(|| format!("Discriminator field {} has no value", name))?,
+            )
+        }
+        "constantDiscriminatorNode" => {
+            let constant = field(node, "constant")?;
+            value_bytes(field(constant, "type")?, field(constant, "value")?)
+        }
+        other => anyhow::bail!("Unsupported discriminator {}", other),
+    }
+}
+
+fn value_bytes(ty: &Value, value: &Value) -> anyhow::Result<Vec<u8>> {
+    match kind(value)? {
+        "numberValueNode" => {
+            let number = field(value, "number")?.as_u64().context("Invalid number value")?;
+            let len = match number_type(ty)? {
+                IdlType::U8 => 1,
+                IdlType::U16 => 2,
+                IdlType::U32 => 4,
+                IdlType::U64 => 8,
+                other => anyhow::bail!("Unsupported discriminator type {}", other),
+            };
+            Ok(number.to_le_bytes()[..len].to_vec())
+        }
+        "bytesValueNode" => {
+            let data = str_field(value, "data")?;
+            Ok(match str_field(value, "encoding")? {
+                "base16" => (0..data.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(data.get(i..i + 2).unwrap_or(""), 16))
+                    .collect::<Result<_, _>>()
+                    .context("Invalid base16 data")?,
+                "base58" => bs58::decode(data).into_vec()?,
+                "base64" => base64::engine::general_purpose::STANDARD.decode(data)?,
+                "utf8" => data.as_bytes().to_vec(),
+                other => anyhow::bail!("Unsupported bytes encoding {}", other),
+            })
+        }
+        other => anyhow::bail!("Unsupported discriminator value {}", other),
+    }
+}
+
+fn number_type(node: &Value) -> anyhow::Result<IdlType> {
+    anyhow::ensure!(kind(node)? == "numberTypeNode", "Expected a number type");
+    anyhow::ensure!(
+        node.get("endian").and_then(Value::as_str).unwrap_or("le") == "le",
+        "Big-endian numbers are not supported"
+    );
+    Ok(match str_field(node, "format")? {
+        "u8" => IdlType::U8,
+        "i8" => IdlType::I8,
+        "u16" => IdlType::U16,
+        "i16" => IdlType::I16,
+        "u32" => IdlType::U32,
+        "i32" => IdlType::I32,
+        "f32" => IdlType::F32,
+        "u64" => IdlType::U64,
+        "i64" => IdlType::I64,
+        "f64" => IdlType::F64,
+        "u128" => IdlType::U128,
+        "i128" => IdlType::I128,
+        other => anyhow::bail!("Unsupported number format {}", other),
+    })
+}
+
+fn kind(node: &Value) -> anyhow::Result<&str> {
+    str_field(node, "kind")
+}
+
+fn field<'a>(node: &'a Value, name: &str) -> anyhow::Result<&'a Value> {
+    node.get(name)
+        .with_context(|| format!("Codama node has no {}", name))
+}
+
+fn str_field<'a>(node: &'a Value, name: &str) -> anyhow::Result<&'a str> {
+    field(node, name)?
+        .as_str()
+        .with_context(|| format!("Codama node {} is not a string", name))
+}
+
+fn nodes<'a>(node: &'a Value, name: &str) -> impl Iterator<Item = &'a Value> {
+    node.get(name)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transaction_log_parser::borsh::decode_account;
+    use crate::transaction_log_parser::{Idl, InstructionWithContext, LogParser, TransactionLogParser};
+    use serde_json::json;
+
+    const PROGRAM_ID: &str = "Count11111111111111111111111111111111111111";
+
+    fn number(format: &str) -> serde_json::Value {
+        json!({"kind": "numberTypeNode", "format": format, "endian": "le"})
+    }
+
+    fn codama_idl() -> String {
+        json!({
+            "kind": "rootNode",
+            "standard": "codama",
+            "version": "1.2.0",
+            "program": {
+                "kind": "programNode",
+                "name": "counter",
+                "publicKey": PROGRAM_ID,
+                "version": "0.1.0",
+                "accounts": [{
+                    "kind": "accountNode",
+                    "name": "counter",
+                    "data": {"kind": "structTypeNode", "fields": [
+                        {"kind": "structFieldTypeNode", "name": "discriminator", "type": number("u8"),
+                         "defaultValue": {"kind": "numberValueNode", "number": 7}},
+                        {"kind": "structFieldTypeNode", "name": "count", "type": number("u64")},
+                        {"kind": "structFieldTypeNode", "name": "mode",
+                         "type": {"kind": "definedTypeLinkNode", "name": "mode"}}
+                    ]},
+                    "discriminators": [{"kind": "fieldDiscriminatorNode", "name": "discriminator", "offset": 0}]
+                }],
+                "instructions": [{
+                    "kind": "instructionNode",
+                    "name": "increment",
+                    "accounts": [
+                        {"kind": "instructionAccountNode", "name": "counter", "isWritable": true, "isSigner": false},
+                        {"kind": "instructionAccountNode", "name": "authority", "isWritable": false, "isSigner": "either"}
+                    ],
+                    "arguments": [
+                        {"kind": "instructionArgumentNode", "name": "discriminator", "type": number("u8"),
+                         "defaultValue": {"kind": "numberValueNode", "number": 1}, "defaultValueStrategy": "omitted"},
+                        {"kind": "instructionArgumentNode", "name": "amount", "type": number("u32")},
+                        {"kind": "instructionArgumentNode", "name": "memo", "type": {
+                            "kind": "optionTypeNode", "fixed": false, "prefix": number("u8"),
+                            "item": {"kind": "sizePrefixTypeNode", "prefix": number("u32"),
+                                     "type": {"kind": "stringTypeNode", "encoding": "utf8"}}
+                        }}
+                    ],
+                    "discriminators": [{"kind": "fieldDiscriminatorNode", "name": "discriminator", "offset": 0}]
+                }],
+                "definedTypes": [{
+                    "kind": "definedTypeNode",
+                    "name": "mode",
+                    "type": {"kind": "enumTypeNode", "size": number("u8"), "variants": [
+                        {"kind": "enumEmptyVariantTypeNode", "name": "off"},
+                        {"kind": "enumTupleVariantTypeNode", "name": "step",
+                         "tuple": {"kind": "tupleTypeNode", "items": [number("u16")]}}
+                    ]}
+                }]
+            },
+            "additionalPrograms": []
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn decodes_codama_instructions_and_accounts() {
+        let idl = Idl::from_json(&codama_idl()).unwrap();
+        assert_eq!(idl.origin(), Some("codama"));
+
+        let parser = TransactionLogParser::new();
+        parser.idl_store().add_idl(PROGRAM_ID, idl.clone());
+
+        let mut data = vec![1];
+        data.extend_from_slice(&5u32.to_le_bytes());
+        data.push(0);
+        let accounts = vec!["Counter".to_string(), "Authority".to_string()];
+        let decoded = parser
+            .decode_instruction(&InstructionWithContext::bare(PROGRAM_ID, &data, &accounts))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.name, "increment");
+        assert_eq!(decoded.arguments.len(), 2);
+        assert_eq!(decoded.arguments[0].value, json!(5));
+        assert_eq!(decoded.arguments[1].arg_type, "option<string>");
+        assert!(decoded.accounts[0].writable);
+        assert!(!decoded.accounts[1].signer);
+
+        let mut data = vec![7];
+        data.extend_from_slice(&9u64.to_le_bytes());
+        data.extend_from_slice(&[1, 2, 0]);
+        let (name, value) = decode_account(&idl, &data).unwrap().unwrap();
+        assert_eq!(name, "counter");
+        assert_eq!(value, json!({"count": "9", "mode": {"step": [2]}}));
+
+        assert!(decode_account(&idl, &[8, 0]).unwrap().is_none());
+    }
+
+    #[test]
+    fn skips_unsupported_nodes_and_renames_colliding_hoisted_types() {
+        let mut root: serde_json::Value = serde_json::from_str(&codama_idl()).unwrap();
+        let program = &mut root["program"];
+        program["definedTypes"].as_array_mut().unwrap().extend([
+            json!({"kind": "definedTypeNode", "name": "bigEndian",
+                   "type": {"kind": "numberTypeNode", "format": "u32", "endian": "be"}}),
+            json!({"kind": "definedTypeNode", "name": "reset_config", "type": number("u8")}),
+        ]);
+        program["accounts"].as_array_mut().unwrap().push(json!({
+            "kind": "accountNode",
+            "name": "optional",
+            "data": {"kind": "structTypeNode", "fields": [
+                {"kind": "structFieldTypeNode", "name": "value",
+                 "type": {"kind": "remainderOptionTypeNode", "item": number("u8")}}
+            ]}
+        }));
+        let discriminator = |value: u8| {
+            json!({"kind": "instructionArgumentNode", "name": "discriminator", "type": number("u8"),
+                   "defaultValue": {"kind": "numberValueNode", "number": value}})
+        };
+        let field_discriminator =
+            json!([{"kind": "fieldDiscriminatorNode", "name": "discriminator", "offset": 0}]);
+        program["instructions"].as_array_mut().unwrap().extend([
+            json!({"kind": "instructionNode", "name": "compact", "accounts": [],
+                   "arguments": [discriminator(2),
+                                 {"kind": "instructionArgumentNode", "name": "len", "type": number("shortU16")}],
+                   "discriminators": field_discriminator}),
+            json!({"kind": "instructionNode", "name": "reset", "accounts": [],
+                   "arguments": [discriminator(3),
+                                 {"kind": "instructionArgumentNode", "name": "config", "type": {
+                                     "kind": "structTypeNode",
+                                     "fields": [{"kind": "structFieldTypeNode", "name": "to", "type": number("u16")}]
+                                 }}],
+                   "discriminators": field_discriminator}),
+        ]);
+
+        let idl = Idl::from_json(&root.to_string()).unwrap();
+        let names = |items: Vec<&str>| items.into_iter().map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            names(idl.instructions.iter().map(|i| i.name.as_str()).collect()),
+            names(vec!["increment", "reset"])
+        );
+        assert_eq!(
+            names(idl.accounts.iter().map(|a| a.name.as_str()).collect()),
+            names(vec!["counter"])
+        );
+        assert!(idl.type_def("bigEndian").is_none());
+        assert!(idl.type_def("optional").is_none());
+        assert!(idl.type_def("reset_config").is_some());
+        assert_eq!(
+            idl.instructions[1].args[0].ty,
+            crate::transaction_log_parser::IdlType::Defined {
+                name: "reset_config_2".to_string(),
+                generics: Vec::new(),
+            }
+        );
+    }
+}
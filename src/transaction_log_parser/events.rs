@@ -0,0 +1,220 @@
+use crate::clients::solana::{SolanaProgramLog, SolanaTransaction};
+use crate::transaction_log_parser::{InstructionWithContext, InvocationTree};
+use base64::Engine;
+
+/// Prefix of the instruction data Anchor's `emit_cpi!` passes to the
+/// program's self-invocation, ahead of the event discriminator.
+pub const EVENT_IX_TAG: [u8; 8] = 0x1d9a_cb51_2ea5_45e4u64.to_le_bytes();
+
+/// Decodes the space-separated base64 payload of a `Program data: ` line.
+pub fn decode_program_data(data: &str) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for chunk in data.split_whitespace() {
+        bytes.extend(base64::engine::general_purpose::STANDARD.decode(chunk)?);
+    }
+    Ok(bytes)
+}
+
+/// Events emitted with `emit_cpi!` by programs matching `is_interesting`.
+///
+/// Each event is returned as a synthetic `Program data: ` log carrying the
+/// same bytes `emit!` would have logged, so it decodes, persists and replays
+/// like a log-emitted event. Log indexes continue after the transaction's
+/// real log messages to keep them unique within the transaction; use
+/// `merge_cpi_event_logs` to deliver them in execution order.
+pub fn cpi_event_logs(
+    transaction: &SolanaTransaction,
+    is_interesting: impl Fn(&str) -> bool,
+) -> Vec<SolanaProgramLog> {
+    InstructionWithContext::from_transaction(transaction)
+        .into_iter()
+        .filter(|i| {
+            i.parent_program_id() == Some(i.program_id.as_str())
+                && i.data.starts_with(&EVENT_IX_TAG)
+        })
+        .enumerate()
+        .filter(|(_, i)| is_interesting(&i.program_id))
+        .map(|(n, i)| SolanaProgramLog {
+            log_index: (transaction.log_messages.len() + n) as u64,
+            signature: transaction.signature.clone(),
+            slot: transaction.slot,
+            block_time: transaction.block_time,
+            log_message: format!(
+                "Program data: {}",
+                base64::engine::general_purpose::STANDARD.encode(&i.data[EVENT_IX_TAG.len()..])
+            ),
+            instruction_index: i.position.top_level_index,
+            inner_instruction_index: i.position.inner_index,
+            depth: i.stack_height as usize,
+            parent_program_id: Some(i.program_id.clone()),
+            logs_truncated: false,
+            program_id: i.program_id,
+        })
+        .collect()
+}
+
+/// Inserts the `emit_cpi!` events of `transactions` into `logs`, right after
+/// the line invoking their self-CPI.
+///
+/// `logs` must be laid out as fetched: grouped by program, then ordered by
+/// transaction as in `transactions` and by log index. Events whose
+/// invocation is missing from truncated logs go after the program's other
+/// logs of the transaction.
+pub fn merge_cpi_event_logs(
+    transactions: &[SolanaTransaction],
+    logs: &mut Vec<SolanaProgramLog>,
+    is_interesting: impl Fn(&str) -> bool,
+) {
+    let position = |signature: &str| transactions.iter().position(|t| t.signature == signature);
+
+    for (transaction_position, transaction) in transactions.iter().enumerate() {
+        let events = cpi_event_logs(transaction, &is_interesting);
+        if events.is_empty() {
+            continue;
+        }
+
+        let tree = InvocationTree::parse(&transaction.log_messages);
+        let invoke_line = |event: &SolanaProgramLog| {
+            let invocation = tree.invocations.iter().position(|i| {
+                i.instruction_index == event.instruction_index
+                    && i.inner_index == event.inner_instruction_index
+            })?;
+            tree.line_invocations
+                .iter()
+                .position(|&line| line == Some(invocation))
+        };
+
+        // Line each of the transaction's logs sorts at; events sort at the
+        // line of their invocation.
+        let mut event_lines = Vec::new();
+        for event in events {
+            let line = invoke_line(&event).unwrap_or(usize::MAX);
+            let line_of = |log: &SolanaProgramLog| {
+                event_lines
+                    .iter()
+                    .find(|(index, _)| *index == log.log_index)
+                    .map_or(log.log_index as usize, |&(_, line)| line)
+            };
+            let index = logs
+                .iter()
+                .position(|l| {
+                    l.program_id == event.program_id
+                        && if l.signature == transaction.signature {
+                            line_of(l) > line
+                        } else {
+                            position(&l.signature) > Some(transaction_position)
+                        }
+                })
+                .or_else(|| {
+                    logs.iter()
+                        .rposition(|l| l.program_id == event.program_id)
+                        .map(|i| i + 1)
+                })
+                .unwrap_or(logs.len());
+            event_lines.push((event.log_index, line));
+            logs.insert(index, event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::solana::{SolanaInnerInstruction, SolanaInstruction};
+
+    fn instruction(program_id_index: usize, data: &[u8], stack_height: u32) -> SolanaInstruction {
+        SolanaInstruction {
+            program_id_index,
+            accounts: vec![2],
+            data: bs58::encode(data).into_string(),
+            stack_height: Some(stack_height),
+        }
+    }
+
+    #[test]
+    fn extracts_self_cpi_events_only() {
+        let mut event_data = EVENT_IX_TAG.to_vec();
+        event_data.extend_from_slice(&[1, 2, 3]);
+
+        let transaction = SolanaTransaction {
+            signature: "sig".to_string(),
+            slot: 9,
+            block_time: Some(1),
+            fee: 5000,
+            success: true,
+            error: None,
+            account_keys: vec!["Prog".to_string(), "Token".to_string(), "EventAuth".to_string()],
+            program_ids: vec!["Prog".to_string(), "Token".to_string()],
+            log_messages: vec!["Program Prog invoke [1]".to_string(), "Program Prog success".to_string()],
+            pre_balances: Vec::new(),
+            post_balances: Vec::new(),
+            pre_token_balances: Vec::new(),
+            post_token_balances: Vec::new(),
+            instructions: vec![instruction(0, &[7], 1)],
+            inner_instructions: vec![SolanaInnerInstruction {
+                index: 0,
+                instructions: vec![
+                    instruction(1, &event_data, 2),
+                    instruction(0, &[7], 2),
+                    instruction(0, &event_data, 2),
+                ],
+            }],
+            ..Default::default()
+        };
+
+        let logs = cpi_event_logs(&transaction, |p| p == "Prog");
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].program_id, "Prog");
+        assert_eq!(logs[0].log_index, 2);
+        assert_eq!(logs[0].depth, 2);
+        assert_eq!(logs[0].log_message, "Program data: AQID");
+        assert_eq!(decode_program_data("AQID").unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn merges_cpi_events_at_their_invocation() {
+        let messages: Vec<String> = [
+            "Program Prog invoke [1]",
+            "Program log: before",
+            "Program Prog invoke [2]",
+            "Program Prog success",
+            "Program log: after",
+            "Program Prog success",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let transaction = |signature: &str, inner: bool| SolanaTransaction {
+            signature: signature.to_string(),
+            account_keys: vec!["Prog".to_string(), "EventAuth".to_string(), "User".to_string()],
+            program_ids: vec!["Prog".to_string()],
+            log_messages: messages.clone(),
+            instructions: vec![instruction(0, &[7], 1)],
+            inner_instructions: vec![SolanaInnerInstruction {
+                index: 0,
+                instructions: if inner {
+                    vec![instruction(0, &EVENT_IX_TAG, 2)]
+                } else {
+                    vec![instruction(0, &[7], 2)]
+                },
+            }],
+            ..Default::default()
+        };
+        let transactions = vec![transaction("sig1", true), transaction("sig2", false)];
+        let mut logs: Vec<_> = transactions
+            .iter()
+            .flat_map(|t| {
+                InvocationTree::parse(&t.log_messages).program_logs(&t.log_messages, "Prog", &t.signature, 9, None)
+            })
+            .collect();
+
+        merge_cpi_event_logs(&transactions, &mut logs, |p| p == "Prog");
+
+        let order: Vec<_> = logs.iter().map(|l| (l.signature.as_str(), l.log_index)).collect();
+        assert_eq!(
+            &order[..7],
+            &[("sig1", 0), ("sig1", 1), ("sig1", 2), ("sig1", 6), ("sig1", 3), ("sig1", 4), ("sig1", 5)]
+        );
+        assert!(order[7..].iter().all(|&(signature, _)| signature == "sig2"));
+    }
+}
@@ -0,0 +1,848 @@
+use crate::transaction_log_parser::codama;
+use anyhow::Context;
+use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// Program IDL, accepting Anchor's legacy (pre-0.30) layout and 0.30+
+/// specification, Shank IDLs, and Codama IDLs (converted on load by
+/// `Idl::from_json`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct Idl {
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub metadata: Option<IdlMetadata>,
+    #[serde(default)]
+    pub instructions: Vec<IdlInstruction>,
+    #[serde(default)]
+    pub accounts: Vec<IdlAccountDef>,
+    #[serde(default)]
+    pub events: Vec<IdlEvent>,
+    #[serde(default)]
+    pub types: Vec<IdlTypeDef>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlMetadata {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub spec: Option<String>,
+    /// Tool that generated a non-Anchor IDL: `shank` or `codama`.
+    #[serde(default)]
+    pub origin: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlInstruction {
+    pub name: String,
+    #[serde(default)]
+    pub discriminator: Option<Vec<u8>>,
+    #[serde(default)]
+    pub accounts: Vec<IdlInstructionAccountItem>,
+    #[serde(default)]
+    pub args: Vec<IdlField>,
+    #[serde(default)]
+    pub returns: Option<IdlType>,
+    /// Shank's instruction discriminator, folded into `discriminator` on load.
+    #[serde(default)]
+    pub discriminant: Option<IdlDiscriminant>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlDiscriminant {
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+    pub value: u64,
+}
+
+/// Account type a program owns. Its layout is the type definition of the
+/// same name; legacy Anchor and Shank IDLs declare it inline, and it is moved
+/// to `types` on load.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlAccountDef {
+    pub name: String,
+    /// Prefix identifying the account type, skipped before decoding.
+    #[serde(default)]
+    pub discriminator: Option<Vec<u8>>,
+    /// Data length identifying the account type, for programs that tell
+    /// accounts apart by size.
+    #[serde(default)]
+    pub size: Option<usize>,
+    #[serde(default, rename = "type")]
+    pub ty: Option<IdlTypeDefTy>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum IdlInstructionAccountItem {
+    Composite(IdlInstructionAccounts),
+    Single(IdlInstructionAccount),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlInstructionAccounts {
+    pub name: String,
+    pub accounts: Vec<IdlInstructionAccountItem>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlInstructionAccount {
+    pub name: String,
+    #[serde(default, alias = "isMut")]
+    pub writable: bool,
+    #[serde(default, alias = "isSigner")]
+    pub signer: bool,
+    #[serde(default, alias = "isOptional")]
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlEvent {
+    pub name: String,
+    #[serde(default)]
+    pub discriminator: Option<Vec<u8>>,
+    /// Legacy IDLs declare event fields inline; 0.30+ IDLs refer to a type of
+    /// the same name in `types`.
+    #[serde(default)]
+    pub fields: Option<Vec<IdlField>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: IdlType,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlTypeDef {
+    pub name: String,
+    #[serde(default)]
+    pub serialization: IdlSerialization,
+    #[serde(default)]
+    pub repr: Option<IdlRepr>,
+    #[serde(default)]
+    pub generics: Vec<IdlTypeDefGeneric>,
+    #[serde(rename = "type")]
+    pub ty: IdlTypeDefTy,
+}
+
+/// How values of a type are laid out in account and instruction data.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdlSerialization {
+    #[default]
+    Borsh,
+    /// Zero-copy types, read with their `#[repr(C)]` memory layout.
+    Bytemuck,
+    BytemuckUnsafe,
+    Custom(String),
+}
+
+impl IdlSerialization {
+    pub fn is_zero_copy(&self) -> bool {
+        matches!(self, IdlSerialization::Bytemuck | IdlSerialization::BytemuckUnsafe)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlRepr {
+    pub kind: IdlReprKind,
+    #[serde(default)]
+    pub packed: bool,
+    #[serde(default)]
+    pub align: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdlReprKind {
+    Rust,
+    C,
+    Transparent,
+}
+
+/// Generic parameter declared by a type definition.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IdlTypeDefGeneric {
+    Type {
+        name: String,
+    },
+    Const {
+        name: String,
+        #[serde(rename = "type")]
+        ty: String,
+    },
+}
+
+impl IdlTypeDefGeneric {
+    pub fn name(&self) -> &str {
+        match self {
+            IdlTypeDefGeneric::Type { name } | IdlTypeDefGeneric::Const { name, .. } => name,
+        }
+    }
+}
+
+/// Generic argument supplied where a generic type definition is used.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IdlGenericArg {
+    Type {
+        #[serde(rename = "type")]
+        ty: IdlType,
+    },
+    Const {
+        value: String,
+    },
+}
+
+impl fmt::Display for IdlGenericArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdlGenericArg::Type { ty } => write!(f, "{}", ty),
+            IdlGenericArg::Const { value } => write!(f, "{}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IdlTypeDefTy {
+    Struct {
+        #[serde(default)]
+        fields: Option<IdlDefinedFields>,
+    },
+    Enum {
+        variants: Vec<IdlEnumVariant>,
+    },
+    Type {
+        alias: IdlType,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum IdlDefinedFields {
+    Named(Vec<IdlField>),
+    Tuple(Vec<IdlType>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdlEnumVariant {
+    pub name: String,
+    #[serde(default)]
+    pub fields: Option<IdlDefinedFields>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdlType {
+    Bool,
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    U64,
+    I64,
+    F64,
+    U128,
+    I128,
+    U256,
+    I256,
+    Bytes,
+    String,
+    Pubkey,
+    Option(Box<IdlType>),
+    /// Fixed-size option with a four-byte tag, as used by SPL programs.
+    COption(Box<IdlType>),
+    Vec(Box<IdlType>),
+    Array(Box<IdlType>, IdlArrayLen),
+    Tuple(Vec<IdlType>),
+    /// Length-prefixed map, decoded to a JSON object keyed by the key's
+    /// string form.
+    Map(Box<IdlType>, Box<IdlType>),
+    Set(Box<IdlType>),
+    Defined {
+        name: String,
+        generics: Vec<IdlGenericArg>,
+    },
+    /// Type parameter of the enclosing generic type definition.
+    Generic(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdlArrayLen {
+    Value(usize),
+    /// Const parameter of the enclosing generic type definition.
+    Generic(String),
+}
+
+impl fmt::Display for IdlArrayLen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdlArrayLen::Value(len) => write!(f, "{}", len),
+            IdlArrayLen::Generic(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl IdlType {
+    fn from_json(value: &serde_json::Value) -> anyhow::Result<Self> {
+        use serde_json::Value;
+
+        match value {
+            Value::String(name) => Ok(match name.as_str() {
+                "bool" => IdlType::Bool,
+                "u8" => IdlType::U8,
+                "i8" => IdlType::I8,
+                "u16" => IdlType::U16,
+                "i16" => IdlType::I16,
+                "u32" => IdlType::U32,
+                "i32" => IdlType::I32,
+                "f32" => IdlType::F32,
+                "u64" => IdlType::U64,
+                "i64" => IdlType::I64,
+                "f64" => IdlType::F64,
+                "u128" => IdlType::U128,
+                "i128" => IdlType::I128,
+                "u256" => IdlType::U256,
+                "i256" => IdlType::I256,
+                "bytes" => IdlType::Bytes,
+                "string" => IdlType::String,
+                "pubkey" | "publicKey" => IdlType::Pubkey,
+                other => anyhow::bail!("Unsupported IDL type: {}", other),
+            }),
+            Value::Object(map) => {
+                let (key, inner) = map.iter().next().context("Empty IDL type object")?;
+                match key.as_str() {
+                    "option" => Ok(IdlType::Option(Box::new(Self::from_json(inner)?))),
+                    "coption" => Ok(IdlType::COption(Box::new(Self::from_json(inner)?))),
+                    "vec" => Ok(IdlType::Vec(Box::new(Self::from_json(inner)?))),
+                    "array" => {
+                        let parts = inner.as_array().context("IDL array must be [type, len]")?;
+                        anyhow::ensure!(parts.len() == 2, "IDL array must be [type, len]");
+                        let len = match &parts[1] {
+                            Value::Object(len) => IdlArrayLen::Generic(
+                                len.get("generic")
+                                    .and_then(Value::as_str)
+                                    .context("IDL array length must be a number or generic")?
+                                    .to_string(),
+                            ),
+                            len => IdlArrayLen::Value(
+                                len.as_u64()
+                                    .context("IDL array length must be a number or generic")?
+                                    as usize,
+                            ),
+                        };
+                        Ok(IdlType::Array(Box::new(Self::from_json(&parts[0])?), len))
+                    }
+                    "defined" => match inner {
+                        Value::String(name) => Ok(IdlType::Defined {
+                            name: name.clone(),
+                            generics: Vec::new(),
+                        }),
+                        Value::Object(defined) => Ok(IdlType::Defined {
+                            name: defined
+                                .get("name")
+                                .and_then(Value::as_str)
+                                .context("IDL defined type has no name")?
+                                .to_string(),
+                            generics: match defined.get("generics") {
+                                Some(generics) => serde_json::from_value(generics.clone())
+                                    .context("Invalid IDL generic arguments")?,
+                                None => Vec::new(),
+                            },
+                        }),
+                        _ => anyhow::bail!("Invalid IDL defined type"),
+                    },
+                    "tuple" => Ok(IdlType::Tuple(
+                        inner
+                            .as_array()
+                            .context("IDL tuple must be a list of types")?
+                            .iter()
+                            .map(Self::from_json)
+                            .collect::<anyhow::Result<_>>()?,
+                    )),
+                    "hashMap" | "bTreeMap" => {
+                        let parts = inner.as_array().context("IDL map must be [key, value]")?;
+                        anyhow::ensure!(parts.len() == 2, "IDL map must be [key, value]");
+                        Ok(IdlType::Map(
+                            Box::new(Self::from_json(&parts[0])?),
+                            Box::new(Self::from_json(&parts[1])?),
+                        ))
+                    }
+                    "hashSet" | "bTreeSet" => Ok(IdlType::Set(Box::new(Self::from_json(inner)?))),
+                    "generic" => Ok(IdlType::Generic(
+                        inner
+                            .as_str()
+                            .context("IDL generic must be a name")?
+                            .to_string(),
+                    )),
+                    other => anyhow::bail!("Unsupported IDL type: {}", other),
+                }
+            }
+            _ => anyhow::bail!("Invalid IDL type: {}", value),
+        }
+    }
+
+    /// Replaces generic parameters with the arguments bound to them.
+    fn substitute(&self, bindings: &HashMap<&str, &IdlGenericArg>) -> anyhow::Result<Self> {
+        let bound = |name: &str| {
+            bindings
+                .get(name)
+                .copied()
+                .with_context(|| format!("Unbound IDL generic {}", name))
+        };
+
+        Ok(match self {
+            IdlType::Generic(name) => match bound(name)? {
+                IdlGenericArg::Type { ty } => ty.clone(),
+                IdlGenericArg::Const { .. } => anyhow::bail!("Generic {} is not a type", name),
+            },
+            IdlType::Option(inner) => IdlType::Option(Box::new(inner.substitute(bindings)?)),
+            IdlType::COption(inner) => IdlType::COption(Box::new(inner.substitute(bindings)?)),
+            IdlType::Vec(inner) => IdlType::Vec(Box::new(inner.substitute(bindings)?)),
+            IdlType::Set(inner) => IdlType::Set(Box::new(inner.substitute(bindings)?)),
+            IdlType::Map(key, value) => IdlType::Map(
+                Box::new(key.substitute(bindings)?),
+                Box::new(value.substitute(bindings)?),
+            ),
+            IdlType::Tuple(types) => IdlType::Tuple(
+                types
+                    .iter()
+                    .map(|ty| ty.substitute(bindings))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+            IdlType::Array(inner, len) => {
+                let len = match len {
+                    IdlArrayLen::Generic(name) => match bound(name)? {
+                        IdlGenericArg::Const { value } => IdlArrayLen::Value(
+                            value
+                                .parse()
+                                .with_context(|| format!("Invalid array length {}", value))?,
+                        ),
+                        IdlGenericArg::Type { .. } => {
+                            anyhow::bail!("Generic {} is not a const", name)
+                        }
+                    },
+                    len => len.clone(),
+                };
+                IdlType::Array(Box::new(inner.substitute(bindings)?), len)
+            }
+            IdlType::Defined { name, generics } => IdlType::Defined {
+                name: name.clone(),
+                generics: generics
+                    .iter()
+                    .map(|arg| {
+                        Ok(match arg {
+                            IdlGenericArg::Type { ty } => IdlGenericArg::Type {
+                                ty: ty.substitute(bindings)?,
+                            },
+                            IdlGenericArg::Const { value } => match bindings.get(value.as_str()) {
+                                Some(bound) => (*bound).clone(),
+                                None => arg.clone(),
+                            },
+                        })
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            },
+            other => other.clone(),
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for IdlType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        Self::from_json(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for IdlType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdlType::Bool => write!(f, "bool"),
+            IdlType::U8 => write!(f, "u8"),
+            IdlType::I8 => write!(f, "i8"),
+            IdlType::U16 => write!(f, "u16"),
+            IdlType::I16 => write!(f, "i16"),
+            IdlType::U32 => write!(f, "u32"),
+            IdlType::I32 => write!(f, "i32"),
+            IdlType::F32 => write!(f, "f32"),
+            IdlType::U64 => write!(f, "u64"),
+            IdlType::I64 => write!(f, "i64"),
+            IdlType::F64 => write!(f, "f64"),
+            IdlType::U128 => write!(f, "u128"),
+            IdlType::I128 => write!(f, "i128"),
+            IdlType::U256 => write!(f, "u256"),
+            IdlType::I256 => write!(f, "i256"),
+            IdlType::Bytes => write!(f, "bytes"),
+            IdlType::String => write!(f, "string"),
+            IdlType::Pubkey => write!(f, "pubkey"),
+            IdlType::Option(inner) => write!(f, "option<{}>", inner),
+            IdlType::COption(inner) => write!(f, "coption<{}>", inner),
+            IdlType::Vec(inner) => write!(f, "vec<{}>", inner),
+            IdlType::Array(inner, len) => write!(f, "[{}; {}]", inner, len),
+            IdlType::Tuple(types) => {
+                let types: Vec<String> = types.iter().map(ToString::to_string).collect();
+                write!(f, "({})", types.join(", "))
+            }
+            IdlType::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            IdlType::Set(inner) => write!(f, "set<{}>", inner),
+            IdlType::Defined { name, generics } if generics.is_empty() => write!(f, "{}", name),
+            IdlType::Defined { name, generics } => {
+                let generics: Vec<String> = generics.iter().map(ToString::to_string).collect();
+                write!(f, "{}<{}>", name, generics.join(", "))
+            }
+            IdlType::Generic(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+pub fn sighash(namespace: &str, name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("{}:{}", namespace, name).as_bytes());
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+/// Converts an instruction name to snake case with heck's word boundaries,
+/// which Anchor uses for discriminators: "setNFTOwner" becomes
+/// "set_nft_owner" and "initialize2" stays one word.
+fn to_snake_case(name: &str) -> String {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Mode {
+        Boundary,
+        Lowercase,
+        Uppercase,
+    }
+
+    let mut words: Vec<String> = Vec::new();
+    for word in name.split(|c: char| !c.is_alphanumeric()) {
+        let mut chars = word.char_indices().peekable();
+        let mut start = 0;
+        let mut mode = Mode::Boundary;
+        while let Some((i, c)) = chars.next() {
+            let Some(&(next_i, next)) = chars.peek() else {
+                words.push(word[start..].to_lowercase());
+                break;
+            };
+
+            let next_mode = if c.is_lowercase() {
+                Mode::Lowercase
+            } else if c.is_uppercase() {
+                Mode::Uppercase
+            } else {
+                mode
+            };
+            if next_mode == Mode::Lowercase && next.is_uppercase() {
+                // "setNFT": boundary after the lowercase character.
+                words.push(word[start..next_i].to_lowercase());
+                start = next_i;
+                mode = Mode::Boundary;
+            } else if mode == Mode::Uppercase && c.is_uppercase() && next.is_lowercase() {
+                // "NFTOwner": boundary before the last uppercase character.
+                words.push(word[start..i].to_lowercase());
+                start = i;
+                mode = Mode::Boundary;
+            } else {
+                mode = next_mode;
+            }
+        }
+    }
+    words.retain(|w| !w.is_empty());
+    words.join("_")
+}
+
+impl Idl {
+    /// Parses an Anchor, Shank or Codama IDL and normalizes its
+    /// discriminators and account layouts.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json).context("Failed to parse IDL")?;
+        if value.get("standard").and_then(|s| s.as_str()) == Some("codama") {
+            return codama::idl_from_codama(&value).context("Failed to convert Codama IDL");
+        }
+
+        let mut idl: Idl = serde_json::from_value(value).context("Failed to parse IDL")?;
+        idl.normalize()?;
+        Ok(idl)
+    }
+
+    pub fn origin(&self) -> Option<&str> {
+        self.metadata.as_ref().and_then(|m| m.origin.as_deref())
+    }
+
+    fn normalize(&mut self) -> anyhow::Result<()> {
+        let is_anchor = self.origin().is_none();
+
+        for instruction in &mut self.instructions {
+            if let Some(discriminant) = instruction.discriminant.take() {
+                let bytes = discriminant.value.to_le_bytes();
+                let len = match discriminant.ty {
+                    IdlType::U8 => 1,
+                    IdlType::U16 => 2,
+                    IdlType::U32 => 4,
+                    IdlType::U64 => 8,
+                    other => anyhow::bail!("Unsupported discriminant type {}", other),
+                };
+                instruction.discriminator = Some(bytes[..len].to_vec());
+            }
+        }
+
+        for account in &mut self.accounts {
+            if let Some(ty) = account.ty.take() {
+                if !self.types.iter().any(|t| t.name == account.name) {
+                    self.types.push(IdlTypeDef {
+                        name: account.name.clone(),
+                        serialization: IdlSerialization::default(),
+                        repr: None,
+                        generics: Vec::new(),
+                        ty,
+                    });
+                }
+            }
+            if is_anchor && account.discriminator.is_none() {
+                account.discriminator = Some(sighash("account", &account.name).to_vec());
+            }
+        }
+        Ok(())
+    }
+
+    /// Account definition whose discriminator prefixes `data`, or whose
+    /// declared size equals its length.
+    pub fn account_by_discriminator(&self, data: &[u8]) -> Option<&IdlAccountDef> {
+        self.accounts.iter().find(|account| match &account.discriminator {
+            Some(discriminator) => data.starts_with(discriminator),
+            None => account.size == Some(data.len()),
+        })
+    }
+
+    pub fn type_def(&self, name: &str) -> Option<&IdlTypeDef> {
+        self.types.iter().find(|t| t.name == name)
+    }
+
+    pub fn event_by_discriminator(&self, data: &[u8]) -> Option<&IdlEvent> {
+        self.events
+            .iter()
+            .find(|event| data.starts_with(&event.discriminator()))
+    }
+
+    /// Fields of `event`, declared inline in legacy IDLs or as a struct type
+    /// of the same name in 0.30+ IDLs.
+    pub fn event_fields(&self, event: &IdlEvent) -> anyhow::Result<Vec<IdlField>> {
+        if let Some(fields) = &event.fields {
+            return Ok(fields.clone());
+        }
+
+        let type_def = self
+            .type_def(&event.name)
+            .with_context(|| format!("Type for event {} not found in IDL", event.name))?;
+        match &type_def.ty {
+            IdlTypeDefTy::Struct { fields: None } => Ok(Vec::new()),
+            IdlTypeDefTy::Struct {
+                fields: Some(IdlDefinedFields::Named(fields)),
+            } => Ok(fields.clone()),
+            IdlTypeDefTy::Struct {
+                fields: Some(IdlDefinedFields::Tuple(types)),
+            } => Ok(types
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| IdlField {
+                    name: i.to_string(),
+                    ty: ty.clone(),
+                })
+                .collect()),
+            _ => anyhow::bail!("Event {} is not a struct", event.name),
+        }
+    }
+
+    pub fn instruction_by_discriminator(&self, data: &[u8]) -> Option<&IdlInstruction> {
+        self.instructions
+            .iter()
+            .find(|ix| data.starts_with(&ix.discriminator()))
+    }
+}
+
+impl IdlTypeDef {
+    /// The type's definition with its generic parameters bound to `generics`.
+    pub fn instantiate(&self, generics: &[IdlGenericArg]) -> anyhow::Result<IdlTypeDefTy> {
+        anyhow::ensure!(
+            generics.len() == self.generics.len(),
+            "Type {} takes {} generic arguments, got {}",
+            self.name,
+            self.generics.len(),
+            generics.len()
+        );
+        if generics.is_empty() {
+            return Ok(self.ty.clone());
+        }
+
+        let bindings: HashMap<&str, &IdlGenericArg> = self
+            .generics
+            .iter()
+            .map(IdlTypeDefGeneric::name)
+            .zip(generics)
+            .collect();
+        let fields = |fields: &Option<IdlDefinedFields>| -> anyhow::Result<_> {
+            Ok(match fields {
+                None => None,
+                Some(IdlDefinedFields::Named(fields)) => Some(IdlDefinedFields::Named(
+                    fields
+                        .iter()
+                        .map(|field| {
+                            Ok(IdlField {
+                                name: field.name.clone(),
+                                ty: field.ty.substitute(&bindings)?,
+                            })
+                        })
+                        .collect::<anyhow::Result<_>>()?,
+                )),
+                Some(IdlDefinedFields::Tuple(types)) => Some(IdlDefinedFields::Tuple(
+                    types
+                        .iter()
+                        .map(|ty| ty.substitute(&bindings))
+                        .collect::<anyhow::Result<_>>()?,
+                )),
+            })
+        };
+
+        Ok(match &self.ty {
+            IdlTypeDefTy::Struct { fields: f } => IdlTypeDefTy::Struct { fields: fields(f)? },
+            IdlTypeDefTy::Enum { variants } => IdlTypeDefTy::Enum {
+                variants: variants
+                    .iter()
+                    .map(|variant| {
+                        Ok(IdlEnumVariant {
+                            name: variant.name.clone(),
+                            fields: fields(&variant.fields)?,
+                        })
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            },
+            IdlTypeDefTy::Type { alias } => IdlTypeDefTy::Type {
+                alias: alias.substitute(&bindings)?,
+            },
+        })
+    }
+
+    /// Whether the type is laid out without padding between fields.
+    pub fn is_packed(&self) -> bool {
+        self.repr.as_ref().is_some_and(|repr| repr.packed)
+    }
+}
+
+impl IdlEvent {
+    /// Declared discriminator, or Anchor's `sha256("event:<Name>")` prefix for
+    /// legacy IDLs that do not declare one.
+    pub fn discriminator(&self) -> Vec<u8> {
+        match &self.discriminator {
+            Some(discriminator) => discriminator.clone(),
+            None => sighash("event", &self.name).to_vec(),
+        }
+    }
+}
+
+impl IdlInstruction {
+    /// Declared discriminator, or Anchor's `sha256("global:<snake_case_name>")`
+    /// prefix for legacy IDLs that do not declare one.
+    pub fn discriminator(&self) -> Vec<u8> {
+        match &self.discriminator {
+            Some(discriminator) => discriminator.clone(),
+            None => sighash("global", &to_snake_case(&self.name)).to_vec(),
+        }
+    }
+
+    /// Instruction accounts in positional order, with composite account groups
+    /// flattened and their names prefixed with the group name.
+    pub fn flattened_accounts(&self) -> Vec<IdlInstructionAccount> {
+        fn flatten(
+            items: &[IdlInstructionAccountItem],
+            prefix: &str,
+            out: &mut Vec<IdlInstructionAccount>,
+        ) {
+            for item in items {
+                match item {
+                    IdlInstructionAccountItem::Single(account) => {
+                        let mut account = account.clone();
+                        account.name = format!("{}{}", prefix, account.name);
+                        out.push(account);
+                    }
+                    IdlInstructionAccountItem::Composite(group) => {
+                        flatten(&group.accounts, &format!("{}{}.", prefix, group.name), out);
+                    }
+                }
+            }
+        }
+
+        let mut accounts = Vec::new();
+        flatten(&self.accounts, "", &mut accounts);
+        accounts
+    }
+}
+
+/// IDLs keyed by program ID.
+#[derive(Default)]
+pub struct IdlStore {
+    idls: RwLock<HashMap<String, Arc<Idl>>>,
+}
+
+impl IdlStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_idl(&self, program_id: &str, idl: Idl) {
+        self.idls
+            .write()
+            .expect("IDL store lock poisoned")
+            .insert(program_id.to_string(), Arc::new(idl));
+    }
+
+    pub fn get_idl(&self, program_id: &str) -> Option<Arc<Idl>> {
+        self.idls
+            .read()
+            .expect("IDL store lock poisoned")
+            .get(program_id)
+            .cloned()
+    }
+
+    pub fn program_ids(&self) -> Vec<String> {
+        self.idls
+            .read()
+            .expect("IDL store lock poisoned")
+            .keys()
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snake_case_matches_anchor_word_boundaries() {
+        for (name, snake) in [
+            ("initialize", "initialize"),
+            ("initializePool", "initialize_pool"),
+            ("setNFTOwner", "set_nft_owner"),
+            ("SetNFT", "set_nft"),
+            ("HTTPRequest", "http_request"),
+            ("withdraw2Tokens", "withdraw2_tokens"),
+            ("already_snake", "already_snake"),
+        ] {
+            assert_eq!(to_snake_case(name), snake, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use crate::clients::solana::Client;
+use crate::transaction_log_parser::borsh::BorshReader;
+use crate::transaction_log_parser::{sighash, Idl, IdlStore};
+use anyhow::Context;
+use flate2::read::ZlibDecoder;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::io::Read;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+pub const BPF_UPGRADEABLE_LOADER_ID: &str = "BPFLoaderUpgradeab1e11111111111111111111111";
+
+/// Seed Anchor derives a program's IDL account address with.
+const IDL_SEED: &str = "anchor:idl";
+
+/// Address of the account Anchor publishes `program_id`'s IDL to: an account
+/// created with seed `anchor:idl` from the program's signer PDA.
+pub fn idl_address(program_id: &Pubkey) -> anyhow::Result<Pubkey> {
+    let (base, _) = Pubkey::find_program_address(&[], program_id);
+    Pubkey::create_with_seed(&base, IDL_SEED, program_id).context("Failed to derive IDL address")
+}
+
+/// Parses the data of an IDL account: an 8-byte discriminator, the upgrade
+/// authority, then the length-prefixed, zlib-compressed IDL JSON.
+pub fn decode_idl_account(data: &[u8]) -> anyhow::Result<Idl> {
+    let mut reader = BorshReader::new(data);
+    anyhow::ensure!(
+        reader.read_array::<8>()? == sighash("account", "IdlAccount"),
+        "Not an Anchor IDL account"
+    );
+    reader.read_pubkey()?;
+    let len = reader.read_u32()? as usize;
+    let compressed = reader.read_bytes(len)?;
+
+    let mut json = String::new();
+    ZlibDecoder::new(compressed)
+        .read_to_string(&mut json)
+        .context("Failed to decompress IDL")?;
+    Idl::from_json(&json)
+}
+
+/// Builds IDL account data holding `json`, as `anchor idl init` writes it.
+#[cfg(test)]
+pub(crate) fn encode_idl_account(json: &str) -> Vec<u8> {
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(json.as_bytes()).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let mut data = sighash("account", "IdlAccount").to_vec();
+    data.extend_from_slice(&[0; 32]);
+    data.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+    data.extend_from_slice(&compressed);
+    data
+}
+
+/// Slot `program_id` was last deployed or upgraded at, read from its
+/// programdata account. `None` for programs not owned by the upgradeable
+/// loader, which cannot change.
+pub async fn program_upgrade_slot(
+    client: &dyn Client,
+    program_id: &str,
+) -> anyhow::Result<Option<u64>> {
+    let program = client
+        .get_account_info(program_id)
+        .await?
+        .with_context(|| format!("Program account {} not found", program_id))?;
+    if program.owner != BPF_UPGRADEABLE_LOADER_ID {
+        return Ok(None);
+    }
+
+    // UpgradeableLoaderState::Program { programdata_address }
+    let mut reader = BorshReader::new(&program.data);
+    anyhow::ensure!(reader.read_u32()? == 2, "{} is not a program account", program_id);
+    let programdata_address = reader.read_pubkey()?.to_string();
+
+    // UpgradeableLoaderState::ProgramData { slot, upgrade_authority_address }
+    let programdata = client
+        .get_account_info(&programdata_address)
+        .await?
+        .with_context(|| format!("Programdata account {} not found", programdata_address))?;
+    let mut reader = BorshReader::new(&programdata.data);
+    anyhow::ensure!(
+        reader.read_u32()? == 3,
+        "{} is not a programdata account",
+        programdata_address
+    );
+    Ok(Some(reader.read_u64()?))
+}
+
+struct FetchedIdl {
+    /// Upgrade slot the IDL account was last read at, `None` until a fetch
+    /// has succeeded.
+    upgrade_slot: Option<Option<u64>>,
+    checked_at: Instant,
+}
+
+/// Loads Anchor IDLs from their on-chain IDL accounts into an `IdlStore`.
+///
+/// A program's upgrade slot is checked at most once per `check_interval`,
+/// and its IDL account is only fetched again when the slot has changed.
+/// Programs without an IDL account are remembered the same way, so they are
+/// looked up again only after an upgrade, and failed lookups are retried
+/// only after `check_interval`.
+///
+/// IDLs are always read at the chain head and each program keeps a single
+/// IDL, so slots backfilled from before an upgrade are decoded with the IDL
+/// the program has now.
+pub struct OnChainIdlFetcher {
+    client: Arc<dyn Client>,
+    idl_store: Arc<IdlStore>,
+    check_interval: Duration,
+    fetched: Mutex<HashMap<String, FetchedIdl>>,
+}
+
+impl OnChainIdlFetcher {
+    pub fn new(client: Arc<dyn Client>, idl_store: Arc<IdlStore>) -> Self {
+        Self {
+            client,
+            idl_store,
+            check_interval: Duration::from_secs(60),
+            fetched: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn set_check_interval(&mut self, check_interval: Duration) {
+        self.check_interval = check_interval;
+    }
+
+    pub fn idl_store(&self) -> &Arc<IdlStore> {
+        &self.idl_store
+    }
+
+    /// Returns the IDL of `program_id`, fetching it if the program is new or
+    /// has been upgraded since it was last fetched. An error is returned once
+    /// per `check_interval`; in between, the previously loaded IDL is.
+    pub async fn refresh(&self, program_id: &str) -> anyhow::Result<Option<Arc<Idl>>> {
+        let cached_slot = {
+            let fetched = self.fetched.lock().expect("IDL fetcher lock poisoned");
+            match fetched.get(program_id) {
+                Some(f) if f.checked_at.elapsed() < self.check_interval => {
+                    return Ok(self.idl_store.get_idl(program_id));
+                }
+                Some(f) => f.upgrade_slot,
+                None => None,
+            }
+        };
+
+        let result = self.fetch(program_id, cached_slot).await;
+        self.fetched.lock().expect("IDL fetcher lock poisoned").insert(
+            program_id.to_string(),
+            FetchedIdl {
+                upgrade_slot: match &result {
+                    Ok(upgrade_slot) => Some(*upgrade_slot),
+                    Err(_) => cached_slot,
+                },
+                checked_at: Instant::now(),
+            },
+        );
+        result?;
+        Ok(self.idl_store.get_idl(program_id))
+    }
+
+    /// Loads the IDL account if the program's upgrade slot differs from
+    /// `cached_slot`, and returns the upgrade slot.
+    async fn fetch(
+        &self,
+        program_id: &str,
+        cached_slot: Option<Option<u64>>,
+    ) -> anyhow::Result<Option<u64>> {
+        let upgrade_slot = program_upgrade_slot(self.client.as_ref(), program_id).await?;
+        if cached_slot != Some(upgrade_slot) {
+            let address = idl_address(&Pubkey::from_str(program_id)?)?.to_string();
+            match self.client.get_account_info(&address).await? {
+                Some(account) => {
+                    let idl = decode_idl_account(&account.data)
+                        .with_context(|| format!("Invalid IDL account {}", address))?;
+                    info!(program_id, ?upgrade_slot, "Loaded on-chain IDL");
+                    self.idl_store.add_idl(program_id, idl);
+                }
+                None => debug!(program_id, address, "Program has no on-chain IDL"),
+            }
+        }
+        Ok(upgrade_slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::solana::{SolanaClient, SolanaClientConfig};
+    use base64::Engine;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    type Accounts = Arc<Mutex<HashMap<String, (String, Vec<u8>)>>>;
+
+    /// Serves `getAccountInfo` from `accounts` over HTTP, one request per
+    /// connection.
+    async fn fake_rpc(accounts: Accounts) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let accounts = accounts.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 4096];
+                    let body = loop {
+                        let n = socket.read(&mut buf).await.unwrap();
+                        request.extend_from_slice(&buf[..n]);
+                        let text = String::from_utf8_lossy(&request);
+                        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                            let len: usize = head
+                                .lines()
+                                .find_map(|l| l.to_lowercase().strip_prefix("content-length: ").map(str::to_string))
+                                .and_then(|l| l.trim().parse().ok())
+                                .unwrap_or(0);
+                            if body.len() >= len {
+                                break body.to_string();
+                            }
+                        }
+                    };
+
+                    let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+                    let pubkey = request["params"][0].as_str().unwrap();
+                    let value = accounts.lock().unwrap().get(pubkey).map(|(owner, data)| {
+                        serde_json::json!({
+                            "lamports": 1,
+                            "owner": owner,
+                            "data": [base64::engine::general_purpose::STANDARD.encode(data), "base64"],
+                            "executable": false,
+                            "rentEpoch": 0
+                        })
+                    });
+                    let response = serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": 1,
+                        "result": {"context": {"slot": 100}, "value": value}
+                    })
+                    .to_string();
+                    let reply = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        response.len(),
+                        response
+                    );
+                    socket.write_all(reply.as_bytes()).await.unwrap();
+                });
+            }
+        });
+
+        url
+    }
+
+    fn idl_account(name: &str) -> Vec<u8> {
+        let json = serde_json::json!({
+            "metadata": {"name": name, "version": "0.1.0", "spec": "0.1.0"},
+            "instructions": []
+        });
+        encode_idl_account(&json.to_string())
+    }
+
+    fn programdata(slot: u64) -> Vec<u8> {
+        let mut data = 3u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&slot.to_le_bytes());
+        data.push(0);
+        data
+    }
+
+    fn idl_name(idl: &Idl) -> Option<String> {
+        idl.metadata.as_ref().and_then(|m| m.name.clone())
+    }
+
+    #[tokio::test]
+    async fn fetches_idl_and_refreshes_on_upgrade() {
+        let program_id = Pubkey::new_unique();
+        let programdata_address = Pubkey::new_unique();
+        let idl_address = idl_address(&program_id).unwrap().to_string();
+
+        let mut program = 2u32.to_le_bytes().to_vec();
+        program.extend_from_slice(programdata_address.as_ref());
+
+        let accounts: Accounts = Arc::default();
+        {
+            let mut accounts = accounts.lock().unwrap();
+            let loader = BPF_UPGRADEABLE_LOADER_ID.to_string();
+            accounts.insert(program_id.to_string(), (loader.clone(), program));
+            accounts.insert(programdata_address.to_string(), (loader, programdata(10)));
+            accounts.insert(idl_address.clone(), (program_id.to_string(), idl_account("v1")));
+        }
+
+        let client = SolanaClient::new(SolanaClientConfig {
+            base_url: fake_rpc(accounts.clone()).await,
+            ..Default::default()
+        })
+        .unwrap();
+        let mut fetcher = OnChainIdlFetcher::new(Arc::new(client), Arc::new(IdlStore::new()));
+        fetcher.set_check_interval(Duration::ZERO);
+        let program_id = program_id.to_string();
+
+        let idl = fetcher.refresh(&program_id).await.unwrap().unwrap();
+        assert_eq!(idl_name(&idl).as_deref(), Some("v1"));
+
+        // A new IDL is only picked up once the program is upgraded.
+        accounts.lock().unwrap().get_mut(&idl_address).unwrap().1 = idl_account("v2");
+        let idl = fetcher.refresh(&program_id).await.unwrap().unwrap();
+        assert_eq!(idl_name(&idl).as_deref(), Some("v1"));
+
+        accounts.lock().unwrap().get_mut(&programdata_address.to_string()).unwrap().1 =
+            programdata(20);
+        let idl = fetcher.refresh(&program_id).await.unwrap().unwrap();
+        assert_eq!(idl_name(&idl).as_deref(), Some("v2"));
+        assert!(fetcher.idl_store().get_idl(&program_id).is_some());
+    }
+
+    #[tokio::test]
+    async fn failed_refreshes_are_retried_after_check_interval() {
+        let program_id = Pubkey::new_unique();
+        let accounts: Accounts = Arc::default();
+        let client = SolanaClient::new(SolanaClientConfig {
+            base_url: fake_rpc(accounts.clone()).await,
+            ..Default::default()
+        })
+        .unwrap();
+        let mut fetcher = OnChainIdlFetcher::new(Arc::new(client), Arc::new(IdlStore::new()));
+        fetcher.set_check_interval(Duration::from_secs(3600));
+        let program = program_id.to_string();
+
+        // The program account does not exist yet.
+        assert!(fetcher.refresh(&program).await.is_err());
+
+        {
+            let mut accounts = accounts.lock().unwrap();
+            let idl_address = idl_address(&program_id).unwrap().to_string();
+            accounts.insert(idl_address, (program.clone(), idl_account("v1")));
+            accounts.insert(program.clone(), ("NativeLoader1111".to_string(), Vec::new()));
+        }
+        // Still within the check interval of the failed attempt.
+        assert!(fetcher.refresh(&program).await.unwrap().is_none());
+
+        fetcher.set_check_interval(Duration::ZERO);
+        let idl = fetcher.refresh(&program).await.unwrap().unwrap();
+        assert_eq!(idl_name(&idl).as_deref(), Some("v1"));
+    }
+
+    #[test]
+    fn rejects_non_idl_accounts() {
+        assert!(decode_idl_account(&[0; 48]).is_err());
+        let idl = decode_idl_account(&idl_account("prog")).unwrap();
+        assert_eq!(idl_name(&idl).as_deref(), Some("prog"));
+    }
+}
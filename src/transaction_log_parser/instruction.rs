@@ -0,0 +1,255 @@
+use crate::clients::solana::{SolanaInstruction, SolanaTransaction};
+use crate::transaction_log_parser::DecodedInstruction;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InstructionPosition {
+    pub top_level_index: usize,
+    /// Index within the top-level instruction's inner instructions, or `None`
+    /// for the top-level instruction itself.
+    pub inner_index: Option<usize>,
+}
+
+/// An account passed to an instruction, with the transaction's flags for it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstructionAccount {
+    pub pubkey: String,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InstructionWithContext {
+    pub signature: String,
+    pub slot: u64,
+    pub position: InstructionPosition,
+    pub program_id: String,
+    pub accounts: Vec<InstructionAccount>,
+    pub data: Vec<u8>,
+    /// 1 for top-level instructions, incremented for every level of CPI.
+    pub stack_height: u32,
+    pub parent: Option<InstructionPosition>,
+    /// Program IDs of every invoking instruction, outermost first.
+    pub invoked_by: Vec<String>,
+    pub instruction: SolanaInstruction,
+    /// Filled in by the poller's `LogParser` when it can decode the program.
+    pub decoded: Option<DecodedInstruction>,
+}
+
+impl InstructionWithContext {
+    pub fn parent_program_id(&self) -> Option<&str> {
+        self.invoked_by.last().map(String::as_str)
+    }
+
+    /// Account pubkeys in instruction order.
+    pub fn account_keys(&self) -> Vec<String> {
+        self.accounts.iter().map(|a| a.pubkey.clone()).collect()
+    }
+
+    /// Flattens a transaction's top-level and inner instructions in execution
+    /// order. Inner instructions without a recorded stack height are treated
+    /// as direct children of their top-level instruction.
+    pub fn from_transaction(transaction: &SolanaTransaction) -> Vec<Self> {
+        let resolve = |instruction: &SolanaInstruction| -> (String, Vec<InstructionAccount>, Vec<u8>) {
+            let program_id = transaction
+                .account_keys
+                .get(instruction.program_id_index)
+                .cloned()
+                .unwrap_or_default();
+            let accounts = instruction
+                .accounts
+                .iter()
+                .map(|&i| InstructionAccount {
+                    pubkey: transaction.account_keys.get(i).cloned().unwrap_or_default(),
+                    is_signer: transaction.is_signer(i),
+                    is_writable: transaction.is_writable(i),
+                })
+                .collect();
+            let data = bs58::decode(&instruction.data).into_vec().unwrap_or_default();
+            (program_id, accounts, data)
+        };
+
+        let mut flattened = Vec::new();
+        for (top_level_index, instruction) in transaction.instructions.iter().enumerate() {
+            let position = InstructionPosition {
+                top_level_index,
+                inner_index: None,
+            };
+            let (program_id, accounts, data) = resolve(instruction);
+
+            // (stack height, position, program ID) of the current CPI chain.
+            let mut stack = vec![(1u32, position, program_id.clone())];
+            flattened.push(Self {
+                signature: transaction.signature.clone(),
+                slot: transaction.slot,
+                position,
+                program_id,
+                accounts,
+                data,
+                stack_height: 1,
+                parent: None,
+                invoked_by: Vec::new(),
+                instruction: instruction.clone(),
+                decoded: None,
+            });
+
+            let inner_instructions = transaction
+                .inner_instructions
+                .iter()
+                .filter(|inner| inner.index == top_level_index)
+                .flat_map(|inner| inner.instructions.iter());
+            for (inner_index, instruction) in inner_instructions.enumerate() {
+                let stack_height = instruction.stack_height.unwrap_or(2).max(2);
+                while stack.len() > 1 && stack.last().is_some_and(|(h, _, _)| *h >= stack_height) {
+                    stack.pop();
+                }
+
+                let position = InstructionPosition {
+                    top_level_index,
+                    inner_index: Some(inner_index),
+                };
+                let (program_id, accounts, data) = resolve(instruction);
+                flattened.push(Self {
+                    signature: transaction.signature.clone(),
+                    slot: transaction.slot,
+                    position,
+                    program_id: program_id.clone(),
+                    accounts,
+                    data,
+                    stack_height,
+                    parent: stack.last().map(|(_, p, _)| *p),
+                    invoked_by: stack.iter().map(|(_, _, id)| id.clone()).collect(),
+                    instruction: instruction.clone(),
+                    decoded: None,
+                });
+                stack.push((stack_height, position, program_id));
+            }
+        }
+
+        flattened
+    }
+}
+
+#[cfg(test)]
+impl InstructionWithContext {
+    /// A top-level instruction with no transaction context, for decoder tests.
+    pub(crate) fn bare(program_id: &str, data: &[u8], accounts: &[String]) -> Self {
+        Self {
+            program_id: program_id.to_string(),
+            accounts: accounts
+                .iter()
+                .map(|pubkey| InstructionAccount {
+                    pubkey: pubkey.clone(),
+                    ..Default::default()
+                })
+                .collect(),
+            data: data.to_vec(),
+            stack_height: 1,
+            ..Default::default()
+        }
+    }
+}
+
+impl TryFrom<&InstructionWithContext> for Instruction {
+    type Error = anyhow::Error;
+
+    fn try_from(instruction: &InstructionWithContext) -> anyhow::Result<Self> {
+        let accounts = instruction
+            .accounts
+            .iter()
+            .map(|account| {
+                Ok(AccountMeta {
+                    pubkey: Pubkey::from_str(&account.pubkey)?,
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Instruction {
+            program_id: Pubkey::from_str(&instruction.program_id)?,
+            accounts,
+            data: instruction.data.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::solana::SolanaInnerInstruction;
+
+    fn instruction(program_id_index: usize, stack_height: Option<u32>) -> SolanaInstruction {
+        SolanaInstruction {
+            program_id_index,
+            accounts: vec![0],
+            data: bs58::encode([program_id_index as u8]).into_string(),
+            stack_height,
+        }
+    }
+
+    #[test]
+    fn from_transaction_tracks_cpi_parents() {
+        // Top-level 0 (Outer) -> Middle -> Leaf, then Outer -> Leaf again.
+        // Top-level 1 (Other) has no inner instructions.
+        let transaction = SolanaTransaction {
+            signature: "sig".to_string(),
+            slot: 7,
+            block_time: None,
+            fee: 0,
+            success: true,
+            error: None,
+            account_keys: vec![
+                "Payer".to_string(),
+                "Outer".to_string(),
+                "Middle".to_string(),
+                "Leaf".to_string(),
+                "Other".to_string(),
+            ],
+            program_ids: Vec::new(),
+            log_messages: Vec::new(),
+            pre_balances: Vec::new(),
+            post_balances: Vec::new(),
+            pre_token_balances: Vec::new(),
+            post_token_balances: Vec::new(),
+            instructions: vec![instruction(1, None), instruction(4, None)],
+            inner_instructions: vec![SolanaInnerInstruction {
+                index: 0,
+                instructions: vec![
+                    instruction(2, Some(2)),
+                    instruction(3, Some(3)),
+                    instruction(3, Some(2)),
+                ],
+            }],
+            ..Default::default()
+        };
+
+        let flattened = InstructionWithContext::from_transaction(&transaction);
+        let summary: Vec<_> = flattened
+            .iter()
+            .map(|i| (i.program_id.as_str(), i.stack_height, i.parent_program_id()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Outer", 1, None),
+                ("Middle", 2, Some("Outer")),
+                ("Leaf", 3, Some("Middle")),
+                ("Leaf", 2, Some("Outer")),
+                ("Other", 1, None),
+            ]
+        );
+        assert_eq!(flattened[2].invoked_by, vec!["Outer", "Middle"]);
+        assert_eq!(
+            flattened[2].parent,
+            Some(InstructionPosition {
+                top_level_index: 0,
+                inner_index: Some(0),
+            })
+        );
+        assert_eq!(flattened[1].account_keys(), vec!["Payer"]);
+        assert!(flattened[1].accounts[0].is_signer && flattened[1].accounts[0].is_writable);
+        assert_eq!(flattened[1].data, vec![2]);
+    }
+}
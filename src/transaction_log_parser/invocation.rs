@@ -0,0 +1,381 @@
+use crate::clients::solana::SolanaProgramLog;
+use crate::transaction_log_parser::events::decode_program_data;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvocationStatus {
+    /// No `success` or `failed` line was seen, e.g. because logs were truncated.
+    Incomplete,
+    Success,
+    Failed(String),
+}
+
+/// Parsed `Program <id> consumed N of M compute units` line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComputeUnits {
+    pub consumed: u64,
+    /// Compute units available to the invocation when it started.
+    pub limit: u64,
+}
+
+/// Parsed `Program return: <id> <base64>` line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReturnData {
+    pub program_id: String,
+    pub data: Vec<u8>,
+    /// `data` decoded with the IDL return type of the emitting instruction.
+    pub value: Option<serde_json::Value>,
+    /// IDL return type `value` was decoded as.
+    pub value_type: Option<String>,
+}
+
+/// One program invocation reconstructed from a transaction's log messages.
+#[derive(Debug, Clone)]
+pub struct ProgramInvocation {
+    pub program_id: String,
+    /// Invocation depth as logged by the runtime; top-level instructions are 1.
+    pub depth: usize,
+    /// Index of the top-level instruction this invocation belongs to.
+    pub instruction_index: usize,
+    /// Order of this invocation among the inner instructions of its top-level
+    /// instruction, matching the transaction's `inner_instructions`. `None`
+    /// for the top-level invocation itself.
+    pub inner_index: Option<usize>,
+    /// Index of the invoking program in `InvocationTree::invocations`.
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    /// Messages of `Program log: ` lines emitted by this invocation.
+    pub logs: Vec<String>,
+    /// Base64 payloads of `Program data: ` lines emitted by this invocation.
+    pub data: Vec<Vec<String>>,
+    pub compute_units: Option<ComputeUnits>,
+    /// Raw bytes of the invocation's `Program return: ` line, if any.
+    pub return_data: Option<Vec<u8>>,
+    pub status: InvocationStatus,
+}
+
+/// CPI call tree of a single transaction, built from its `log_messages`.
+#[derive(Debug, Clone, Default)]
+pub struct InvocationTree {
+    /// Invocations in execution order.
+    pub invocations: Vec<ProgramInvocation>,
+    /// For every log line, the invocation that was executing when it was
+    /// written. `None` for lines outside any invocation.
+    pub line_invocations: Vec<Option<usize>>,
+    /// Whether the runtime truncated the log messages.
+    pub truncated: bool,
+}
+
+enum LogLine<'a> {
+    Invoke { program_id: &'a str, depth: usize },
+    Success { program_id: &'a str },
+    Failed { program_id: &'a str, error: &'a str },
+    Consumed { program_id: &'a str, units: ComputeUnits },
+    Return { program_id: &'a str, data: &'a str },
+    Log(&'a str),
+    Data(&'a str),
+    Truncated,
+    Other,
+}
+
+fn classify(line: &str) -> LogLine<'_> {
+    if line == "Log truncated" {
+        return LogLine::Truncated;
+    }
+    if let Some(message) = line.strip_prefix("Program log: ") {
+        return LogLine::Log(message);
+    }
+    if let Some(data) = line.strip_prefix("Program data: ") {
+        return LogLine::Data(data);
+    }
+    if let Some((program_id, data)) = line
+        .strip_prefix("Program return: ")
+        .and_then(|rest| rest.split_once(' '))
+    {
+        return LogLine::Return { program_id, data };
+    }
+    let Some(rest) = line.strip_prefix("Program ") else {
+        return LogLine::Other;
+    };
+    let Some((program_id, rest)) = rest.split_once(' ') else {
+        return LogLine::Other;
+    };
+
+    if rest == "success" {
+        return LogLine::Success { program_id };
+    }
+    if let Some(error) = rest.strip_prefix("failed: ") {
+        return LogLine::Failed { program_id, error };
+    }
+    if let Some(depth) = rest
+        .strip_prefix("invoke [")
+        .and_then(|d| d.strip_suffix(']'))
+        .and_then(|d| d.parse().ok())
+    {
+        return LogLine::Invoke { program_id, depth };
+    }
+    if let Some(units) = rest
+        .strip_prefix("consumed ")
+        .and_then(|u| u.strip_suffix(" compute units"))
+    {
+        if let Some((consumed, limit)) = units.split_once(" of ") {
+            if let (Ok(consumed), Ok(limit)) = (consumed.parse(), limit.parse()) {
+                return LogLine::Consumed {
+                    program_id,
+                    units: ComputeUnits { consumed, limit },
+                };
+            }
+        }
+    }
+    LogLine::Other
+}
+
+impl InvocationTree {
+    pub fn parse(log_messages: &[String]) -> Self {
+        let mut tree = InvocationTree::default();
+        let mut stack: Vec<usize> = Vec::new();
+        let mut instruction_index: Option<usize> = None;
+        let mut inner_count = 0;
+
+        for line in log_messages {
+            let mut current = stack.last().copied();
+
+            match classify(line) {
+                LogLine::Invoke { program_id, depth } => {
+                    // Unwind frames whose completion line is missing.
+                    stack.truncate(depth.saturating_sub(1));
+                    let parent = stack.last().copied();
+
+                    let inner_index = if parent.is_none() {
+                        instruction_index = Some(instruction_index.map_or(0, |i| i + 1));
+                        inner_count = 0;
+                        None
+                    } else {
+                        inner_count += 1;
+                        Some(inner_count - 1)
+                    };
+
+                    let id = tree.invocations.len();
+                    tree.invocations.push(ProgramInvocation {
+                        program_id: program_id.to_string(),
+                        depth,
+                        instruction_index: instruction_index.unwrap_or(0),
+                        inner_index,
+                        parent,
+                        children: Vec::new(),
+                        logs: Vec::new(),
+                        data: Vec::new(),
+                        compute_units: None,
+                        return_data: None,
+                        status: InvocationStatus::Incomplete,
+                    });
+                    if let Some(parent) = parent {
+                        tree.invocations[parent].children.push(id);
+                    }
+                    stack.push(id);
+                    current = Some(id);
+                }
+                LogLine::Success { program_id } => {
+                    if let Some(id) = tree.pop_frame(&mut stack, program_id) {
+                        tree.invocations[id].status = InvocationStatus::Success;
+                        current = Some(id);
+                    }
+                }
+                LogLine::Failed { program_id, error } => {
+                    if let Some(id) = tree.pop_frame(&mut stack, program_id) {
+                        tree.invocations[id].status = InvocationStatus::Failed(error.to_string());
+                        current = Some(id);
+                    }
+                }
+                LogLine::Consumed { program_id, units } => {
+                    if let Some(id) = current.filter(|&id| tree.invocations[id].program_id == program_id) {
+                        tree.invocations[id].compute_units = Some(units);
+                    }
+                }
+                LogLine::Return { program_id, data } => {
+                    if let Some(id) = current.filter(|&id| tree.invocations[id].program_id == program_id) {
+                        tree.invocations[id].return_data = decode_program_data(data).ok();
+                    }
+                }
+                LogLine::Log(message) => {
+                    if let Some(id) = current {
+                        tree.invocations[id].logs.push(message.to_string());
+                    }
+                }
+                LogLine::Data(data) => {
+                    if let Some(id) = current {
+                        tree.invocations[id]
+                            .data
+                            .push(data.split_whitespace().map(str::to_string).collect());
+                    }
+                }
+                LogLine::Truncated => tree.truncated = true,
+                LogLine::Other => {}
+            }
+
+            tree.line_invocations.push(current);
+        }
+
+        tree
+    }
+
+    /// Pops the innermost frame for `program_id`, discarding any frames above
+    /// it that never logged their own completion.
+    fn pop_frame(&self, stack: &mut Vec<usize>, program_id: &str) -> Option<usize> {
+        let position = stack
+            .iter()
+            .rposition(|&id| self.invocations[id].program_id == program_id)?;
+        let id = stack[position];
+        stack.truncate(position);
+        Some(id)
+    }
+
+    pub fn roots(&self) -> impl Iterator<Item = &ProgramInvocation> {
+        self.invocations.iter().filter(|i| i.parent.is_none())
+    }
+
+    pub fn invocation_for_line(&self, line: usize) -> Option<&ProgramInvocation> {
+        self.line_invocations
+            .get(line)
+            .copied()
+            .flatten()
+            .map(|id| &self.invocations[id])
+    }
+
+    pub fn parent_of(&self, invocation: &ProgramInvocation) -> Option<&ProgramInvocation> {
+        invocation.parent.map(|id| &self.invocations[id])
+    }
+
+    /// Every log line written while `program_id` was executing, with its
+    /// instruction index, depth and invoking program filled in.
+    pub fn program_logs(
+        &self,
+        log_messages: &[String],
+        program_id: &str,
+        signature: &str,
+        slot: u64,
+        block_time: Option<i64>,
+    ) -> Vec<SolanaProgramLog> {
+        log_messages
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                let invocation = self.invocation_for_line(i)?;
+                if invocation.program_id != program_id {
+                    return None;
+                }
+                Some(SolanaProgramLog {
+                    program_id: program_id.to_string(),
+                    log_index: i as u64,
+                    signature: signature.to_string(),
+                    slot,
+                    block_time,
+                    log_message: line.clone(),
+                    instruction_index: invocation.instruction_index,
+                    inner_instruction_index: invocation.inner_index,
+                    depth: invocation.depth,
+                    parent_program_id: self.parent_of(invocation).map(|p| p.program_id.clone()),
+                    logs_truncated: self.truncated,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Parses a `Program <id> consumed N of M compute units` line.
+pub fn parse_compute_units(line: &str) -> Option<(&str, ComputeUnits)> {
+    match classify(line) {
+        LogLine::Consumed { program_id, units } => Some((program_id, units)),
+        _ => None,
+    }
+}
+
+/// Parses a `Program return: <id> <base64>` line into the program ID and the
+/// raw return data.
+pub fn parse_return_data(line: &str) -> Option<anyhow::Result<(&str, Vec<u8>)>> {
+    match classify(line) {
+        LogLine::Return { program_id, data } => {
+            Some(decode_program_data(data).map(|data| (program_id, data)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn builds_cpi_tree_with_compute_and_failures() {
+        let logs = lines(&[
+            "Program ComputeBudget111111111111111111111111111111 invoke [1]",
+            "Program ComputeBudget111111111111111111111111111111 success",
+            "Program Dex111 invoke [1]",
+            "Program log: Instruction: Swap",
+            "Program Token111 invoke [2]",
+            "Program log: Instruction: Transfer",
+            "Program Token111 consumed 4645 of 180000 compute units",
+            "Program return: Token111 AQID",
+            "Program Token111 success",
+            "Program data: AAEC AwQ=",
+            "Program Token111 invoke [2]",
+            "Program Token111 failed: insufficient funds",
+            "Program Dex111 consumed 20000 of 200000 compute units",
+            "Program Dex111 failed: custom program error: 0x1",
+        ]);
+
+        let tree = InvocationTree::parse(&logs);
+        assert_eq!(tree.invocations.len(), 4);
+        assert_eq!(tree.roots().count(), 2);
+
+        let dex = &tree.invocations[1];
+        assert_eq!(dex.instruction_index, 1);
+        assert_eq!(dex.children, vec![2, 3]);
+        assert_eq!(dex.logs, vec!["Instruction: Swap"]);
+        assert_eq!(dex.data, vec![vec!["AAEC".to_string(), "AwQ=".to_string()]]);
+        assert_eq!(dex.compute_units.map(|u| u.consumed), Some(20000));
+        assert_eq!(
+            dex.status,
+            InvocationStatus::Failed("custom program error: 0x1".to_string())
+        );
+
+        let transfer = &tree.invocations[2];
+        assert_eq!((transfer.depth, transfer.parent), (2, Some(1)));
+        assert_eq!(transfer.inner_index, Some(0));
+        assert_eq!(transfer.compute_units.map(|u| u.limit), Some(180000));
+        assert_eq!(transfer.status, InvocationStatus::Success);
+        assert_eq!(tree.invocations[3].inner_index, Some(1));
+
+        let token_logs = tree.program_logs(&logs, "Token111", "sig", 7, None);
+        assert_eq!(transfer.return_data, Some(vec![1, 2, 3]));
+        assert_eq!(token_logs.len(), 7);
+        assert!(token_logs.iter().all(|l| l.instruction_index == 1
+            && l.depth == 2
+            && l.parent_program_id.as_deref() == Some("Dex111")));
+        assert_eq!(token_logs[1].log_index, 5);
+    }
+
+    #[test]
+    fn recovers_from_truncated_logs() {
+        let logs = lines(&[
+            "Program A invoke [1]",
+            "Program B invoke [2]",
+            "Log truncated",
+            "Program C invoke [1]",
+            "Program C success",
+        ]);
+
+        let tree = InvocationTree::parse(&logs);
+        assert!(tree.truncated);
+        assert!(tree
+            .program_logs(&logs, "C", "sig", 1, None)
+            .iter()
+            .all(|l| l.logs_truncated));
+        assert_eq!(tree.invocations[1].status, InvocationStatus::Incomplete);
+        assert_eq!(tree.invocations[2].parent, None);
+        assert_eq!(tree.invocations[2].instruction_index, 1);
+    }
+}
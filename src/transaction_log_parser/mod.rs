@@ -1,4 +1,20 @@
+pub mod borsh;
+pub mod codama;
+pub mod events;
+pub mod idl;
+pub mod idl_account;
+pub mod instruction;
+pub mod invocation;
+pub mod native;
 pub mod parser;
+pub mod patterns;
+pub mod registry;
 
+pub use events::*;
+pub use idl::*;
+pub use idl_account::*;
+pub use instruction::*;
+pub use invocation::*;
 pub use parser::*;
-
+pub use patterns::*;
+pub use registry::*;
@@ -0,0 +1,131 @@
+use super::{SYSTEM_PROGRAM_ID, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use crate::transaction_log_parser::DecodedInstruction;
+
+/// Typed view of the System and SPL Token instructions that move value or
+/// change control of an account. Token `mint` is `None` for SOL transfers and
+/// for unchecked token transfers, whose instruction does not name the mint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NativeAction {
+    Transfer {
+        program_id: String,
+        source: String,
+        destination: String,
+        authority: String,
+        mint: Option<String>,
+        amount: u64,
+    },
+    Mint {
+        program_id: String,
+        mint: String,
+        account: String,
+        authority: String,
+        amount: u64,
+    },
+    Burn {
+        program_id: String,
+        mint: String,
+        account: String,
+        authority: String,
+        amount: u64,
+    },
+    CreateAccount {
+        funder: String,
+        account: String,
+        owner: String,
+        lamports: u64,
+        space: u64,
+    },
+    SetAuthority {
+        program_id: String,
+        account: String,
+        authority: String,
+        authority_type: String,
+        new_authority: Option<String>,
+    },
+}
+
+impl NativeAction {
+    /// Maps a decoded native instruction to its action. Returns `None` for
+    /// other programs and instructions, or when an expected account or
+    /// argument is missing.
+    pub fn from_instruction(decoded: &DecodedInstruction) -> Option<Self> {
+        let program_id = decoded.program_id.clone();
+        let account = |name: &str| {
+            decoded
+                .accounts
+                .iter()
+                .find(|a| a.name == name)
+                .map(|a| a.pubkey.clone())
+        };
+        let argument = |name: &str| {
+            decoded
+                .arguments
+                .iter()
+                .find(|a| a.name == name)
+                .map(|a| &a.value)
+        };
+        // Native decoders render u64 arguments as decimal strings.
+        let amount = |name: &str| argument(name)?.as_str()?.parse().ok();
+
+        match (decoded.program_id.as_str(), decoded.name.as_str()) {
+            (SYSTEM_PROGRAM_ID, "transfer") => Some(Self::Transfer {
+                program_id,
+                source: account("source")?,
+                destination: account("destination")?,
+                authority: account("source")?,
+                mint: None,
+                amount: amount("lamports")?,
+            }),
+            (SYSTEM_PROGRAM_ID, "transferWithSeed") => Some(Self::Transfer {
+                program_id,
+                source: account("source")?,
+                destination: account("destination")?,
+                authority: account("sourceBase")?,
+                mint: None,
+                amount: amount("lamports")?,
+            }),
+            (SYSTEM_PROGRAM_ID, "createAccount" | "createAccountWithSeed") => {
+                Some(Self::CreateAccount {
+                    funder: account("source")?,
+                    account: account("newAccount")?,
+                    owner: argument("owner")?.as_str()?.to_string(),
+                    lamports: amount("lamports")?,
+                    space: amount("space")?,
+                })
+            }
+            (TOKEN_PROGRAM_ID | TOKEN_2022_PROGRAM_ID, name) => match name {
+                "transfer" | "transferChecked" | "transferCheckedWithFee" => Some(Self::Transfer {
+                    program_id,
+                    source: account("source")?,
+                    destination: account("destination")?,
+                    authority: account("authority")?,
+                    mint: account("mint"),
+                    amount: amount("amount")?,
+                }),
+                "mintTo" | "mintToChecked" => Some(Self::Mint {
+                    program_id,
+                    mint: account("mint")?,
+                    account: account("account")?,
+                    authority: account("mintAuthority")?,
+                    amount: amount("amount")?,
+                }),
+                "burn" | "burnChecked" => Some(Self::Burn {
+                    program_id,
+                    mint: account("mint")?,
+                    account: account("account")?,
+                    authority: account("authority")?,
+                    amount: amount("amount")?,
+                }),
+                "setAuthority" => Some(Self::SetAuthority {
+                    program_id,
+                    account: account("account")?,
+                    authority: account("authority")?,
+                    authority_type: argument("authorityType")?.as_str()?.to_string(),
+                    new_authority: argument("newAuthority")?.as_str().map(str::to_string),
+                }),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
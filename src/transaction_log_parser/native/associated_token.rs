@@ -0,0 +1,32 @@
+use super::{AccountSpec, Args};
+
+const CREATE: &[AccountSpec] = &[
+    ("source", true, true),
+    ("account", true, false),
+    ("wallet", false, false),
+    ("mint", false, false),
+    ("systemProgram", false, false),
+    ("tokenProgram", false, false),
+];
+const RECOVER_NESTED: &[AccountSpec] = &[
+    ("nestedSource", true, false),
+    ("nestedMint", false, false),
+    ("destination", true, false),
+    ("nestedOwner", false, false),
+    ("ownerMint", false, false),
+    ("wallet", true, true),
+    ("tokenProgram", false, false),
+];
+
+/// Legacy clients send `Create` with empty instruction data.
+pub(super) fn decode(args: &mut Args) -> anyhow::Result<(&'static str, &'static [AccountSpec])> {
+    if args.is_empty() {
+        return Ok(("create", CREATE));
+    }
+    Ok(match args.tag_u8()? {
+        0 => ("create", CREATE),
+        1 => ("createIdempotent", CREATE),
+        2 => ("recoverNested", RECOVER_NESTED),
+        tag => anyhow::bail!("Unknown Associated Token Account instruction: {}", tag),
+    })
+}
@@ -0,0 +1,359 @@
+//! Instruction decoders for native and SPL programs that publish no IDL.
+
+mod actions;
+mod associated_token;
+mod system;
+mod token;
+
+use crate::transaction_log_parser::borsh::BorshReader;
+use crate::transaction_log_parser::{Argument, DecodedInstruction, DecodedInstructionAccount};
+use anyhow::Context;
+use serde_json::Value;
+
+pub use actions::NativeAction;
+
+pub const SYSTEM_PROGRAM_ID: &str = "11111111111111111111111111111111";
+pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PVnBqCXEpPxuEb";
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+
+pub fn is_native_program(program_id: &str) -> bool {
+    [
+        SYSTEM_PROGRAM_ID,
+        TOKEN_PROGRAM_ID,
+        TOKEN_2022_PROGRAM_ID,
+        ASSOCIATED_TOKEN_PROGRAM_ID,
+    ]
+    .contains(&program_id)
+}
+
+/// Decodes an instruction of the System, SPL Token, Token-2022 or Associated
+/// Token Account program. Returns `Ok(None)` for any other program.
+pub fn decode_native_instruction(
+    program_id: &str,
+    data: &[u8],
+    accounts: &[String],
+) -> anyhow::Result<Option<DecodedInstruction>> {
+    let mut args = Args::new(data);
+    let (name, account_specs) = match program_id {
+        SYSTEM_PROGRAM_ID => system::decode(&mut args),
+        TOKEN_PROGRAM_ID => token::decode(&mut args, false),
+        TOKEN_2022_PROGRAM_ID => token::decode(&mut args, true),
+        ASSOCIATED_TOKEN_PROGRAM_ID => associated_token::decode(&mut args),
+        _ => return Ok(None),
+    }
+    .with_context(|| format!("Failed to decode instruction for program {}", program_id))?;
+
+    Ok(Some(DecodedInstruction {
+        program_id: program_id.to_string(),
+        name: name.to_string(),
+        arguments: args.arguments,
+        accounts: name_accounts(accounts, account_specs),
+    }))
+}
+
+/// Name, writable, signer.
+type AccountSpec = (&'static str, bool, bool);
+
+fn name_accounts(accounts: &[String], specs: &[AccountSpec]) -> Vec<DecodedInstructionAccount> {
+    accounts
+        .iter()
+        .enumerate()
+        .map(|(i, pubkey)| match specs.get(i) {
+            Some(&(name, writable, signer)) => DecodedInstructionAccount {
+                name: name.to_string(),
+                pubkey: pubkey.clone(),
+                writable,
+                signer,
+            },
+            None => DecodedInstructionAccount {
+                name: format!("remaining_{}", i - specs.len()),
+                pubkey: pubkey.clone(),
+                writable: false,
+                signer: false,
+            },
+        })
+        .collect()
+}
+
+/// Reads fixed-layout instruction arguments in order.
+struct Args<'a> {
+    reader: BorshReader<'a>,
+    arguments: Vec<Argument>,
+}
+
+impl<'a> Args<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            reader: BorshReader::new(data),
+            arguments: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, name: &str, arg_type: &str, value: Value) -> &mut Self {
+        self.arguments.push(Argument {
+            name: name.to_string(),
+            arg_type: arg_type.to_string(),
+            value,
+            indexed: false,
+        });
+        self
+    }
+
+    fn tag_u8(&mut self) -> anyhow::Result<u8> {
+        self.reader.read_u8()
+    }
+
+    fn tag_u32(&mut self) -> anyhow::Result<u32> {
+        self.reader.read_u32()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.reader.remaining() == 0
+    }
+
+    fn u8(&mut self, name: &str) -> anyhow::Result<&mut Self> {
+        let value = self.reader.read_u8()?;
+        Ok(self.push(name, "u8", value.into()))
+    }
+
+    fn u16(&mut self, name: &str) -> anyhow::Result<&mut Self> {
+        let value = u16::from_le_bytes(self.reader.read_array()?);
+        Ok(self.push(name, "u16", value.into()))
+    }
+
+    fn i16(&mut self, name: &str) -> anyhow::Result<&mut Self> {
+        let value = i16::from_le_bytes(self.reader.read_array()?);
+        Ok(self.push(name, "i16", value.into()))
+    }
+
+    fn u64(&mut self, name: &str) -> anyhow::Result<&mut Self> {
+        let value = self.reader.read_u64()?;
+        Ok(self.push(name, "u64", value.to_string().into()))
+    }
+
+    fn pubkey(&mut self, name: &str) -> anyhow::Result<&mut Self> {
+        let value = self.reader.read_pubkey()?.to_string();
+        Ok(self.push(name, "pubkey", value.into()))
+    }
+
+    /// SPL `COption<Pubkey>` as packed in instruction data: a one-byte tag,
+    /// followed by the key only when present.
+    fn coption_pubkey(&mut self, name: &str) -> anyhow::Result<&mut Self> {
+        let value = match self.reader.read_u8()? {
+            0 => Value::Null,
+            1 => self.reader.read_pubkey()?.to_string().into(),
+            tag => anyhow::bail!("Invalid COption tag: {}", tag),
+        };
+        Ok(self.push(name, "option<pubkey>", value))
+    }
+
+    /// Token-2022 `OptionalNonZeroPubkey`: 32 bytes, all zero when unset.
+    fn nonzero_pubkey(&mut self, name: &str) -> anyhow::Result<&mut Self> {
+        let key = self.reader.read_pubkey()?;
+        let value = if key == Default::default() {
+            Value::Null
+        } else {
+            key.to_string().into()
+        };
+        Ok(self.push(name, "option<pubkey>", value))
+    }
+
+    /// Bincode string, as used by System Program instructions: u64 length
+    /// prefix followed by UTF-8 bytes.
+    fn bincode_string(&mut self, name: &str) -> anyhow::Result<&mut Self> {
+        let len = self.reader.read_u64()? as usize;
+        let value = String::from_utf8(self.reader.read_bytes(len)?.to_vec())
+            .context("Invalid UTF-8 string")?;
+        Ok(self.push(name, "string", value.into()))
+    }
+
+    /// Unprefixed UTF-8 string filling the rest of the instruction data.
+    fn rest_string(&mut self, name: &str) -> anyhow::Result<&mut Self> {
+        let bytes = self.reader.read_bytes(self.reader.remaining())?;
+        let value = String::from_utf8(bytes.to_vec()).context("Invalid UTF-8 string")?;
+        Ok(self.push(name, "string", value.into()))
+    }
+
+    fn enum_u8(&mut self, name: &str, variants: &[&str]) -> anyhow::Result<&mut Self> {
+        let tag = self.reader.read_u8()?;
+        let variant = variants
+            .get(tag as usize)
+            .with_context(|| format!("Invalid {} value: {}", name, tag))?;
+        Ok(self.push(name, "string", (*variant).into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn keys(n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("Key{}", i)).collect()
+    }
+
+    fn argument<'a>(decoded: &'a DecodedInstruction, name: &str) -> &'a Value {
+        &decoded
+            .arguments
+            .iter()
+            .find(|a| a.name == name)
+            .unwrap_or_else(|| panic!("missing argument {}", name))
+            .value
+    }
+
+    #[test]
+    fn decodes_system_create_account_and_transfer() {
+        let owner = Pubkey::new_from_array([7; 32]);
+        let mut data = 0u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&1_000u64.to_le_bytes());
+        data.extend_from_slice(&165u64.to_le_bytes());
+        data.extend_from_slice(owner.as_ref());
+
+        let decoded = decode_native_instruction(SYSTEM_PROGRAM_ID, &data, &keys(2))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.name, "createAccount");
+        assert_eq!(argument(&decoded, "space"), &Value::from("165"));
+        assert_eq!(argument(&decoded, "owner"), &Value::from(owner.to_string()));
+        assert_eq!(decoded.accounts[1].name, "newAccount");
+        assert!(decoded.accounts[1].signer);
+
+        let mut data = 2u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&5u64.to_le_bytes());
+        let decoded = decode_native_instruction(SYSTEM_PROGRAM_ID, &data, &keys(2))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.name, "transfer");
+        assert_eq!(argument(&decoded, "lamports"), &Value::from("5"));
+    }
+
+    #[test]
+    fn decodes_token_transfers_and_authority_changes() {
+        let mut data = vec![12];
+        data.extend_from_slice(&250u64.to_le_bytes());
+        data.push(6);
+        let decoded = decode_native_instruction(TOKEN_PROGRAM_ID, &data, &keys(5))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.name, "transferChecked");
+        assert_eq!(argument(&decoded, "amount"), &Value::from("250"));
+        assert_eq!(argument(&decoded, "decimals"), &Value::from(6));
+        let names: Vec<_> = decoded.accounts.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["source", "mint", "destination", "authority", "remaining_0"]);
+
+        let decoded = decode_native_instruction(TOKEN_PROGRAM_ID, &[6, 2, 0], &keys(2))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.name, "setAuthority");
+        assert_eq!(argument(&decoded, "authorityType"), &Value::from("accountOwner"));
+        assert_eq!(argument(&decoded, "newAuthority"), &Value::Null);
+    }
+
+    #[test]
+    fn decodes_token_2022_transfer_fee_extension() {
+        let mut data = vec![26, 1];
+        data.extend_from_slice(&1_000u64.to_le_bytes());
+        data.push(9);
+        data.extend_from_slice(&3u64.to_le_bytes());
+        let decoded = decode_native_instruction(TOKEN_2022_PROGRAM_ID, &data, &keys(4))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.name, "transferCheckedWithFee");
+        assert_eq!(argument(&decoded, "fee"), &Value::from("3"));
+
+        // Token-2022 extensions are not valid for the original token program.
+        assert!(decode_native_instruction(TOKEN_PROGRAM_ID, &data, &keys(4)).is_err());
+    }
+
+    #[test]
+    fn decodes_associated_token_account_creation() {
+        let decoded = decode_native_instruction(ASSOCIATED_TOKEN_PROGRAM_ID, &[], &keys(6))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.name, "create");
+        assert_eq!(decoded.accounts[2].name, "wallet");
+
+        let decoded = decode_native_instruction(ASSOCIATED_TOKEN_PROGRAM_ID, &[1], &keys(6))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.name, "createIdempotent");
+    }
+
+    #[test]
+    fn maps_value_moving_instructions_to_native_actions() {
+        let owner = Pubkey::new_from_array([7; 32]);
+        let mut data = 0u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&1_000u64.to_le_bytes());
+        data.extend_from_slice(&165u64.to_le_bytes());
+        data.extend_from_slice(owner.as_ref());
+        let decoded = decode_native_instruction(SYSTEM_PROGRAM_ID, &data, &keys(2))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            NativeAction::from_instruction(&decoded),
+            Some(NativeAction::CreateAccount {
+                funder: "Key0".to_string(),
+                account: "Key1".to_string(),
+                owner: owner.to_string(),
+                lamports: 1_000,
+                space: 165,
+            })
+        );
+
+        let mut data = vec![12];
+        data.extend_from_slice(&250u64.to_le_bytes());
+        data.push(6);
+        let decoded = decode_native_instruction(TOKEN_2022_PROGRAM_ID, &data, &keys(4))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            NativeAction::from_instruction(&decoded),
+            Some(NativeAction::Transfer {
+                program_id: TOKEN_2022_PROGRAM_ID.to_string(),
+                source: "Key0".to_string(),
+                destination: "Key2".to_string(),
+                authority: "Key3".to_string(),
+                mint: Some("Key1".to_string()),
+                amount: 250,
+            })
+        );
+
+        let mut data = vec![8];
+        data.extend_from_slice(&40u64.to_le_bytes());
+        let decoded = decode_native_instruction(TOKEN_PROGRAM_ID, &data, &keys(3))
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            NativeAction::from_instruction(&decoded),
+            Some(NativeAction::Burn { amount: 40, ref mint, .. }) if mint == "Key1"
+        ));
+
+        let mut data = vec![6, 0, 1];
+        data.extend_from_slice(owner.as_ref());
+        let decoded = decode_native_instruction(TOKEN_PROGRAM_ID, &data, &keys(2))
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.arguments[0].arg_type, "string");
+        assert_eq!(
+            NativeAction::from_instruction(&decoded),
+            Some(NativeAction::SetAuthority {
+                program_id: TOKEN_PROGRAM_ID.to_string(),
+                account: "Key0".to_string(),
+                authority: "Key1".to_string(),
+                authority_type: "mintTokens".to_string(),
+                new_authority: Some(owner.to_string()),
+            })
+        );
+
+        let decoded = decode_native_instruction(TOKEN_PROGRAM_ID, &[9], &keys(3))
+            .unwrap()
+            .unwrap();
+        assert_eq!(NativeAction::from_instruction(&decoded), None);
+    }
+
+    #[test]
+    fn ignores_other_programs() {
+        assert!(decode_native_instruction("Other", &[0], &[]).unwrap().is_none());
+    }
+}
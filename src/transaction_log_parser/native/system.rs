@@ -0,0 +1,100 @@
+use super::{AccountSpec, Args};
+
+const FUNDED_ACCOUNT: &[AccountSpec] = &[("source", true, true), ("newAccount", true, true)];
+const FUNDED_ACCOUNT_WITH_SEED: &[AccountSpec] = &[
+    ("source", true, true),
+    ("newAccount", true, false),
+    ("base", false, true),
+];
+const ACCOUNT: &[AccountSpec] = &[("account", true, true)];
+const ACCOUNT_WITH_SEED: &[AccountSpec] = &[("account", true, false), ("base", false, true)];
+const TRANSFER: &[AccountSpec] = &[("source", true, true), ("destination", true, false)];
+const TRANSFER_WITH_SEED: &[AccountSpec] = &[
+    ("source", true, false),
+    ("sourceBase", false, true),
+    ("destination", true, false),
+];
+const ADVANCE_NONCE: &[AccountSpec] = &[
+    ("nonceAccount", true, false),
+    ("recentBlockhashesSysvar", false, false),
+    ("nonceAuthority", false, true),
+];
+const WITHDRAW_NONCE: &[AccountSpec] = &[
+    ("nonceAccount", true, false),
+    ("destination", true, false),
+    ("recentBlockhashesSysvar", false, false),
+    ("rentSysvar", false, false),
+    ("nonceAuthority", false, true),
+];
+const INITIALIZE_NONCE: &[AccountSpec] = &[
+    ("nonceAccount", true, false),
+    ("recentBlockhashesSysvar", false, false),
+    ("rentSysvar", false, false),
+];
+const AUTHORIZE_NONCE: &[AccountSpec] =
+    &[("nonceAccount", true, false), ("nonceAuthority", false, true)];
+const UPGRADE_NONCE: &[AccountSpec] = &[("nonceAccount", true, false)];
+
+/// System Program instructions are bincode-encoded with a u32 variant tag.
+pub(super) fn decode(args: &mut Args) -> anyhow::Result<(&'static str, &'static [AccountSpec])> {
+    Ok(match args.tag_u32()? {
+        0 => {
+            args.u64("lamports")?.u64("space")?.pubkey("owner")?;
+            ("createAccount", FUNDED_ACCOUNT)
+        }
+        1 => {
+            args.pubkey("owner")?;
+            ("assign", ACCOUNT)
+        }
+        2 => {
+            args.u64("lamports")?;
+            ("transfer", TRANSFER)
+        }
+        3 => {
+            args.pubkey("base")?
+                .bincode_string("seed")?
+                .u64("lamports")?
+                .u64("space")?
+                .pubkey("owner")?;
+            ("createAccountWithSeed", FUNDED_ACCOUNT_WITH_SEED)
+        }
+        4 => ("advanceNonce", ADVANCE_NONCE),
+        5 => {
+            args.u64("lamports")?;
+            ("withdrawFromNonce", WITHDRAW_NONCE)
+        }
+        6 => {
+            args.pubkey("nonceAuthority")?;
+            ("initializeNonce", INITIALIZE_NONCE)
+        }
+        7 => {
+            args.pubkey("newAuthorized")?;
+            ("authorizeNonce", AUTHORIZE_NONCE)
+        }
+        8 => {
+            args.u64("space")?;
+            ("allocate", ACCOUNT)
+        }
+        9 => {
+            args.pubkey("base")?
+                .bincode_string("seed")?
+                .u64("space")?
+                .pubkey("owner")?;
+            ("allocateWithSeed", ACCOUNT_WITH_SEED)
+        }
+        10 => {
+            args.pubkey("base")?
+                .bincode_string("seed")?
+                .pubkey("owner")?;
+            ("assignWithSeed", ACCOUNT_WITH_SEED)
+        }
+        11 => {
+            args.u64("lamports")?
+                .bincode_string("sourceSeed")?
+                .pubkey("sourceOwner")?;
+            ("transferWithSeed", TRANSFER_WITH_SEED)
+        }
+        12 => ("upgradeNonce", UPGRADE_NONCE),
+        tag => anyhow::bail!("Unknown System Program instruction: {}", tag),
+    })
+}
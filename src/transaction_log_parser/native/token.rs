@@ -0,0 +1,316 @@
+use super::{AccountSpec, Args};
+
+const AUTHORITY_TYPES: &[&str] = &[
+    "mintTokens",
+    "freezeAccount",
+    "accountOwner",
+    "closeAccount",
+    "transferFeeConfig",
+    "withheldWithdraw",
+    "closeMint",
+    "interestRate",
+    "permanentDelegate",
+    "confidentialTransferMint",
+    "transferHookProgramId",
+    "confidentialTransferFeeConfig",
+    "metadataPointer",
+    "groupPointer",
+    "groupMemberPointer",
+];
+const ACCOUNT_STATES: &[&str] = &["uninitialized", "initialized", "frozen"];
+
+const INITIALIZE_MINT: &[AccountSpec] = &[("mint", true, false), ("rentSysvar", false, false)];
+const INITIALIZE_ACCOUNT: &[AccountSpec] = &[
+    ("account", true, false),
+    ("mint", false, false),
+    ("owner", false, false),
+    ("rentSysvar", false, false),
+];
+const INITIALIZE_ACCOUNT_2: &[AccountSpec] = &[
+    ("account", true, false),
+    ("mint", false, false),
+    ("rentSysvar", false, false),
+];
+const INITIALIZE_ACCOUNT_3: &[AccountSpec] = &[("account", true, false), ("mint", false, false)];
+const INITIALIZE_MULTISIG: &[AccountSpec] =
+    &[("multisig", true, false), ("rentSysvar", false, false)];
+const MULTISIG: &[AccountSpec] = &[("multisig", true, false)];
+const TRANSFER: &[AccountSpec] = &[
+    ("source", true, false),
+    ("destination", true, false),
+    ("authority", false, true),
+];
+const APPROVE: &[AccountSpec] = &[
+    ("source", true, false),
+    ("delegate", false, false),
+    ("owner", false, true),
+];
+const REVOKE: &[AccountSpec] = &[("source", true, false), ("owner", false, true)];
+const SET_AUTHORITY: &[AccountSpec] = &[("account", true, false), ("authority", false, true)];
+const MINT_TO: &[AccountSpec] = &[
+    ("mint", true, false),
+    ("account", true, false),
+    ("mintAuthority", false, true),
+];
+const BURN: &[AccountSpec] = &[
+    ("account", true, false),
+    ("mint", true, false),
+    ("authority", false, true),
+];
+const CLOSE_ACCOUNT: &[AccountSpec] = &[
+    ("account", true, false),
+    ("destination", true, false),
+    ("owner", false, true),
+];
+const FREEZE: &[AccountSpec] = &[
+    ("account", true, false),
+    ("mint", false, false),
+    ("freezeAuthority", false, true),
+];
+const TRANSFER_CHECKED: &[AccountSpec] = &[
+    ("source", true, false),
+    ("mint", false, false),
+    ("destination", true, false),
+    ("authority", false, true),
+];
+const APPROVE_CHECKED: &[AccountSpec] = &[
+    ("source", true, false),
+    ("mint", false, false),
+    ("delegate", false, false),
+    ("owner", false, true),
+];
+const ACCOUNT: &[AccountSpec] = &[("account", true, false)];
+const ACCOUNT_WITH_OWNER: &[AccountSpec] = &[("account", true, false), ("owner", false, true)];
+const MINT: &[AccountSpec] = &[("mint", true, false)];
+const MINT_WITH_AUTHORITY: &[AccountSpec] = &[("mint", true, false), ("authority", false, true)];
+const READ_MINT: &[AccountSpec] = &[("mint", false, false)];
+const WITHDRAW_WITHHELD: &[AccountSpec] = &[
+    ("mint", true, false),
+    ("destination", true, false),
+    ("authority", false, true),
+];
+const HARVEST_WITHHELD: &[AccountSpec] = &[("mint", true, false)];
+const REALLOCATE: &[AccountSpec] = &[
+    ("account", true, false),
+    ("payer", true, true),
+    ("systemProgram", false, false),
+    ("owner", false, true),
+];
+const CREATE_NATIVE_MINT: &[AccountSpec] = &[
+    ("payer", true, true),
+    ("nativeMint", true, false),
+    ("systemProgram", false, false),
+];
+const WITHDRAW_EXCESS_LAMPORTS: &[AccountSpec] = &[
+    ("source", true, false),
+    ("destination", true, false),
+    ("authority", false, true),
+];
+const UNPARSED: &[AccountSpec] = &[];
+
+/// SPL Token instructions carry a u8 tag. Token-2022 accepts the same
+/// instructions plus extension instructions from tag 25 on, most of which
+/// carry a second u8 sub-instruction tag.
+pub(super) fn decode(
+    args: &mut Args,
+    token_2022: bool,
+) -> anyhow::Result<(&'static str, &'static [AccountSpec])> {
+    let tag = args.tag_u8()?;
+    Ok(match tag {
+        0 => {
+            args.u8("decimals")?
+                .pubkey("mintAuthority")?
+                .coption_pubkey("freezeAuthority")?;
+            ("initializeMint", INITIALIZE_MINT)
+        }
+        1 => ("initializeAccount", INITIALIZE_ACCOUNT),
+        2 => {
+            args.u8("m")?;
+            ("initializeMultisig", INITIALIZE_MULTISIG)
+        }
+        3 => {
+            args.u64("amount")?;
+            ("transfer", TRANSFER)
+        }
+        4 => {
+            args.u64("amount")?;
+            ("approve", APPROVE)
+        }
+        5 => ("revoke", REVOKE),
+        6 => {
+            args.enum_u8("authorityType", AUTHORITY_TYPES)?
+                .coption_pubkey("newAuthority")?;
+            ("setAuthority", SET_AUTHORITY)
+        }
+        7 => {
+            args.u64("amount")?;
+            ("mintTo", MINT_TO)
+        }
+        8 => {
+            args.u64("amount")?;
+            ("burn", BURN)
+        }
+        9 => ("closeAccount", CLOSE_ACCOUNT),
+        10 => ("freezeAccount", FREEZE),
+        11 => ("thawAccount", FREEZE),
+        12 => {
+            args.u64("amount")?.u8("decimals")?;
+            ("transferChecked", TRANSFER_CHECKED)
+        }
+        13 => {
+            args.u64("amount")?.u8("decimals")?;
+            ("approveChecked", APPROVE_CHECKED)
+        }
+        14 => {
+            args.u64("amount")?.u8("decimals")?;
+            ("mintToChecked", MINT_TO)
+        }
+        15 => {
+            args.u64("amount")?.u8("decimals")?;
+            ("burnChecked", BURN)
+        }
+        16 => {
+            args.pubkey("owner")?;
+            ("initializeAccount2", INITIALIZE_ACCOUNT_2)
+        }
+        17 => ("syncNative", ACCOUNT),
+        18 => {
+            args.pubkey("owner")?;
+            ("initializeAccount3", INITIALIZE_ACCOUNT_3)
+        }
+        19 => {
+            args.u8("m")?;
+            ("initializeMultisig2", MULTISIG)
+        }
+        20 => {
+            args.u8("decimals")?
+                .pubkey("mintAuthority")?
+                .coption_pubkey("freezeAuthority")?;
+            ("initializeMint2", MINT)
+        }
+        21 => ("getAccountDataSize", READ_MINT),
+        22 => ("initializeImmutableOwner", ACCOUNT),
+        23 => {
+            args.u64("amount")?;
+            ("amountToUiAmount", READ_MINT)
+        }
+        24 => {
+            args.rest_string("uiAmount")?;
+            ("uiAmountToAmount", READ_MINT)
+        }
+        _ if token_2022 => decode_extension(args, tag)?,
+        _ => anyhow::bail!("Unknown SPL Token instruction: {}", tag),
+    })
+}
+
+fn decode_extension(
+    args: &mut Args,
+    tag: u8,
+) -> anyhow::Result<(&'static str, &'static [AccountSpec])> {
+    Ok(match tag {
+        25 => {
+            args.coption_pubkey("closeAuthority")?;
+            ("initializeMintCloseAuthority", MINT)
+        }
+        26 => match args.tag_u8()? {
+            0 => {
+                args.coption_pubkey("transferFeeConfigAuthority")?
+                    .coption_pubkey("withdrawWithheldAuthority")?
+                    .u16("transferFeeBasisPoints")?
+                    .u64("maximumFee")?;
+                ("initializeTransferFeeConfig", MINT)
+            }
+            1 => {
+                args.u64("amount")?.u8("decimals")?.u64("fee")?;
+                ("transferCheckedWithFee", TRANSFER_CHECKED)
+            }
+            2 => ("withdrawWithheldTokensFromMint", WITHDRAW_WITHHELD),
+            3 => {
+                args.u8("numTokenAccounts")?;
+                ("withdrawWithheldTokensFromAccounts", WITHDRAW_WITHHELD)
+            }
+            4 => ("harvestWithheldTokensToMint", HARVEST_WITHHELD),
+            5 => {
+                args.u16("transferFeeBasisPoints")?.u64("maximumFee")?;
+                ("setTransferFee", MINT_WITH_AUTHORITY)
+            }
+            sub => anyhow::bail!("Unknown transfer fee instruction: {}", sub),
+        },
+        27 => ("confidentialTransferExtension", UNPARSED),
+        28 => match args.tag_u8()? {
+            0 => {
+                args.enum_u8("accountState", ACCOUNT_STATES)?;
+                ("initializeDefaultAccountState", MINT)
+            }
+            1 => {
+                args.enum_u8("accountState", ACCOUNT_STATES)?;
+                ("updateDefaultAccountState", MINT_WITH_AUTHORITY)
+            }
+            sub => anyhow::bail!("Unknown default account state instruction: {}", sub),
+        },
+        29 => ("reallocate", REALLOCATE),
+        30 => match args.tag_u8()? {
+            0 => ("enableRequiredMemoTransfers", ACCOUNT_WITH_OWNER),
+            1 => ("disableRequiredMemoTransfers", ACCOUNT_WITH_OWNER),
+            sub => anyhow::bail!("Unknown memo transfer instruction: {}", sub),
+        },
+        31 => ("createNativeMint", CREATE_NATIVE_MINT),
+        32 => ("initializeNonTransferableMint", MINT),
+        33 => match args.tag_u8()? {
+            0 => {
+                args.nonzero_pubkey("rateAuthority")?.i16("rate")?;
+                ("initializeInterestBearingConfig", MINT)
+            }
+            1 => {
+                args.i16("rate")?;
+                ("updateInterestBearingConfigRate", MINT_WITH_AUTHORITY)
+            }
+            sub => anyhow::bail!("Unknown interest bearing instruction: {}", sub),
+        },
+        34 => match args.tag_u8()? {
+            0 => ("enableCpiGuard", ACCOUNT_WITH_OWNER),
+            1 => ("disableCpiGuard", ACCOUNT_WITH_OWNER),
+            sub => anyhow::bail!("Unknown CPI guard instruction: {}", sub),
+        },
+        35 => {
+            args.pubkey("delegate")?;
+            ("initializePermanentDelegate", MINT)
+        }
+        36 => match args.tag_u8()? {
+            0 => {
+                args.nonzero_pubkey("authority")?.nonzero_pubkey("programId")?;
+                ("initializeTransferHook", MINT)
+            }
+            1 => {
+                args.nonzero_pubkey("programId")?;
+                ("updateTransferHook", MINT_WITH_AUTHORITY)
+            }
+            sub => anyhow::bail!("Unknown transfer hook instruction: {}", sub),
+        },
+        37 => ("confidentialTransferFeeExtension", UNPARSED),
+        38 => ("withdrawExcessLamports", WITHDRAW_EXCESS_LAMPORTS),
+        39..=41 => {
+            let (initialize, update, address) = match tag {
+                39 => ("initializeMetadataPointer", "updateMetadataPointer", "metadataAddress"),
+                40 => ("initializeGroupPointer", "updateGroupPointer", "groupAddress"),
+                _ => (
+                    "initializeGroupMemberPointer",
+                    "updateGroupMemberPointer",
+                    "memberAddress",
+                ),
+            };
+            match args.tag_u8()? {
+                0 => {
+                    args.nonzero_pubkey("authority")?.nonzero_pubkey(address)?;
+                    (initialize, MINT)
+                }
+                1 => {
+                    args.nonzero_pubkey(address)?;
+                    (update, MINT_WITH_AUTHORITY)
+                }
+                sub => anyhow::bail!("Unknown pointer instruction: {}", sub),
+            }
+        }
+        _ => anyhow::bail!("Unknown Token-2022 instruction: {}", tag),
+    })
+}
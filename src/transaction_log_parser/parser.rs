@@ -1,6 +1,15 @@
 use crate::clients::solana::SolanaProgramLog;
+use crate::transaction_log_parser::borsh::{decode_account, decode_fields, decode_type, BorshReader};
+use crate::transaction_log_parser::events::decode_program_data;
+use crate::transaction_log_parser::invocation::{parse_compute_units, parse_return_data};
+use crate::transaction_log_parser::idl::IdlStore;
+use crate::transaction_log_parser::instruction::InstructionWithContext;
+use crate::transaction_log_parser::native::decode_native_instruction;
+use crate::transaction_log_parser::registry::LogForm;
+use anyhow::Context;
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::sync::Arc;
 use tracing::debug;
 
 #[derive(Debug, Clone)]
@@ -10,6 +19,9 @@ pub struct DecodedLog {
     pub arguments: Vec<Argument>,
     pub event_name: String,
     pub output_data: HashMap<String, serde_json::Value>,
+    /// The transaction's log messages were truncated by the validator, so
+    /// events written after the cut-off are missing.
+    pub truncated: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -20,6 +32,22 @@ pub struct Argument {
     pub indexed: bool,
 }
 
+#[derive(Debug, Clone)]
+pub struct DecodedInstruction {
+    pub program_id: String,
+    pub name: String,
+    pub arguments: Vec<Argument>,
+    pub accounts: Vec<DecodedInstructionAccount>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DecodedInstructionAccount {
+    pub name: String,
+    pub pubkey: String,
+    pub writable: bool,
+    pub signer: bool,
+}
+
 #[async_trait]
 pub trait LogParser: Send + Sync {
     async fn decode_log(
@@ -27,15 +55,85 @@ pub trait LogParser: Send + Sync {
         program_id: &str,
         log: &SolanaProgramLog,
     ) -> anyhow::Result<DecodedLog>;
+
+    /// Decodes an instruction's data and accounts. Returns `Ok(None)` when
+    /// the parser has no definition for the program.
+    /// `TransactionLogParser` uses the program's IDL, falling back to the
+    /// built-in System, SPL Token, Token-2022 and Associated Token decoders.
+    async fn decode_instruction(
+        &self,
+        _instruction: &InstructionWithContext,
+    ) -> anyhow::Result<Option<DecodedInstruction>> {
+        Ok(None)
+    }
+
+    /// Decodes the return data of the instruction with `instruction_data`
+    /// into its declared return type and that type's name. Returns `Ok(None)`
+    /// when the parser does not know the instruction's return type.
+    async fn decode_return_data(
+        &self,
+        _program_id: &str,
+        _instruction_data: &[u8],
+        _return_data: &[u8],
+    ) -> anyhow::Result<Option<(String, serde_json::Value)>> {
+        Ok(None)
+    }
+
+    /// Decodes the data of an account owned by `program_id` into its account
+    /// type's name and value. Returns `Ok(None)` when the parser has no
+    /// matching account definition.
+    async fn decode_account(
+        &self,
+        _program_id: &str,
+        _data: &[u8],
+    ) -> anyhow::Result<Option<(String, serde_json::Value)>> {
+        Ok(None)
+    }
+
+    /// Log line forms `decode_log` understands. `LogParserRegistry` only
+    /// routes matching logs to this parser.
+    fn handled_log_forms(&self) -> &[LogForm] {
+        LogForm::ALL
+    }
 }
 
 pub struct TransactionLogParser {
-    // For future use: program IDL store
+    idl_store: Arc<IdlStore>,
 }
 
 impl TransactionLogParser {
     pub fn new() -> Self {
-        Self {}
+        Self::with_idl_store(Arc::new(IdlStore::new()))
+    }
+
+    pub fn with_idl_store(idl_store: Arc<IdlStore>) -> Self {
+        Self { idl_store }
+    }
+
+    pub fn idl_store(&self) -> &Arc<IdlStore> {
+        &self.idl_store
+    }
+
+    /// Decodes an Anchor event (discriminator followed by Borsh-encoded
+    /// fields) into its name and arguments. Returns `Ok(None)` when the
+    /// program has no IDL or the IDL declares no event with that discriminator.
+    pub fn decode_event(
+        &self,
+        program_id: &str,
+        data: &[u8],
+    ) -> anyhow::Result<Option<(String, Vec<Argument>)>> {
+        let Some(idl) = self.idl_store.get_idl(program_id) else {
+            return Ok(None);
+        };
+        let Some(event) = idl.event_by_discriminator(data) else {
+            return Ok(None);
+        };
+
+        let fields = idl.event_fields(event)?;
+        let mut reader = BorshReader::new(&data[event.discriminator().len()..]);
+        let arguments = decode_fields(&idl, &fields, &mut reader)
+            .with_context(|| format!("Failed to decode event {}", event.name))?;
+        Ok(Some((event.name.clone(), arguments)))
     }
 }
 
@@ -49,7 +147,7 @@ impl Default for TransactionLogParser {
 impl LogParser for TransactionLogParser {
     async fn decode_log(
         &self,
-        _program_id: &str,
+        program_id: &str,
         log: &SolanaProgramLog,
     ) -> anyhow::Result<DecodedLog> {
         debug!(
@@ -63,16 +161,58 @@ impl LogParser for TransactionLogParser {
             event_name: String::new(),
             arguments: Vec::new(),
             output_data: HashMap::new(),
+            truncated: log.logs_truncated,
         };
 
         // Parse the log message to extract event information
         // Solana logs typically follow patterns like:
         // "Program <programId> invoke [1]"
         // "Program log: <message>"
+        // "Program data: <base64 Anchor event>"
+        // "Program <programId> consumed <n> of <m> compute units"
+        // "Program return: <programId> <base64 return data>"
         // "Program <programId> success"
 
         let log_msg = &log.log_message;
-        if log_msg.starts_with("Program log: ") {
+        let event = match log_msg.strip_prefix("Program data: ") {
+            Some(data) => {
+                let data = decode_program_data(data).context("Invalid Program data encoding")?;
+                self.decode_event(program_id, &data)?
+            }
+            None => None,
+        };
+
+        if let Some((event_name, arguments)) = event {
+            decoded_log.event_name = event_name;
+            decoded_log.output_data = arguments
+                .iter()
+                .map(|a| (a.name.clone(), a.value.clone()))
+                .collect();
+            decoded_log.arguments = arguments;
+        } else if let Some((_, units)) = parse_compute_units(log_msg) {
+            decoded_log.event_name = "ComputeUnits".to_string();
+            for (name, value) in [("consumed", units.consumed), ("limit", units.limit)] {
+                let value = serde_json::Value::String(value.to_string());
+                decoded_log.output_data.insert(name.to_string(), value.clone());
+                decoded_log.arguments.push(Argument {
+                    name: name.to_string(),
+                    arg_type: "u64".to_string(),
+                    value,
+                    indexed: false,
+                });
+            }
+        } else if let Some(return_data) = parse_return_data(log_msg) {
+            let (_, data) = return_data.context("Invalid Program return encoding")?;
+            let value = serde_json::Value::from(data);
+            decoded_log.event_name = "ReturnData".to_string();
+            decoded_log.output_data.insert("data".to_string(), value.clone());
+            decoded_log.arguments.push(Argument {
+                name: "data".to_string(),
+                arg_type: "bytes".to_string(),
+                value,
+                indexed: false,
+            });
+        } else if log_msg.starts_with("Program log: ") {
             let event_data = log_msg.strip_prefix("Program log: ").unwrap_or(log_msg);
             decoded_log.event_name = "ProgramLog".to_string();
             decoded_log.output_data.insert(
@@ -101,5 +241,375 @@ impl LogParser for TransactionLogParser {
 
         Ok(decoded_log)
     }
+
+    async fn decode_instruction(
+        &self,
+        instruction_with_context: &InstructionWithContext,
+    ) -> anyhow::Result<Option<DecodedInstruction>> {
+        let program_id = instruction_with_context.program_id.as_str();
+        let data = instruction_with_context.data.as_slice();
+        let Some(idl) = self.idl_store.get_idl(program_id) else {
+            return decode_native_instruction(
+                program_id,
+                data,
+                &instruction_with_context.account_keys(),
+            );
+        };
+
+        let instruction = idl.instruction_by_discriminator(data).with_context(|| {
+            format!("No IDL instruction matches discriminator for program {}", program_id)
+        })?;
+
+        let mut reader = BorshReader::new(&data[instruction.discriminator().len()..]);
+        let arguments = decode_fields(&idl, &instruction.args, &mut reader)
+            .with_context(|| format!("Failed to decode instruction {}", instruction.name))?;
+
+        let idl_accounts = instruction.flattened_accounts();
+        let accounts = instruction_with_context
+            .accounts
+            .iter()
+            .enumerate()
+            .map(|(i, account)| match idl_accounts.get(i) {
+                Some(idl_account) => DecodedInstructionAccount {
+                    name: idl_account.name.clone(),
+                    pubkey: account.pubkey.clone(),
+                    writable: idl_account.writable,
+                    signer: idl_account.signer,
+                },
+                None => DecodedInstructionAccount {
+                    name: format!("remaining_{}", i - idl_accounts.len()),
+                    pubkey: account.pubkey.clone(),
+                    writable: account.is_writable,
+                    signer: account.is_signer,
+                },
+            })
+            .collect();
+
+        Ok(Some(DecodedInstruction {
+            program_id: program_id.to_string(),
+            name: instruction.name.clone(),
+            arguments,
+            accounts,
+        }))
+    }
+
+    async fn decode_return_data(
+        &self,
+        program_id: &str,
+        instruction_data: &[u8],
+        return_data: &[u8],
+    ) -> anyhow::Result<Option<(String, serde_json::Value)>> {
+        let Some(idl) = self.idl_store.get_idl(program_id) else {
+            return Ok(None);
+        };
+        let Some(returns) = idl
+            .instruction_by_discriminator(instruction_data)
+            .and_then(|instruction| instruction.returns.as_ref())
+        else {
+            return Ok(None);
+        };
+
+        let value = decode_type(&idl, returns, &mut BorshReader::new(return_data))
+            .with_context(|| format!("Failed to decode return data as {}", returns))?;
+        Ok(Some((returns.to_string(), value)))
+    }
+
+    async fn decode_account(
+        &self,
+        program_id: &str,
+        data: &[u8],
+    ) -> anyhow::Result<Option<(String, serde_json::Value)>> {
+        match self.idl_store.get_idl(program_id) {
+            Some(idl) => decode_account(&idl, data),
+            None => Ok(None),
+        }
+    }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction_log_parser::idl::{sighash, Idl};
+
+    const PROGRAM_ID: &str = "Prog1111111111111111111111111111111111111111";
+
+    fn parser_with_idl(json: &str) -> TransactionLogParser {
+        let parser = TransactionLogParser::new();
+        parser
+            .idl_store()
+            .add_idl(PROGRAM_ID, Idl::from_json(json).expect("valid IDL"));
+        parser
+    }
+
+    #[tokio::test]
+    async fn decodes_legacy_idl_instruction_with_composite_accounts() {
+        let parser = parser_with_idl(
+            r#"{
+                "name": "pool",
+                "instructions": [{
+                    "name": "initializePool",
+                    "accounts": [
+                        {"name": "authority", "isMut": false, "isSigner": true},
+                        {"name": "vaults", "accounts": [
+                            {"name": "tokenA", "isMut": true, "isSigner": false}
+                        ]}
+                    ],
+                    "args": [
+                        {"name": "amount", "type": "u64"},
+                        {"name": "fee", "type": {"option": "u16"}},
+                        {"name": "side", "type": {"defined": "Side"}}
+                    ]
+                }],
+                "types": [{
+                    "name": "Side",
+                    "type": {"kind": "enum", "variants": [{"name": "Bid"}, {"name": "Ask"}]}
+                }]
+            }"#,
+        );
+
+        let mut data = sighash("global", "initialize_pool").to_vec();
+        data.extend_from_slice(&500u64.to_le_bytes());
+        data.extend_from_slice(&[1, 30, 0]);
+        data.push(1);
+        let accounts = vec!["A".to_string(), "B".to_string(), "C".to_string()];
+
+        let decoded = parser
+            .decode_instruction(&InstructionWithContext::bare(PROGRAM_ID, &data, &accounts))
+            .await
+            .unwrap()
+            .expect("program has an IDL");
+
+        assert_eq!(decoded.name, "initializePool");
+        let values: Vec<serde_json::Value> = decoded.arguments.iter().map(|a| a.value.clone()).collect();
+        assert_eq!(values, vec![serde_json::json!("500"), serde_json::json!(30), serde_json::json!("Ask")]);
+        assert_eq!(decoded.arguments[1].arg_type, "option<u16>");
+
+        let names: Vec<_> = decoded.accounts.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["authority", "vaults.tokenA", "remaining_0"]);
+        assert!(decoded.accounts[0].signer);
+        assert!(decoded.accounts[1].writable);
+    }
+
+    #[tokio::test]
+    async fn decodes_new_idl_instruction_with_declared_discriminator() {
+        let parser = parser_with_idl(
+            r#"{
+                "address": "Prog1111111111111111111111111111111111111111",
+                "metadata": {"name": "pool", "version": "0.1.0", "spec": "0.1.0"},
+                "instructions": [{
+                    "name": "swap",
+                    "discriminator": [9, 8, 7, 6, 5, 4, 3, 2],
+                    "accounts": [{"name": "user", "writable": true, "signer": true}],
+                    "args": [
+                        {"name": "memo", "type": "string"},
+                        {"name": "limit", "type": {"defined": {"name": "Limit"}}}
+                    ]
+                }],
+                "types": [{
+                    "name": "Limit",
+                    "type": {"kind": "struct", "fields": [{"name": "price", "type": "u128"}]}
+                }]
+            }"#,
+        );
+
+        let mut data = vec![9, 8, 7, 6, 5, 4, 3, 2];
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(b"hi");
+        data.extend_from_slice(&7u128.to_le_bytes());
+
+        let decoded = parser
+            .decode_instruction(&InstructionWithContext::bare(
+                PROGRAM_ID,
+                &data,
+                &["U".to_string()],
+            ))
+            .await
+            .unwrap()
+            .expect("program has an IDL");
+
+        assert_eq!(decoded.name, "swap");
+        assert_eq!(decoded.arguments[0].value, "hi");
+        assert_eq!(decoded.arguments[1].value, serde_json::json!({"price": "7"}));
+        assert!(decoded.accounts[0].writable && decoded.accounts[0].signer);
+    }
+
+    #[tokio::test]
+    async fn unknown_program_and_discriminator() {
+        let parser = parser_with_idl(r#"{"instructions": [{"name": "ping"}]}"#);
+
+        assert!(parser
+            .decode_instruction(&InstructionWithContext::bare("Other", &[0; 8], &[]))
+            .await
+            .unwrap()
+            .is_none());
+        assert!(parser
+            .decode_instruction(&InstructionWithContext::bare(PROGRAM_ID, &[0; 8], &[]))
+            .await
+            .is_err());
+    }
+
+    fn program_data_log(data: &[u8]) -> SolanaProgramLog {
+        use base64::Engine;
+        SolanaProgramLog {
+            program_id: PROGRAM_ID.to_string(),
+            log_index: 4,
+            signature: "sig".to_string(),
+            slot: 1,
+            block_time: None,
+            log_message: format!(
+                "Program data: {}",
+                base64::engine::general_purpose::STANDARD.encode(data)
+            ),
+            instruction_index: 0,
+            inner_instruction_index: Some(0),
+            depth: 2,
+            parent_program_id: Some(PROGRAM_ID.to_string()),
+            logs_truncated: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn decodes_program_data_events_from_legacy_and_new_idls() {
+        let legacy = parser_with_idl(
+            r#"{"events": [{"name": "Deposited", "fields": [{"name": "amount", "type": "u64", "index": false}]}]}"#,
+        );
+        let mut data = sighash("event", "Deposited").to_vec();
+        data.extend_from_slice(&42u64.to_le_bytes());
+
+        let decoded = legacy
+            .decode_log(PROGRAM_ID, &program_data_log(&data))
+            .await
+            .unwrap();
+        assert_eq!(decoded.event_name, "Deposited");
+        assert_eq!(decoded.output_data["amount"], serde_json::json!("42"));
+
+        let new = parser_with_idl(
+            r#"{
+                "events": [{"name": "Withdrawn", "discriminator": [1, 1, 1, 1, 1, 1, 1, 1]}],
+                "types": [{"name": "Withdrawn", "type": {"kind": "struct", "fields": [{"name": "ok", "type": "bool"}]}}]
+            }"#,
+        );
+        let decoded = new
+            .decode_log(PROGRAM_ID, &program_data_log(&[1, 1, 1, 1, 1, 1, 1, 1, 1]))
+            .await
+            .unwrap();
+        assert_eq!(decoded.event_name, "Withdrawn");
+        assert_eq!(decoded.arguments[0].value, serde_json::json!(true));
+
+        let unknown = new
+            .decode_log(PROGRAM_ID, &program_data_log(&[2; 8]))
+            .await
+            .unwrap();
+        assert_eq!(unknown.event_name, "Unknown");
+    }
+
+    #[tokio::test]
+    async fn decodes_compute_units_and_return_data() {
+        let parser = parser_with_idl(
+            r#"{"instructions": [{"name": "quote", "discriminator": [5, 5, 5, 5, 5, 5, 5, 5], "returns": {"option": "u64"}}]}"#,
+        );
+
+        let mut log = program_data_log(&[]);
+        log.log_message = format!("Program {} consumed 1200 of 200000 compute units", PROGRAM_ID);
+        let decoded = parser.decode_log(PROGRAM_ID, &log).await.unwrap();
+        assert_eq!(decoded.event_name, "ComputeUnits");
+        assert_eq!(decoded.output_data["consumed"], serde_json::json!("1200"));
+        assert_eq!(decoded.output_data["limit"], serde_json::json!("200000"));
+
+        log.log_message = format!("Program return: {} AQcAAAAAAAAA", PROGRAM_ID);
+        let decoded = parser.decode_log(PROGRAM_ID, &log).await.unwrap();
+        assert_eq!(decoded.event_name, "ReturnData");
+
+        let (_, data) = parse_return_data(&log.log_message).unwrap().unwrap();
+        let (value_type, value) = parser
+            .decode_return_data(PROGRAM_ID, &[5; 8], &data)
+            .await
+            .unwrap()
+            .expect("instruction declares a return type");
+        assert_eq!(value_type, "option<u64>");
+        assert_eq!(value, serde_json::json!("7"));
+
+        assert!(parser
+            .decode_return_data(PROGRAM_ID, &[6; 8], &data)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn decodes_shank_instructions_and_accounts() {
+        let parser = parser_with_idl(
+            r#"{
+                "version": "0.1.0",
+                "name": "vault",
+                "instructions": [{
+                    "name": "Deposit",
+                    "accounts": [
+                        {"name": "vault", "isMut": true, "isSigner": false},
+                        {"name": "payer", "isMut": true, "isSigner": true}
+                    ],
+                    "args": [{"name": "args", "type": {"defined": "DepositArgs"}}],
+                    "discriminant": {"type": "u8", "value": 1}
+                }],
+                "accounts": [{
+                    "name": "Vault",
+                    "type": {"kind": "struct", "fields": [
+                        {"name": "key", "type": {"defined": "Key"}},
+                        {"name": "owner", "type": "publicKey"},
+                        {"name": "limits", "type": {"tuple": ["u16", "u64"]}}
+                    ]}
+                }],
+                "types": [
+                    {"name": "DepositArgs", "type": {"kind": "struct", "fields": [
+                        {"name": "amount", "type": "u64"},
+                        {"name": "tags", "type": {"bTreeSet": "u8"}}
+                    ]}},
+                    {"name": "Key", "type": {"kind": "enum", "variants": [
+                        {"name": "Uninitialized"}, {"name": "Vault"}
+                    ]}}
+                ],
+                "metadata": {"origin": "shank", "address": "Prog1111111111111111111111111111111111111111"}
+            }"#,
+        );
+
+        let mut data = vec![1];
+        data.extend_from_slice(&3u64.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.push(9);
+        let decoded = parser
+            .decode_instruction(&InstructionWithContext::bare(
+                PROGRAM_ID,
+                &data,
+                &["V".to_string(), "P".to_string()],
+            ))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded.name, "Deposit");
+        assert_eq!(decoded.arguments[0].value, serde_json::json!({"amount": "3", "tags": [9]}));
+        assert!(decoded.accounts[1].signer);
+
+        // Shank accounts carry no discriminator and match by layout size.
+        let mut data = vec![1];
+        data.extend_from_slice(&[0; 32]);
+        data.extend_from_slice(&2u16.to_le_bytes());
+        data.extend_from_slice(&4u64.to_le_bytes());
+        let (name, value) = parser.decode_account(PROGRAM_ID, &data).await.unwrap().unwrap();
+        assert_eq!(name, "Vault");
+        assert_eq!(value["key"], serde_json::json!("Vault"));
+        assert_eq!(value["limits"], serde_json::json!([2, "4"]));
+        assert!(parser.decode_account(PROGRAM_ID, &data[1..]).await.unwrap().is_none());
+
+        // Two accounts of the same size cannot be told apart.
+        let mut idl = parser.idl_store().get_idl(PROGRAM_ID).unwrap().as_ref().clone();
+        let mut twin = idl.accounts[0].clone();
+        twin.name = "VaultCopy".to_string();
+        idl.accounts.push(twin);
+        let mut copy = idl.types.iter().find(|t| t.name == "Vault").unwrap().clone();
+        copy.name = "VaultCopy".to_string();
+        idl.types.push(copy);
+        parser.idl_store().add_idl(PROGRAM_ID, idl);
+        assert!(parser.decode_account(PROGRAM_ID, &data).await.is_err());
+    }
+}
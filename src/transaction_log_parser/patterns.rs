@@ -0,0 +1,392 @@
+use crate::clients::solana::SolanaProgramLog;
+use crate::transaction_log_parser::{Argument, DecodedLog, LogForm, LogParser};
+use anyhow::Context;
+use async_trait::async_trait;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Type a pattern capture is converted to. Named like the matching IDL types
+/// so `Argument::arg_type` is consistent across decoders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureType {
+    #[default]
+    String,
+    Bool,
+    U64,
+    I64,
+    U128,
+    I128,
+    F64,
+    Pubkey,
+}
+
+impl CaptureType {
+    fn from_name(name: &str) -> anyhow::Result<Self> {
+        serde_json::from_value(Value::String(name.to_string()))
+            .with_context(|| format!("Unsupported capture type: {}", name))
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            CaptureType::String => "string",
+            CaptureType::Bool => "bool",
+            CaptureType::U64 => "u64",
+            CaptureType::I64 => "i64",
+            CaptureType::U128 => "u128",
+            CaptureType::I128 => "i128",
+            CaptureType::F64 => "f64",
+            CaptureType::Pubkey => "pubkey",
+        }
+    }
+
+    /// Regex a template placeholder of this type expands to.
+    fn template_regex(self) -> &'static str {
+        match self {
+            CaptureType::String => ".+?",
+            CaptureType::Bool => "true|false",
+            CaptureType::U64 | CaptureType::U128 => r"\d+",
+            CaptureType::I64 | CaptureType::I128 => r"-?\d+",
+            CaptureType::F64 => r"-?\d+(?:\.\d+)?(?:[eE][-+]?\d+)?",
+            CaptureType::Pubkey => "[1-9A-HJ-NP-Za-km-z]{32,44}",
+        }
+    }
+
+    /// Integers of 64 bits and wider are rendered as decimal strings, like
+    /// every other decoder renders them.
+    fn parse(self, raw: &str) -> anyhow::Result<Value> {
+        Ok(match self {
+            CaptureType::String => Value::String(raw.to_string()),
+            CaptureType::Bool => Value::Bool(raw.parse()?),
+            CaptureType::U64 => Value::String(raw.parse::<u64>()?.to_string()),
+            CaptureType::I64 => Value::String(raw.parse::<i64>()?.to_string()),
+            CaptureType::U128 => Value::String(raw.parse::<u128>()?.to_string()),
+            CaptureType::I128 => Value::String(raw.parse::<i128>()?.to_string()),
+            CaptureType::F64 => Value::from(raw.parse::<f64>()?),
+            CaptureType::Pubkey => Value::String(Pubkey::from_str(raw)?.to_string()),
+        })
+    }
+}
+
+/// One declarative text-log pattern, as written in configuration.
+///
+/// Exactly one of `regex` or `template` must be set. Regex patterns take their
+/// argument names from named groups and their types from `captures`
+/// (defaulting to `string`). Templates are matched against the whole message
+/// and use `{name}` or `{name:type}` placeholders, with `{{` and `}}` for
+/// literal braces:
+///
+/// ```json
+/// {
+///   "program_id": "9W959DqEETiGZocYWCQPaJ6sBmUzgfxXfqGeTEdp3aQP",
+///   "event_name": "Swap",
+///   "template": "Instruction: Swap amount_in={amount_in:u64} amount_out={amount_out:u64}"
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct LogPatternConfig {
+    /// Restricts the pattern to one program; applies to every program if unset.
+    #[serde(default)]
+    pub program_id: Option<String>,
+    pub event_name: String,
+    #[serde(default)]
+    pub regex: Option<String>,
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub captures: HashMap<String, CaptureType>,
+}
+
+struct CompiledPattern {
+    program_id: Option<String>,
+    event_name: String,
+    regex: Regex,
+    /// Capture names in the order they appear in the pattern.
+    captures: Vec<(String, CaptureType)>,
+}
+
+impl CompiledPattern {
+    fn compile(config: &LogPatternConfig) -> anyhow::Result<Self> {
+        let (source, mut types) = match (&config.regex, &config.template) {
+            (Some(regex), None) => (regex.clone(), config.captures.clone()),
+            (None, Some(template)) => {
+                let (regex, mut types) = template_to_regex(template)?;
+                types.extend(config.captures.iter().map(|(k, v)| (k.clone(), *v)));
+                (regex, types)
+            }
+            _ => anyhow::bail!(
+                "Pattern for {} must set exactly one of regex or template",
+                config.event_name
+            ),
+        };
+
+        let regex = Regex::new(&source)
+            .with_context(|| format!("Invalid pattern for {}", config.event_name))?;
+        let captures = regex
+            .capture_names()
+            .flatten()
+            .map(|name| (name.to_string(), types.remove(name).unwrap_or_default()))
+            .collect();
+        if let Some(name) = types.keys().next() {
+            anyhow::bail!(
+                "Pattern for {} declares a type for unknown capture {}",
+                config.event_name,
+                name
+            );
+        }
+
+        Ok(Self {
+            program_id: config.program_id.clone(),
+            event_name: config.event_name.clone(),
+            regex,
+            captures,
+        })
+    }
+}
+
+/// Translates a format-string template into an anchored regex with one named
+/// group per placeholder.
+fn template_to_regex(template: &str) -> anyhow::Result<(String, HashMap<String, CaptureType>)> {
+    let mut regex = String::from("^");
+    let mut types = HashMap::new();
+    let mut rest = template;
+
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix("{{") {
+            regex.push_str(r"\{");
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix("}}") {
+            regex.push_str(r"\}");
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix('{') {
+            let end = tail
+                .find('}')
+                .with_context(|| format!("Unclosed placeholder in template: {}", template))?;
+            let (name, ty) = match tail[..end].split_once(':') {
+                Some((name, ty)) => (name, CaptureType::from_name(ty)?),
+                None => (&tail[..end], CaptureType::String),
+            };
+            anyhow::ensure!(
+                types.insert(name.to_string(), ty).is_none(),
+                "Duplicate placeholder {} in template: {}",
+                name,
+                template
+            );
+            regex.push_str(&format!("(?P<{}>{})", name, ty.template_regex()));
+            rest = &tail[end + 1..];
+        } else {
+            let end = rest.find(['{', '}']).unwrap_or(rest.len());
+            anyhow::ensure!(end > 0, "Unmatched }} in template: {}", template);
+            regex.push_str(&regex::escape(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+
+    regex.push('$');
+    Ok((regex, types))
+}
+
+/// Compiled set of `LogPatternConfig`s, tried in order.
+pub struct LogPatternSet {
+    patterns: Vec<CompiledPattern>,
+}
+
+impl LogPatternSet {
+    pub fn new(configs: &[LogPatternConfig]) -> anyhow::Result<Self> {
+        Ok(Self {
+            patterns: configs
+                .iter()
+                .map(CompiledPattern::compile)
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+
+    /// Loads patterns from a JSON array of `LogPatternConfig`s.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let configs: Vec<LogPatternConfig> =
+            serde_json::from_str(json).context("Failed to parse log patterns")?;
+        Self::new(&configs)
+    }
+
+    /// Matches `message` (without the `Program log: ` prefix) against the
+    /// patterns for `program_id`. Returns the first match's event name and
+    /// typed arguments, or `Ok(None)` if no pattern matches.
+    pub fn match_message(
+        &self,
+        program_id: &str,
+        message: &str,
+    ) -> anyhow::Result<Option<(String, Vec<Argument>)>> {
+        for pattern in self
+            .patterns
+            .iter()
+            .filter(|p| p.program_id.as_deref().is_none_or(|id| id == program_id))
+        {
+            let Some(captures) = pattern.regex.captures(message) else {
+                continue;
+            };
+
+            let arguments = pattern
+                .captures
+                .iter()
+                .filter_map(|(name, ty)| captures.name(name).map(|m| (name, ty, m.as_str())))
+                .map(|(name, ty, raw)| {
+                    Ok(Argument {
+                        name: name.clone(),
+                        arg_type: ty.as_str().to_string(),
+                        value: ty.parse(raw).with_context(|| {
+                            format!("Capture {} of {} is not a valid {}", name, pattern.event_name, ty.as_str())
+                        })?,
+                        indexed: false,
+                    })
+                })
+                .collect::<anyhow::Result<_>>()?;
+            return Ok(Some((pattern.event_name.clone(), arguments)));
+        }
+        Ok(None)
+    }
+}
+
+/// `LogParser` for text logs of programs without an IDL. Fails on lines no
+/// pattern matches, so a `LogParserRegistry` moves on to its next decoder.
+pub struct PatternLogParser {
+    patterns: LogPatternSet,
+}
+
+impl PatternLogParser {
+    pub fn new(patterns: LogPatternSet) -> Self {
+        Self { patterns }
+    }
+}
+
+#[async_trait]
+impl LogParser for PatternLogParser {
+    async fn decode_log(
+        &self,
+        program_id: &str,
+        log: &SolanaProgramLog,
+    ) -> anyhow::Result<DecodedLog> {
+        let message = log
+            .log_message
+            .strip_prefix("Program log: ")
+            .unwrap_or(&log.log_message);
+
+        let (event_name, arguments) = self
+            .patterns
+            .match_message(program_id, message)?
+            .with_context(|| format!("No log pattern matches: {}", log.log_message))?;
+
+        Ok(DecodedLog {
+            log_index: log.log_index,
+            address: log.program_id.clone(),
+            output_data: arguments
+                .iter()
+                .map(|a| (a.name.clone(), a.value.clone()))
+                .collect(),
+            arguments,
+            event_name,
+            truncated: log.logs_truncated,
+        })
+    }
+
+    fn handled_log_forms(&self) -> &[LogForm] {
+        &[LogForm::Text]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATTERNS: &str = r#"[
+        {
+            "program_id": "Amm",
+            "event_name": "Swap",
+            "template": "Instruction: Swap amount_in={amount_in:u64} amount_out={amount_out:u128} user={user:pubkey}"
+        },
+        {
+            "event_name": "Price",
+            "regex": "^price (?P<pair>\\w+/\\w+) = (?P<price>-?[0-9.]+)$",
+            "captures": {"price": "f64"}
+        },
+        {
+            "event_name": "Braced",
+            "template": "{{ratio}} {value:i64}"
+        }
+    ]"#;
+
+    fn log(program_id: &str, message: &str) -> SolanaProgramLog {
+        SolanaProgramLog {
+            program_id: program_id.to_string(),
+            log_index: 3,
+            signature: "sig".to_string(),
+            slot: 1,
+            block_time: None,
+            log_message: format!("Program log: {}", message),
+            instruction_index: 0,
+            inner_instruction_index: None,
+            depth: 1,
+            parent_program_id: None,
+            logs_truncated: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn decodes_template_and_regex_patterns() {
+        let parser = PatternLogParser::new(LogPatternSet::from_json(PATTERNS).unwrap());
+        let user = Pubkey::new_from_array([3; 32]).to_string();
+
+        let decoded = parser
+            .decode_log(
+                "Amm",
+                &log("Amm", &format!("Instruction: Swap amount_in=10 amount_out=20 user={}", user)),
+            )
+            .await
+            .unwrap();
+        assert_eq!(decoded.event_name, "Swap");
+        assert_eq!(decoded.output_data["amount_in"], serde_json::json!("10"));
+        assert_eq!(decoded.output_data["amount_out"], serde_json::json!("20"));
+        assert_eq!(decoded.arguments[2].arg_type, "pubkey");
+        assert_eq!(decoded.arguments[2].value, serde_json::json!(user));
+
+        let decoded = parser
+            .decode_log("Oracle", &log("Oracle", "price SOL/USD = 151.25"))
+            .await
+            .unwrap();
+        assert_eq!(decoded.event_name, "Price");
+        assert_eq!(decoded.output_data["pair"], serde_json::json!("SOL/USD"));
+        assert_eq!(decoded.output_data["price"], serde_json::json!(151.25));
+
+        let decoded = parser.decode_log("X", &log("X", "{ratio} -4")).await.unwrap();
+        assert_eq!(decoded.output_data["value"], serde_json::json!("-4"));
+    }
+
+    #[tokio::test]
+    async fn rejects_unmatched_lines_and_bad_captures() {
+        let parser = PatternLogParser::new(LogPatternSet::from_json(PATTERNS).unwrap());
+
+        // Restricted to the Amm program.
+        assert!(parser
+            .decode_log("Other", &log("Other", "Instruction: Swap amount_in=1 amount_out=2 user=x"))
+            .await
+            .is_err());
+        assert!(parser
+            .decode_log("Oracle", &log("Oracle", "price SOL/USD = 1.2.3"))
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_configs() {
+        assert!(LogPatternSet::from_json(r#"[{"event_name": "A"}]"#).is_err());
+        assert!(LogPatternSet::from_json(r#"[{"event_name": "A", "template": "{a:u7}"}]"#).is_err());
+        assert!(LogPatternSet::from_json(r#"[{"event_name": "A", "template": "{a} {a}"}]"#).is_err());
+        assert!(LogPatternSet::from_json(
+            r#"[{"event_name": "A", "regex": "(?P<a>x)", "captures": {"b": "u64"}}]"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use crate::clients::solana::SolanaProgramLog;
+use crate::transaction_log_parser::{DecodedInstruction, DecodedLog, InstructionWithContext, LogParser};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Shape of a program log line, used to route it to decoders that declare
+/// support for it through `LogParser::handled_log_forms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LogForm {
+    /// `Program log: ` lines and any other text written by the program.
+    Text,
+    /// `Program data: ` lines carrying base64 event payloads.
+    ProgramData,
+    /// `Program return: ` lines carrying base64 return data.
+    ReturnData,
+}
+
+impl LogForm {
+    pub const ALL: &'static [LogForm] = &[LogForm::Text, LogForm::ProgramData, LogForm::ReturnData];
+
+    pub fn of(log_message: &str) -> Self {
+        if log_message.starts_with("Program data: ") {
+            LogForm::ProgramData
+        } else if log_message.starts_with("Program return: ") {
+            LogForm::ReturnData
+        } else {
+            LogForm::Text
+        }
+    }
+}
+
+impl fmt::Display for LogForm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogForm::Text => write!(f, "text"),
+            LogForm::ProgramData => write!(f, "program data"),
+            LogForm::ReturnData => write!(f, "return data"),
+        }
+    }
+}
+
+/// `LogParser` that routes each program to the decoders registered for it,
+/// then to the fallback decoders, in registration order.
+///
+/// A log goes to the first decoder in the chain that handles its `LogForm`
+/// and decodes it without error. An instruction goes to the first decoder
+/// that returns `Some`, and so do accounts and return data. When every
+/// candidate fails, the first error is returned. Register a
+/// `TransactionLogParser` as the last fallback to keep IDL and native
+/// decoding for programs without a dedicated decoder.
+#[derive(Default)]
+pub struct LogParserRegistry {
+    decoders: HashMap<String, Vec<Arc<dyn LogParser>>>,
+    fallbacks: Vec<Arc<dyn LogParser>>,
+}
+
+impl LogParserRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, program_id: &str, decoder: Arc<dyn LogParser>) {
+        self.decoders
+            .entry(program_id.to_string())
+            .or_default()
+            .push(decoder);
+    }
+
+    pub fn register_fallback(&mut self, decoder: Arc<dyn LogParser>) {
+        self.fallbacks.push(decoder);
+    }
+
+    /// Decoders tried for `program_id`, most specific first.
+    pub fn decoders_for(&self, program_id: &str) -> impl Iterator<Item = &Arc<dyn LogParser>> {
+        self.decoders
+            .get(program_id)
+            .into_iter()
+            .flatten()
+            .chain(self.fallbacks.iter())
+    }
+}
+
+#[async_trait]
+impl LogParser for LogParserRegistry {
+    async fn decode_log(
+        &self,
+        program_id: &str,
+        log: &SolanaProgramLog,
+    ) -> anyhow::Result<DecodedLog> {
+        let form = LogForm::of(&log.log_message);
+        let mut first_error = None;
+
+        for decoder in self
+            .decoders_for(program_id)
+            .filter(|d| d.handled_log_forms().contains(&form))
+        {
+            match decoder.decode_log(program_id, log).await {
+                Ok(decoded) => return Ok(decoded),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        Err(first_error.unwrap_or_else(|| {
+            anyhow::anyhow!("No decoder for program {} handles {} logs", program_id, form)
+        }))
+    }
+
+    async fn decode_instruction(
+        &self,
+        instruction: &InstructionWithContext,
+    ) -> anyhow::Result<Option<DecodedInstruction>> {
+        let mut first_error = None;
+
+        for decoder in self.decoders_for(&instruction.program_id) {
+            match decoder.decode_instruction(instruction).await {
+                Ok(Some(decoded)) => return Ok(Some(decoded)),
+                Ok(None) => {}
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(None),
+        }
+    }
+
+    async fn decode_return_data(
+        &self,
+        program_id: &str,
+        instruction_data: &[u8],
+        return_data: &[u8],
+    ) -> anyhow::Result<Option<(String, serde_json::Value)>> {
+        let mut first_error = None;
+
+        for decoder in self.decoders_for(program_id) {
+            match decoder
+                .decode_return_data(program_id, instruction_data, return_data)
+                .await
+            {
+                Ok(Some(decoded)) => return Ok(Some(decoded)),
+                Ok(None) => {}
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(None),
+        }
+    }
+
+    async fn decode_account(
+        &self,
+        program_id: &str,
+        data: &[u8],
+    ) -> anyhow::Result<Option<(String, serde_json::Value)>> {
+        let mut first_error = None;
+
+        for decoder in self.decoders_for(program_id) {
+            match decoder.decode_account(program_id, data).await {
+                Ok(Some(decoded)) => return Ok(Some(decoded)),
+                Ok(None) => {}
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction_log_parser::native::TOKEN_PROGRAM_ID;
+    use crate::transaction_log_parser::TransactionLogParser;
+
+    /// Decodes `Program log: ` text lines as `event_name`, failing on `fail`.
+    struct TextDecoder {
+        event_name: &'static str,
+    }
+
+    #[async_trait]
+    impl LogParser for TextDecoder {
+        async fn decode_log(
+            &self,
+            program_id: &str,
+            log: &SolanaProgramLog,
+        ) -> anyhow::Result<DecodedLog> {
+            anyhow::ensure!(!log.log_message.ends_with("fail"), "cannot decode");
+            Ok(DecodedLog {
+                log_index: log.log_index,
+                address: program_id.to_string(),
+                arguments: Vec::new(),
+                event_name: self.event_name.to_string(),
+                output_data: HashMap::new(),
+                truncated: false,
+            })
+        }
+
+        fn handled_log_forms(&self) -> &[LogForm] {
+            &[LogForm::Text]
+        }
+    }
+
+    fn log(program_id: &str, message: &str) -> SolanaProgramLog {
+        SolanaProgramLog {
+            program_id: program_id.to_string(),
+            log_index: 0,
+            signature: "sig".to_string(),
+            slot: 1,
+            block_time: None,
+            log_message: message.to_string(),
+            instruction_index: 0,
+            inner_instruction_index: None,
+            depth: 1,
+            parent_program_id: None,
+            logs_truncated: false,
+        }
+    }
+
+    fn registry() -> LogParserRegistry {
+        let mut registry = LogParserRegistry::new();
+        registry.register("A", Arc::new(TextDecoder { event_name: "Custom" }));
+        registry.register_fallback(Arc::new(TransactionLogParser::new()));
+        registry
+    }
+
+    #[tokio::test]
+    async fn routes_by_program_and_log_form() {
+        let registry = registry();
+
+        let decoded = registry.decode_log("A", &log("A", "Program log: hi")).await.unwrap();
+        assert_eq!(decoded.event_name, "Custom");
+
+        let decoded = registry.decode_log("B", &log("B", "Program log: hi")).await.unwrap();
+        assert_eq!(decoded.event_name, "ProgramLog");
+
+        // The custom decoder does not declare Program data support.
+        let decoded = registry
+            .decode_log("A", &log("A", "Program data: AQID"))
+            .await
+            .unwrap();
+        assert_eq!(decoded.event_name, "Unknown");
+    }
+
+    #[tokio::test]
+    async fn falls_back_when_a_decoder_fails() {
+        let decoded = registry()
+            .decode_log("A", &log("A", "Program log: fail"))
+            .await
+            .unwrap();
+        assert_eq!(decoded.event_name, "ProgramLog");
+
+        let mut strict = LogParserRegistry::new();
+        strict.register("A", Arc::new(TextDecoder { event_name: "Custom" }));
+        assert!(strict.decode_log("A", &log("A", "Program log: fail")).await.is_err());
+        assert!(strict.decode_log("B", &log("B", "Program log: hi")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn routes_instructions_through_fallbacks() {
+        let registry = registry();
+
+        let mut data = vec![3];
+        data.extend_from_slice(&10u64.to_le_bytes());
+        let decoded = registry
+            .decode_instruction(&InstructionWithContext::bare(TOKEN_PROGRAM_ID, &data, &[]))
+            .await
+            .unwrap()
+            .expect("native decoder handles SPL Token");
+        assert_eq!(decoded.name, "transfer");
+
+        let unknown = InstructionWithContext::bare("A", &[1], &[]);
+        assert!(registry.decode_instruction(&unknown).await.unwrap().is_none());
+    }
+}